@@ -0,0 +1,442 @@
+use super::core::SudokuGrid;
+use super::solver::{SolverResult, SolverStats, SudokuSolver};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// BatchStatus is what became of one puzzle of a batch.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BatchStatus {
+  Solved,
+  Unsolvable,
+  // The puzzle has more than one solution; the first one was kept.
+  MultipleSolutions,
+  // The solver gave up before deciding.
+  NodeLimitReached,
+}
+
+// BatchOutcome is the result of solving one puzzle of a batch.
+#[derive(Clone, Debug)]
+pub struct BatchOutcome {
+  pub status: BatchStatus,
+  // The filled grid, for Solved and MultipleSolutions.
+  pub solution: Option<SudokuGrid>,
+  // The work done to find the solution and check it is unique.
+  pub stats: SolverStats,
+  pub duration: Duration,
+}
+
+impl BatchOutcome {
+  // `solve` solves a single puzzle the way the batch does.
+  pub fn solve(
+    puzzle: &SudokuGrid,
+    max_nodes: Option<u64>,
+  ) -> BatchOutcome {
+    let start = Instant::now();
+    let given_choices = puzzle.filled_choices();
+    let mut solver = match SudokuSolver::new(&given_choices) {
+      None => {
+        return BatchOutcome {
+          status: BatchStatus::Unsolvable,
+          solution: None,
+          stats: SolverStats::default(),
+          duration: start.elapsed(),
+        }
+      }
+      Some(solver) => solver,
+    };
+    if let Some(max_nodes) = max_nodes {
+      solver = solver.with_node_limit(max_nodes);
+    }
+
+    let (status, solution) = match solver.next_solution() {
+      SolverResult::NoMoreSolutions => (BatchStatus::Unsolvable, None),
+      SolverResult::NodeLimitReached => {
+        (BatchStatus::NodeLimitReached, None)
+      }
+      SolverResult::Solved(solution_choices) => {
+        let mut solution = puzzle.clone();
+        for choice in solution_choices {
+          solution.place(choice);
+        }
+        // Hitting the node limit while looking for a second solution
+        // leaves the question open.
+        let status = match solver.next_solution() {
+          SolverResult::Solved(_) => BatchStatus::MultipleSolutions,
+          SolverResult::NoMoreSolutions => BatchStatus::Solved,
+          SolverResult::NodeLimitReached => {
+            BatchStatus::NodeLimitReached
+          }
+        };
+        (status, Some(solution))
+      }
+    };
+
+    BatchOutcome {
+      status,
+      solution,
+      stats: solver.stats(),
+      duration: start.elapsed(),
+    }
+  }
+}
+
+// BatchSolver solves a stream of puzzles on several threads. Results
+// are handed back in input order, as soon as all the puzzles before
+// them are done, so that output can be streamed too.
+//
+//   BatchSolver::new().solve_all(
+//     |feeder| {
+//       for (name, grid) in puzzles {
+//         feeder.push(name, grid);
+//       }
+//     },
+//     |name, outcome| println!("{}: {:?}", name, outcome.status),
+//   );
+//
+// Each puzzle travels with a tag of the caller's choosing (a name, a
+// line number...), which comes back with its outcome.
+pub struct BatchSolver {
+  num_threads: usize,
+  max_nodes: Option<u64>,
+}
+
+impl Default for BatchSolver {
+  fn default() -> BatchSolver {
+    let num_threads =
+      thread::available_parallelism().map_or(1, |num| num.get());
+
+    BatchSolver {
+      num_threads,
+      max_nodes: None,
+    }
+  }
+}
+
+impl BatchSolver {
+  // `new` uses one thread per core.
+  pub fn new() -> BatchSolver {
+    BatchSolver::default()
+  }
+
+  pub fn with_threads(mut self, num_threads: usize) -> BatchSolver {
+    self.num_threads = num_threads.max(1);
+    self
+  }
+
+  // `with_node_limit` bounds the solver on each puzzle.
+  pub fn with_node_limit(mut self, max_nodes: u64) -> BatchSolver {
+    self.max_nodes = Some(max_nodes);
+    self
+  }
+
+  // `solve_all` calls `produce`, which pushes puzzles into the feeder,
+  // and hands each outcome to `consume`, in the order the puzzles were
+  // pushed. `consume` is called on the calling thread, while `produce`
+  // is running and after it returns, until every puzzle is done.
+  pub fn solve_all<T, P, C>(&self, produce: P, consume: C)
+  where
+    T: Send,
+    P: FnOnce(&mut BatchFeeder<T, C>),
+    C: FnMut(T, BatchOutcome),
+  {
+    // Bounding the queue keeps a fast producer from reading the whole
+    // input into memory.
+    let (job_sender, job_receiver) =
+      mpsc::sync_channel::<(usize, T, SudokuGrid)>(
+        self.num_threads * 4,
+      );
+    let job_receiver = Arc::new(Mutex::new(job_receiver));
+    let (outcome_sender, outcome_receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+      for _ in 0..self.num_threads {
+        let job_receiver = Arc::clone(&job_receiver);
+        let outcome_sender: Sender<(usize, T, BatchOutcome)> =
+          outcome_sender.clone();
+        let max_nodes = self.max_nodes;
+        scope.spawn(move || loop {
+          // The lock is released as soon as a job is taken.
+          let job = job_receiver.lock().unwrap().recv();
+          let (idx, tag, puzzle) = match job {
+            Ok(job) => job,
+            Err(_) => return,
+          };
+          let outcome = BatchOutcome::solve(&puzzle, max_nodes);
+          if outcome_sender.send((idx, tag, outcome)).is_err() {
+            return;
+          }
+        });
+      }
+      drop(outcome_sender);
+
+      let mut feeder = BatchFeeder {
+        job_sender: Some(job_sender),
+        outcome_receiver,
+        pending: BTreeMap::new(),
+        num_pushed: 0,
+        num_consumed: 0,
+        consume,
+      };
+      produce(&mut feeder);
+      feeder.finish();
+    });
+  }
+}
+
+// BatchFeeder takes puzzles for a BatchSolver, and passes finished
+// outcomes on to the consumer as it goes.
+pub struct BatchFeeder<T, C> {
+  // Dropped once the producer is done, so that the workers stop.
+  job_sender: Option<SyncSender<(usize, T, SudokuGrid)>>,
+  outcome_receiver: Receiver<(usize, T, BatchOutcome)>,
+  // Outcomes that are done, waiting for earlier ones.
+  pending: BTreeMap<usize, (T, BatchOutcome)>,
+  num_pushed: usize,
+  num_consumed: usize,
+  consume: C,
+}
+
+impl<T, C: FnMut(T, BatchOutcome)> BatchFeeder<T, C> {
+  pub fn push(&mut self, tag: T, puzzle: SudokuGrid) {
+    let job = (self.num_pushed, tag, puzzle);
+    self.num_pushed += 1;
+
+    // While the queue is full, we may as well consume outcomes.
+    let mut job = Some(job);
+    while let Some(next_job) = job.take() {
+      let job_sender = self.job_sender.as_ref().unwrap();
+      match job_sender.try_send(next_job) {
+        Ok(()) => {}
+        Err(mpsc::TrySendError::Full(next_job)) => {
+          job = Some(next_job);
+          self.wait_for_outcome();
+        }
+        Err(mpsc::TrySendError::Disconnected(_)) => {
+          panic!("batch workers stopped early")
+        }
+      }
+    }
+    self.consume_ready();
+  }
+
+  fn finish(mut self) {
+    self.job_sender = None;
+    while self.num_consumed < self.num_pushed {
+      self.wait_for_outcome();
+    }
+  }
+
+  // `wait_for_outcome` blocks until some worker finishes a puzzle.
+  fn wait_for_outcome(&mut self) {
+    let (idx, tag, outcome) = self
+      .outcome_receiver
+      .recv()
+      .expect("batch workers stopped early");
+    self.pending.insert(idx, (tag, outcome));
+    self.consume_ready();
+  }
+
+  fn consume_ready(&mut self) {
+    while let Ok((idx, tag, outcome)) = self.outcome_receiver.try_recv()
+    {
+      self.pending.insert(idx, (tag, outcome));
+    }
+    while let Some((tag, outcome)) =
+      self.pending.remove(&self.num_consumed)
+    {
+      (self.consume)(tag, outcome);
+      self.num_consumed += 1;
+    }
+  }
+}
+
+// BatchSummary tallies the outcomes of a batch.
+#[derive(Clone, Debug, Default)]
+pub struct BatchSummary {
+  pub num_solved: usize,
+  pub num_unsolvable: usize,
+  pub num_multiple_solutions: usize,
+  pub num_node_limit_reached: usize,
+  durations: Vec<Duration>,
+  node_counts: Vec<u64>,
+}
+
+impl BatchSummary {
+  pub fn new() -> BatchSummary {
+    BatchSummary::default()
+  }
+
+  pub fn add(&mut self, outcome: &BatchOutcome) {
+    match outcome.status {
+      BatchStatus::Solved => self.num_solved += 1,
+      BatchStatus::Unsolvable => self.num_unsolvable += 1,
+      BatchStatus::MultipleSolutions => {
+        self.num_multiple_solutions += 1
+      }
+      BatchStatus::NodeLimitReached => self.num_node_limit_reached += 1,
+    }
+    self.durations.push(outcome.duration);
+    self.node_counts.push(outcome.stats.num_nodes);
+  }
+
+  pub fn num_puzzles(&self) -> usize {
+    self.durations.len()
+  }
+
+  // `duration_percentile` is the solve time that `percent` percent of
+  // the puzzles took at most. Returns None for an empty batch.
+  pub fn duration_percentile(&self, percent: f64) -> Option<Duration> {
+    percentile(&self.durations, percent)
+  }
+
+  // `nodes_percentile` is like `duration_percentile`, for the number of
+  // solver choices.
+  pub fn nodes_percentile(&self, percent: f64) -> Option<u64> {
+    percentile(&self.node_counts, percent)
+  }
+}
+
+// `Display` writes a short report:
+//
+//   puzzles: 1000, solved: 998, unsolvable: 1, multiple solutions: 1,
+//   node limit reached: 0
+//   time:  p50 1.2ms p90 3.4ms p99 10.1ms max 52.0ms
+//   nodes: p50 270 p90 812 p99 3301 max 20017
+impl fmt::Display for BatchSummary {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(
+      f,
+      "puzzles: {}, solved: {}, unsolvable: {}, multiple solutions: {},\n\
+       node limit reached: {}",
+      self.num_puzzles(),
+      self.num_solved,
+      self.num_unsolvable,
+      self.num_multiple_solutions,
+      self.num_node_limit_reached
+    )?;
+    if self.num_puzzles() == 0 {
+      return Ok(());
+    }
+
+    write!(f, "time: ")?;
+    for &(label, percent) in PERCENTILES {
+      let duration = self.duration_percentile(percent).unwrap();
+      let millis = duration.as_secs_f64() * 1000.0;
+      write!(f, " {} {:.1}ms", label, millis)?;
+    }
+    write!(f, "\nnodes:")?;
+    for &(label, percent) in PERCENTILES {
+      let num_nodes = self.nodes_percentile(percent).unwrap();
+      write!(f, " {} {}", label, num_nodes)?;
+    }
+    writeln!(f)
+  }
+}
+
+const PERCENTILES: &[(&str, f64)] =
+  &[("p50", 50.0), ("p90", 90.0), ("p99", 99.0), ("max", 100.0)];
+
+// `percentile` uses the nearest-rank method.
+fn percentile<V: Copy + Ord>(values: &[V], percent: f64) -> Option<V> {
+  if values.is_empty() {
+    return None;
+  }
+
+  let mut sorted_values = values.to_vec();
+  sorted_values.sort();
+  let rank = (percent / 100.0 * values.len() as f64).ceil() as usize;
+  Some(sorted_values[rank.clamp(1, values.len()) - 1])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::{SudokuChoice, SudokuPosition, SudokuValue};
+  use test_util::{puzzle_of, seeded_solution};
+
+  // `clashing_grid` has two 1s in its top row.
+  fn clashing_grid() -> SudokuGrid {
+    let choices: Vec<_> = (0..2)
+      .map(|col_idx| {
+        SudokuChoice::new(
+          SudokuPosition::new(0, col_idx),
+          SudokuValue::first(),
+        )
+      })
+      .collect();
+    SudokuGrid::from_choices(&choices)
+  }
+
+  #[test]
+  fn outcomes_tell_the_puzzles_apart() {
+    let solution = seeded_solution(1);
+    let outcome = BatchOutcome::solve(&puzzle_of(&solution), None);
+    assert_eq!(outcome.status, BatchStatus::Solved);
+    assert_eq!(outcome.solution, Some(solution));
+
+    let outcome = BatchOutcome::solve(&SudokuGrid::default(), None);
+    assert_eq!(outcome.status, BatchStatus::MultipleSolutions);
+    assert!(outcome.solution.is_some());
+
+    let outcome = BatchOutcome::solve(&clashing_grid(), None);
+    assert_eq!(outcome.status, BatchStatus::Unsolvable);
+    assert_eq!(outcome.solution, None);
+
+    let outcome = BatchOutcome::solve(&SudokuGrid::default(), Some(1));
+    assert_eq!(outcome.status, BatchStatus::NodeLimitReached);
+  }
+
+  #[test]
+  fn outcomes_come_back_in_input_order() {
+    // The puzzles take very different times to solve, so workers
+    // finish out of order, and there are more than the queue holds, so
+    // that pushing has to wait for outcomes.
+    let puzzles: Vec<SudokuGrid> = (0..40)
+      .map(|idx| match idx % 4 {
+        0 => SudokuGrid::default(),
+        1 => clashing_grid(),
+        _ => puzzle_of(&seeded_solution(idx)),
+      })
+      .collect();
+
+    let mut tags = vec![];
+    let mut summary = BatchSummary::new();
+    BatchSolver::new().with_threads(4).solve_all(
+      |feeder| {
+        for (idx, puzzle) in puzzles.iter().enumerate() {
+          feeder.push(idx, puzzle.clone());
+        }
+      },
+      |idx, outcome| {
+        let expected_status = match idx % 4 {
+          0 => BatchStatus::MultipleSolutions,
+          1 => BatchStatus::Unsolvable,
+          _ => BatchStatus::Solved,
+        };
+        assert_eq!(outcome.status, expected_status);
+        tags.push(idx);
+        summary.add(&outcome);
+      },
+    );
+
+    assert_eq!(tags, (0..puzzles.len()).collect::<Vec<_>>());
+    assert_eq!(summary.num_puzzles(), 40);
+    assert_eq!(summary.num_solved, 20);
+    assert_eq!(summary.num_unsolvable, 10);
+    assert_eq!(summary.num_multiple_solutions, 10);
+  }
+
+  #[test]
+  fn percentiles_use_the_nearest_rank() {
+    let values: Vec<u64> = (1..=10).rev().collect();
+    assert_eq!(percentile(&values, 50.0), Some(5));
+    assert_eq!(percentile(&values, 90.0), Some(9));
+    assert_eq!(percentile(&values, 99.0), Some(10));
+    assert_eq!(percentile(&values, 0.0), Some(1));
+    assert_eq!(percentile::<u64>(&[], 50.0), None);
+  }
+}
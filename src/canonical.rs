@@ -0,0 +1,701 @@
+use super::core::{
+  SudokuCell, SudokuGrid, SudokuPosition, SudokuValue,
+  SUDOKU_DIM_SQRT_USIZE, SUDOKU_DIM_USIZE,
+};
+use super::transform::SudokuTransform;
+
+// Two grids are "the same" if one can be turned into the other by
+// relabeling the values, reordering the rows within a band, reordering
+// the bands, doing the same for columns and stacks, and transposing.
+//
+// The canonical form of a grid is the smallest grid it can be turned
+// into, where grids are compared cell by cell in left-to-right,
+// top-to-bottom order, and an empty cell counts as smaller than any
+// value. Two grids are the same exactly when their canonical forms are
+// equal, which makes the canonical form handy for deduplicating large
+// collections of puzzles.
+
+// `canonical_form` returns the canonical form of the grid.
+pub fn canonical_form(grid: &SudokuGrid) -> SudokuGrid {
+  canonical_transform(grid).apply_to_grid(grid)
+}
+
+// `canonical_transform` returns a transform which turns the grid into
+// its canonical form. If the grid has symmetries, more than one
+// transform would do; we return the first one we find.
+pub fn canonical_transform(grid: &SudokuGrid) -> SudokuTransform {
+  let mut search = CanonicalSearch::new(grid);
+  search.run();
+  search.best_transform()
+}
+
+// `are_isomorphic` checks whether the two grids are the same. If they
+// are, it returns a transform which turns `grid` into `other_grid`.
+pub fn are_isomorphic(
+  grid: &SudokuGrid,
+  other_grid: &SudokuGrid,
+) -> Option<SudokuTransform> {
+  // Cheap check first: transforms never change the number of clues.
+  if grid.filled_choices().len() != other_grid.filled_choices().len() {
+    return None;
+  }
+
+  let transform = canonical_transform(grid);
+  let other_transform = canonical_transform(other_grid);
+  if transform.apply_to_grid(grid)
+    != other_transform.apply_to_grid(other_grid)
+  {
+    return None;
+  }
+
+  // Go to the canonical form, then back out to the other grid.
+  Some(transform.then(other_transform.inverse()))
+}
+
+// Cells of the search grids are stored as u8s: zero for an empty cell,
+// and otherwise the value.
+type RawGrid = [[u8; SUDOKU_DIM_USIZE]; SUDOKU_DIM_USIZE];
+
+// Marks a value with no linked column in `SearchState::linked_cols`.
+const NO_COL: u8 = u8::MAX;
+
+// CanonicalSearch builds the smallest transformed grid one cell at a
+// time, in left-to-right, top-to-bottom order. It keeps every partial
+// transform which makes the smallest cells so far (there is more than
+// one when the grid has symmetries, or when choices can't be told apart
+// yet), and drops the others at the first cell they lose on.
+//
+// Values are labeled in order of first appearance, since that is always
+// the smallest relabeling for a given arrangement of the rows and
+// columns.
+//
+// Choices are put off until a cell depends on them:
+//
+// * Columns which have held the same values in every row placed so far
+//   form a block, whose order is only picked once a later row tells the
+//   columns apart.
+// * When new values first appear in a block, every order of them makes
+//   the same row, so the block becomes "linked": each value's label is
+//   tied to wherever its column ends up. The order is settled one
+//   column at a time, when a later row needs a value's label (it takes
+//   the smallest label left in the block), or gets to one of the
+//   block's columns.
+//
+// Without linked blocks, a solution grid would need every order of the
+// values of its first row to be tried. With them, puzzles and solution
+// grids alike take milliseconds.
+struct CanonicalSearch {
+  // `raw_grids[0]` is the original grid; `raw_grids[1]` is its
+  // transpose.
+  raw_grids: [RawGrid; 2],
+  // The cells of the smallest grid found so far.
+  result: RawGrid,
+  // The partial transforms which make `result`, up to the current cell.
+  states: Vec<SearchState>,
+}
+
+#[derive(Clone)]
+struct SearchState {
+  // Index into `raw_grids`.
+  raw_grid_idx: usize,
+  // Row `i` of the result is row `row_order[i]` of the raw grid.
+  row_order: [u8; SUDOKU_DIM_USIZE],
+  used_rows: [bool; SUDOKU_DIM_USIZE],
+  // Column `j` of the result is column `col_order[j]` of the raw grid.
+  // Within a block the order is only tentative.
+  col_order: [u8; SUDOKU_DIM_USIZE],
+  // `col_positions[c]` is the index of raw column `c` in `col_order`.
+  col_positions: [u8; SUDOKU_DIM_USIZE],
+  // `is_block_start[j]` is set when column `j` of the result starts a
+  // new block.
+  is_block_start: [bool; SUDOKU_DIM_USIZE],
+  // For the first column of a linked block, the label of its first
+  // column; zero for other blocks.
+  block_labels: [u8; SUDOKU_DIM_USIZE],
+  // `is_pinned[j]` is set once the column at `j` of a linked block is
+  // settled.
+  is_pinned: [bool; SUDOKU_DIM_USIZE],
+  // `labels[v]` is the value written for raw value `v`, or zero if `v`
+  // has no label yet.
+  labels: [u8; SUDOKU_DIM_USIZE + 1],
+  next_label: u8,
+  // For a value whose label waits on a linked block, `linked_cols[v]`
+  // is its column in that block, and `linked_values` maps back.
+  linked_cols: [u8; SUDOKU_DIM_USIZE + 1],
+  linked_values: [u8; SUDOKU_DIM_USIZE],
+  // The cells of the current row before `num_pending` are already
+  // known: they were laid out along with an earlier cell of their
+  // block.
+  pending: [u8; SUDOKU_DIM_USIZE],
+  num_pending: usize,
+}
+
+impl CanonicalSearch {
+  fn new(grid: &SudokuGrid) -> CanonicalSearch {
+    let mut raw_grids = [[[0; SUDOKU_DIM_USIZE]; SUDOKU_DIM_USIZE]; 2];
+    for position in SudokuPosition::all() {
+      let (row_idx, col_idx) = position.as_usize_pair();
+      let raw_value = match grid.cell(position) {
+        SudokuCell::Empty => 0,
+        SudokuCell::Filled(value) => value.as_u8_value(),
+      };
+
+      raw_grids[0][row_idx][col_idx] = raw_value;
+      raw_grids[1][col_idx][row_idx] = raw_value;
+    }
+
+    CanonicalSearch {
+      raw_grids,
+      result: [[0; SUDOKU_DIM_USIZE]; SUDOKU_DIM_USIZE],
+      states: vec![],
+    }
+  }
+
+  fn run(&mut self) {
+    let mut identity_order = [0; SUDOKU_DIM_USIZE];
+    for (idx, entry) in identity_order.iter_mut().enumerate() {
+      *entry = idx as u8;
+    }
+    self.states = (0..2)
+      .map(|raw_grid_idx| SearchState {
+        raw_grid_idx,
+        row_order: [0; SUDOKU_DIM_USIZE],
+        used_rows: [false; SUDOKU_DIM_USIZE],
+        col_order: identity_order,
+        col_positions: identity_order,
+        is_block_start: [false; SUDOKU_DIM_USIZE],
+        block_labels: [0; SUDOKU_DIM_USIZE],
+        is_pinned: [false; SUDOKU_DIM_USIZE],
+        labels: [0; SUDOKU_DIM_USIZE + 1],
+        next_label: 1,
+        linked_cols: [NO_COL; SUDOKU_DIM_USIZE + 1],
+        linked_values: [0; SUDOKU_DIM_USIZE],
+        pending: [0; SUDOKU_DIM_USIZE],
+        num_pending: 0,
+      })
+      .collect();
+
+    for row_idx in 0..SUDOKU_DIM_USIZE {
+      let states = std::mem::take(&mut self.states);
+      self.states = states
+        .iter()
+        .flat_map(|state| self.row_options(state, row_idx))
+        .collect();
+
+      for col_idx in 0..SUDOKU_DIM_USIZE {
+        let states = std::mem::take(&mut self.states);
+        let mut best_value = None;
+        for state in &states {
+          for (option, value) in
+            self.cell_options(state, row_idx, col_idx)
+          {
+            if best_value.is_none_or(|best_value| value < best_value) {
+              self.states.clear();
+              best_value = Some(value);
+            }
+            if best_value == Some(value) {
+              self.states.push(option);
+            }
+          }
+        }
+        self.result[row_idx][col_idx] = best_value.unwrap();
+      }
+    }
+  }
+
+  // `row_options` lists the states with each possible raw row placed at
+  // `row_idx` of the result. For the first row, we also choose the order
+  // of the stacks.
+  fn row_options(
+    &self,
+    state: &SearchState,
+    row_idx: usize,
+  ) -> Vec<SearchState> {
+    let raw_grid = &self.raw_grids[state.raw_grid_idx];
+    let is_band_used = |band_start: usize| {
+      state.used_rows[band_start..band_start + SUDOKU_DIM_SQRT_USIZE]
+        .iter()
+        .any(|&is_used| is_used)
+    };
+
+    // At the start of a band, any row of an unused band may come next.
+    // Otherwise, the row must come from the band we are in.
+    let candidate_raw_row_idxs: Vec<usize> =
+      if row_idx.is_multiple_of(SUDOKU_DIM_SQRT_USIZE) {
+        // Two identical bands can be swapped without changing the grid,
+        // so only the first unused one of them is tried.
+        (0..SUDOKU_DIM_USIZE)
+          .filter(|&raw_row_idx| {
+            let band_start = group_start(raw_row_idx);
+            !is_band_used(band_start)
+              && !(0..band_start).step_by(SUDOKU_DIM_SQRT_USIZE).any(
+                |other_band_start| {
+                  !is_band_used(other_band_start)
+                    && self.is_same_band(
+                      state.raw_grid_idx,
+                      band_start,
+                      other_band_start,
+                    )
+                },
+              )
+          })
+          .collect()
+      } else {
+        let band_start =
+          group_start(state.row_order[row_idx - 1] as usize);
+        (band_start..band_start + SUDOKU_DIM_SQRT_USIZE)
+          .filter(|&raw_row_idx| !state.used_rows[raw_row_idx])
+          .collect()
+      };
+
+    let stack_orders = if row_idx == 0 {
+      self.stack_orders(state.raw_grid_idx)
+    } else {
+      vec![]
+    };
+
+    let mut options = vec![];
+    for (candidate_idx, &raw_row_idx) in
+      candidate_raw_row_idxs.iter().enumerate()
+    {
+      // Two identical rows of the same band (say, two empty rows) can be
+      // swapped without changing the grid, so only try one of them.
+      let is_duplicate = candidate_raw_row_idxs[..candidate_idx]
+        .iter()
+        .any(|&prev_raw_row_idx| {
+          group_start(prev_raw_row_idx) == group_start(raw_row_idx)
+            && raw_grid[prev_raw_row_idx] == raw_grid[raw_row_idx]
+        });
+      if is_duplicate {
+        continue;
+      }
+
+      let mut option = state.clone();
+      option.row_order[row_idx] = raw_row_idx as u8;
+      option.used_rows[raw_row_idx] = true;
+      option.num_pending = 0;
+      if row_idx != 0 {
+        options.push(option);
+        continue;
+      }
+
+      // Each stack starts out as a single block of columns.
+      for stack_order in &stack_orders {
+        let mut option = option.clone();
+        for (stack_slot_idx, &stack_idx) in
+          stack_order.iter().enumerate()
+        {
+          for offset in 0..SUDOKU_DIM_SQRT_USIZE {
+            let col_idx =
+              stack_slot_idx * SUDOKU_DIM_SQRT_USIZE + offset;
+            let raw_col_idx =
+              stack_idx * SUDOKU_DIM_SQRT_USIZE + offset;
+            option.col_order[col_idx] = raw_col_idx as u8;
+            option.col_positions[raw_col_idx] = col_idx as u8;
+            option.is_block_start[col_idx] = offset == 0;
+          }
+        }
+        options.push(option);
+      }
+    }
+
+    options
+  }
+
+  fn is_same_band(
+    &self,
+    raw_grid_idx: usize,
+    band_start: usize,
+    other_band_start: usize,
+  ) -> bool {
+    let raw_grid = &self.raw_grids[raw_grid_idx];
+    (0..SUDOKU_DIM_SQRT_USIZE).all(|offset| {
+      raw_grid[band_start + offset]
+        == raw_grid[other_band_start + offset]
+    })
+  }
+
+  // `stack_orders` lists the orders of the raw stacks worth trying. Two
+  // identical stacks (say, two empty stacks) can be swapped without
+  // changing the grid, so we keep those in their original order.
+  fn stack_orders(&self, raw_grid_idx: usize) -> Vec<Vec<usize>> {
+    let raw_grid = &self.raw_grids[raw_grid_idx];
+    let is_same_stack = |stack_idx: usize, other_stack_idx: usize| {
+      raw_grid.iter().all(|raw_row| {
+        stack_slice(raw_row, stack_idx)
+          == stack_slice(raw_row, other_stack_idx)
+      })
+    };
+
+    permutations(SUDOKU_DIM_SQRT_USIZE)
+      .into_iter()
+      .filter(|stack_order| {
+        stack_order
+          .iter()
+          .enumerate()
+          .all(|(slot_idx, &stack_idx)| {
+            stack_order[slot_idx + 1..].iter().all(|&later_stack_idx| {
+              later_stack_idx > stack_idx
+                || !is_same_stack(stack_idx, later_stack_idx)
+            })
+          })
+      })
+      .collect()
+  }
+
+  // `cell_options` lists the ways of filling in cell (`row_idx`,
+  // `col_idx`) of the result from `state`, with the value each writes.
+  // Only the options writing the smallest value are worth keeping, but
+  // the caller compares them across states.
+  fn cell_options(
+    &self,
+    state: &SearchState,
+    row_idx: usize,
+    col_idx: usize,
+  ) -> Vec<(SearchState, u8)> {
+    if col_idx < state.num_pending {
+      return vec![(state.clone(), state.pending[col_idx])];
+    }
+
+    let raw_row = &self.raw_grids[state.raw_grid_idx]
+      [state.row_order[row_idx] as usize];
+    let block_start = state.block_start(col_idx);
+    let block_end = state.block_end(col_idx);
+
+    // In a linked block, each column left may go here.
+    if state.block_labels[block_start] != 0 {
+      let raw_col_idxs: Vec<u8> = if state.is_pinned[col_idx] {
+        vec![state.col_order[col_idx]]
+      } else {
+        (col_idx..block_end)
+          .filter(|&idx| !state.is_pinned[idx])
+          .map(|idx| state.col_order[idx])
+          .collect()
+      };
+      return raw_col_idxs
+        .into_iter()
+        .map(|raw_col_idx| {
+          let mut option = state.clone();
+          option.pin(col_idx, raw_col_idx);
+          let value = option.label(raw_row[raw_col_idx as usize]);
+          (option, value)
+        })
+        .collect();
+    }
+
+    // Otherwise we are at the start of a block of columns which are
+    // the same so far. The ones writing the smallest value come first.
+    let block = &state.col_order[col_idx..block_end];
+    let value_for =
+      |raw_col_idx: u8| state.peek_label(raw_row[raw_col_idx as usize]);
+    let min_value = block.iter().map(|&c| value_for(c)).min().unwrap();
+    let min_raw_col_idxs: Vec<u8> = block
+      .iter()
+      .cloned()
+      .filter(|&raw_col_idx| value_for(raw_col_idx) == min_value)
+      .collect();
+    let raw_value = raw_row[min_raw_col_idxs[0] as usize];
+    let mut raw_values: Vec<u8> = min_raw_col_idxs
+      .iter()
+      .map(|&raw_col_idx| raw_row[raw_col_idx as usize])
+      .collect();
+    raw_values.sort_unstable();
+    raw_values.dedup();
+
+    let mut option = state.clone();
+    if raw_value == 0 || state.labels[raw_value as usize] != 0 {
+      // Empty columns, or ones with a value we have already seen, stay
+      // a block of their own, which a later row may split.
+      option.split_block(col_idx, block_end, &min_raw_col_idxs);
+      let num_cols = min_raw_col_idxs.len();
+      option.pending[col_idx..col_idx + num_cols].fill(min_value);
+      option.num_pending = col_idx + num_cols;
+      vec![(option, min_value)]
+    } else if state.linked_cols[raw_value as usize] == NO_COL
+      && raw_values.len() == min_raw_col_idxs.len()
+    {
+      // New values, which are always the biggest: the whole block
+      // becomes a linked block.
+      option.block_labels[col_idx] = option.next_label;
+      for (offset, &raw_col_idx) in block.iter().enumerate() {
+        let raw_value = raw_row[raw_col_idx as usize];
+        option.linked_cols[raw_value as usize] = raw_col_idx;
+        option.linked_values[raw_col_idx as usize] = raw_value;
+        option.pending[col_idx + offset] = option.next_label;
+        option.next_label += 1;
+      }
+      option.num_pending = block_end;
+      vec![(option, min_value)]
+    } else {
+      // Values waiting on the same linked block, or a new value repeated
+      // in the row: each column could be the one to go first.
+      min_raw_col_idxs
+        .iter()
+        .map(|&raw_col_idx| {
+          let mut option = state.clone();
+          option.split_block(col_idx, block_end, &[raw_col_idx]);
+          let value = option.label(raw_row[raw_col_idx as usize]);
+          (option, value)
+        })
+        .collect()
+    }
+  }
+
+  fn best_transform(&self) -> SudokuTransform {
+    let best = &self.states[0];
+
+    // Columns of linked blocks which no later row told apart keep their
+    // tentative order. Values which never appear in the grid get the
+    // leftover labels in increasing order.
+    let mut labels = best.labels;
+    for raw_col_idx in 0..SUDOKU_DIM_USIZE {
+      let raw_value = best.linked_values[raw_col_idx] as usize;
+      if raw_value != 0 {
+        let col_idx = best.col_positions[raw_col_idx] as usize;
+        let block_start = best.block_start(col_idx);
+        labels[raw_value] = best.block_labels[block_start]
+          + (col_idx - block_start) as u8;
+      }
+    }
+    let mut next_label = best.next_label;
+    for label in labels.iter_mut().skip(1) {
+      if *label == 0 {
+        *label = next_label;
+        next_label += 1;
+      }
+    }
+
+    let mut relabeling = [SudokuValue::first(); SUDOKU_DIM_USIZE];
+    for value in SudokuValue::all() {
+      relabeling[value.as_usize_idx()] =
+        SudokuValue::new(labels[value.as_u8_value() as usize]);
+    }
+
+    SudokuTransform::from_parts(
+      best.raw_grid_idx == 1,
+      best.row_order,
+      best.col_order,
+      relabeling,
+    )
+  }
+}
+
+impl SearchState {
+  fn block_start(&self, col_idx: usize) -> usize {
+    (0..=col_idx)
+      .rev()
+      .find(|&idx| self.is_block_start[idx])
+      .unwrap_or(0)
+  }
+
+  // `block_end` returns the column just past the block containing
+  // `col_idx`.
+  fn block_end(&self, col_idx: usize) -> usize {
+    (col_idx + 1..SUDOKU_DIM_USIZE)
+      .find(|&idx| self.is_block_start[idx])
+      .unwrap_or(SUDOKU_DIM_USIZE)
+  }
+
+  // `peek_label` is the value `label` would write, without labeling
+  // anything.
+  fn peek_label(&self, raw_value: u8) -> u8 {
+    let raw_value = raw_value as usize;
+    if raw_value == 0 || self.labels[raw_value] != 0 {
+      return self.labels[raw_value];
+    }
+    match self.linked_cols[raw_value] {
+      NO_COL => self.next_label,
+      raw_col_idx => {
+        let col_idx = self.col_positions[raw_col_idx as usize] as usize;
+        let block_start = self.block_start(col_idx);
+        let free_col_idx = (block_start..self.block_end(col_idx))
+          .find(|&idx| !self.is_pinned[idx])
+          .unwrap();
+        self.block_labels[block_start]
+          + (free_col_idx - block_start) as u8
+      }
+    }
+  }
+
+  // `label` returns the value written for a raw value, labeling it if
+  // needed. A value waiting on a linked block takes the smallest label
+  // left there.
+  fn label(&mut self, raw_value: u8) -> u8 {
+    if raw_value == 0 || self.labels[raw_value as usize] != 0 {
+      return self.labels[raw_value as usize];
+    }
+    match self.linked_cols[raw_value as usize] {
+      NO_COL => {
+        self.labels[raw_value as usize] = self.next_label;
+        self.next_label += 1;
+      }
+      raw_col_idx => {
+        let col_idx = self.col_positions[raw_col_idx as usize] as usize;
+        let free_col_idx = (self.block_start(col_idx)
+          ..self.block_end(col_idx))
+          .find(|&idx| !self.is_pinned[idx])
+          .unwrap();
+        self.pin(free_col_idx, raw_col_idx);
+      }
+    }
+    self.labels[raw_value as usize]
+  }
+
+  // `pin` settles raw column `raw_col_idx` of a linked block at
+  // `col_idx`, which labels its value. Pinning a settled column again
+  // does nothing.
+  fn pin(&mut self, col_idx: usize, raw_col_idx: u8) {
+    if self.is_pinned[col_idx] {
+      return;
+    }
+    self.swap_cols(col_idx, self.col_positions[raw_col_idx as usize]);
+    self.is_pinned[col_idx] = true;
+
+    let raw_value = self.linked_values[raw_col_idx as usize] as usize;
+    let block_start = self.block_start(col_idx);
+    self.labels[raw_value] =
+      self.block_labels[block_start] + (col_idx - block_start) as u8;
+    self.linked_cols[raw_value] = NO_COL;
+    self.linked_values[raw_col_idx as usize] = 0;
+  }
+
+  // `split_block` moves `raw_col_idxs` to the front of the block
+  // `block_start..block_end`, as a block of their own.
+  fn split_block(
+    &mut self,
+    block_start: usize,
+    block_end: usize,
+    raw_col_idxs: &[u8],
+  ) {
+    for (offset, &raw_col_idx) in raw_col_idxs.iter().enumerate() {
+      self.swap_cols(
+        block_start + offset,
+        self.col_positions[raw_col_idx as usize],
+      );
+    }
+    let split_idx = block_start + raw_col_idxs.len();
+    if split_idx < block_end {
+      self.is_block_start[split_idx] = true;
+    }
+  }
+
+  fn swap_cols(&mut self, col_idx: usize, other_col_idx: u8) {
+    let other_col_idx = other_col_idx as usize;
+    self.col_order.swap(col_idx, other_col_idx);
+    self.col_positions[self.col_order[col_idx] as usize] =
+      col_idx as u8;
+    self.col_positions[self.col_order[other_col_idx] as usize] =
+      other_col_idx as u8;
+  }
+}
+
+// `group_start` returns the first row of the band (or the first column
+// of the stack) containing `idx`.
+fn group_start(idx: usize) -> usize {
+  idx - idx % SUDOKU_DIM_SQRT_USIZE
+}
+
+fn stack_slice(
+  raw_row: &[u8; SUDOKU_DIM_USIZE],
+  stack_idx: usize,
+) -> &[u8] {
+  let stack_start = stack_idx * SUDOKU_DIM_SQRT_USIZE;
+  &raw_row[stack_start..stack_start + SUDOKU_DIM_SQRT_USIZE]
+}
+
+// `permutations` returns every ordering of 0 through `len - 1`.
+fn permutations(len: usize) -> Vec<Vec<usize>> {
+  if len == 0 {
+    return vec![vec![]];
+  }
+
+  let mut result = vec![];
+  for shorter in permutations(len - 1) {
+    for insert_idx in 0..len {
+      let mut permutation = shorter.clone();
+      permutation.insert(insert_idx, len - 1);
+      result.push(permutation);
+    }
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::SmallRng;
+  use rand::seq::SliceRandom;
+  use rand::SeedableRng;
+  use test_util::random_solution;
+
+  // `random_puzzle` keeps `num_clues` cells of a random solution.
+  fn random_puzzle(rng: &mut SmallRng, num_clues: usize) -> SudokuGrid {
+    let mut choices = random_solution(rng).filled_choices();
+    choices.shuffle(rng);
+    SudokuGrid::from_choices(&choices[..num_clues])
+  }
+
+  #[test]
+  fn transformed_copies_have_the_same_canonical_form() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    for &num_clues in &[0, 1, 20, 130, 200, 250, 256] {
+      let grid = random_puzzle(&mut rng, num_clues);
+      let canonical_grid = canonical_form(&grid);
+      for _ in 0..3 {
+        let copy =
+          SudokuTransform::random(&mut rng).apply_to_grid(&grid);
+        assert_eq!(canonical_form(&copy), canonical_grid);
+      }
+    }
+  }
+
+  #[test]
+  fn canonical_form_is_its_own_canonical_form() {
+    let mut rng = SmallRng::seed_from_u64(2);
+    let canonical_grid = canonical_form(&random_puzzle(&mut rng, 150));
+    assert_eq!(canonical_form(&canonical_grid), canonical_grid);
+  }
+
+  #[test]
+  fn canonical_transform_makes_the_canonical_form() {
+    let mut rng = SmallRng::seed_from_u64(3);
+    let grid = random_solution(&mut rng);
+    let canonical_grid =
+      canonical_transform(&grid).apply_to_grid(&grid);
+    assert_eq!(canonical_grid, canonical_form(&grid));
+    // A complete grid's first row always relabels to 1 through 16.
+    for position in SudokuPosition::all().into_iter().take(16) {
+      let (_, col_idx) = position.as_usize_pair();
+      assert_eq!(
+        canonical_grid.cell(position),
+        SudokuCell::Filled(SudokuValue::new(col_idx as u8 + 1))
+      );
+    }
+  }
+
+  #[test]
+  fn are_isomorphic_returns_a_transform_onto_the_other_grid() {
+    let mut rng = SmallRng::seed_from_u64(4);
+    for &num_clues in &[130, 256] {
+      let grid = random_puzzle(&mut rng, num_clues);
+      let other_grid =
+        SudokuTransform::random(&mut rng).apply_to_grid(&grid);
+      let transform = are_isomorphic(&grid, &other_grid).unwrap();
+      assert_eq!(transform.apply_to_grid(&grid), other_grid);
+    }
+  }
+
+  #[test]
+  fn are_isomorphic_tells_different_grids_apart() {
+    let mut rng = SmallRng::seed_from_u64(5);
+    let grid = random_puzzle(&mut rng, 130);
+    let other_grid = random_puzzle(&mut rng, 130);
+    assert!(are_isomorphic(&grid, &other_grid).is_none());
+
+    let mut fewer_clues = grid.filled_choices();
+    fewer_clues.pop();
+    let fewer_clues_grid = SudokuGrid::from_choices(&fewer_clues);
+    assert!(are_isomorphic(&grid, &fewer_clues_grid).is_none());
+  }
+}
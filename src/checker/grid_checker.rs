@@ -1,11 +1,14 @@
 use super::SudokuGroupConflictChecker;
-use core::{SudokuBox, SudokuChoice, SUDOKU_DIM_USIZE};
+use core::{
+  SudokuBox, SudokuChoice, SudokuPosition, SudokuValue,
+  SUDOKU_DIM_USIZE,
+};
 
 // SudokuGridConflictChecker builds on top of
 // SudokuGroupConflictChecker. It checks whether a choice of value at a
 // given position is compatible with constraints on all the other values
 // in the same row/column/box.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct SudokuGridConflictChecker {
   // Keeps a 9x9 grid of group checkers.
   cell_checkers:
@@ -15,25 +18,17 @@ pub struct SudokuGridConflictChecker {
 impl SudokuGridConflictChecker {
   pub fn new() -> SudokuGridConflictChecker {
     // A SudokuGroupConflictChecker is not Copy, so it is a little
-    // annoying to initialize a array of checkers.
-    let cell_checkers = unsafe {
-      use std::mem;
-      use std::ptr;
-
-      // If there were a panic, then it uninitialized memory could leak
-      // out. Whatever...
-      let mut cell_checkers: [[SudokuGroupConflictChecker;
-                               SUDOKU_DIM_USIZE];
-                               SUDOKU_DIM_USIZE] = mem::uninitialized();
-
-      for row in &mut cell_checkers {
-        for chell_checker in row {
-          ptr::write(chell_checker, SudokuGroupConflictChecker::new());
-        }
+    // annoying to initialize a array of checkers. The derived default
+    // checker has no free values, so we overwrite every one of them.
+    let mut cell_checkers: [[SudokuGroupConflictChecker;
+                             SUDOKU_DIM_USIZE];
+                             SUDOKU_DIM_USIZE] = Default::default();
+
+    for row in &mut cell_checkers {
+      for chell_checker in row {
+        *chell_checker = SudokuGroupConflictChecker::new();
       }
-
-      cell_checkers
-    };
+    }
 
     SudokuGridConflictChecker { cell_checkers }
   }
@@ -62,10 +57,59 @@ impl SudokuGridConflictChecker {
     self.unpropagate_conflicts(choice);
   }
 
+  // `candidates` returns the values which don't conflict with any
+  // choice in the same row/column/box as the given position. Note that
+  // a choice never conflicts with its own position, so for a filled
+  // position the chosen value is among the candidates.
+  pub fn candidates(
+    &self,
+    position: SudokuPosition,
+  ) -> Vec<SudokuValue> {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    SudokuValue::all()
+      .into_iter()
+      .filter(|&value| {
+        self.cell_checkers[row_idx][col_idx].can_store_here(value)
+      }).collect()
+  }
+
+  // `eliminate_candidate` rules out a value at the given position
+  // without choosing anything there, as a player does when crossing out
+  // a pencil mark. We refuse to eliminate the last value that could go
+  // at the position.
+  //
+  // Eliminations are counted like any other conflict, so each one must
+  // be undone by exactly one call to `restore_candidate`.
+  pub fn eliminate_candidate(
+    &mut self,
+    position: SudokuPosition,
+    value: SudokuValue,
+  ) -> EliminateCandidateResult {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    let cell_checker = &mut self.cell_checkers[row_idx][col_idx];
+
+    if cell_checker.can_restrict_here(value) {
+      cell_checker.add_conflict(value);
+      EliminateCandidateResult::DidEliminateCandidate
+    } else {
+      EliminateCandidateResult::CouldNotEliminateCandidate
+    }
+  }
+
+  // `restore_candidate` undoes an `eliminate_candidate`.
+  pub fn restore_candidate(
+    &mut self,
+    position: SudokuPosition,
+    value: SudokuValue,
+  ) {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    self.cell_checkers[row_idx][col_idx].remove_conflict(value);
+  }
+
   // `can_accomodate_choice` checks first whether the choice is valid at
   // the specified position, and that it doesn't "overconstrain" other
   // values in the same row/column/box.
-  #[allow(if_same_then_else)]
+  #[allow(clippy::if_same_then_else)]
   fn can_accomodate_choice(&self, choice: SudokuChoice) -> bool {
     let (choice_row_idx, choice_col_idx) =
       choice.position.as_usize_pair();
@@ -125,7 +169,7 @@ impl SudokuGridConflictChecker {
     )
   }
 
-  #[allow(if_same_then_else)]
+  #[allow(clippy::if_same_then_else)]
   fn propagate_conflicts(&mut self, choice: SudokuChoice) {
     let (choice_row_idx, choice_col_idx) =
       choice.position.as_usize_pair();
@@ -174,7 +218,7 @@ impl SudokuGridConflictChecker {
       });
   }
 
-  #[allow(if_same_then_else)]
+  #[allow(clippy::if_same_then_else)]
   fn unpropagate_conflicts(&mut self, choice: SudokuChoice) {
     let (choice_row_idx, choice_col_idx) =
       choice.position.as_usize_pair();
@@ -241,3 +285,21 @@ impl AddChoiceResult {
     }
   }
 }
+
+// EliminateCandidateResult tells the caller of `eliminate_candidate`
+// whether the candidate was ruled out.
+#[must_use]
+#[derive(Clone, Copy)]
+pub enum EliminateCandidateResult {
+  CouldNotEliminateCandidate,
+  DidEliminateCandidate,
+}
+
+impl EliminateCandidateResult {
+  pub fn did_eliminate(self) -> bool {
+    match self {
+      EliminateCandidateResult::CouldNotEliminateCandidate => false,
+      EliminateCandidateResult::DidEliminateCandidate => true,
+    }
+  }
+}
@@ -39,7 +39,7 @@ impl SudokuGroupConflictChecker {
 
   // Will the given choice elsewhere in the same row/col/box eliminate
   // the last possible value here?
-  #[allow(if_same_then_else, needless_bool)]
+  #[allow(clippy::if_same_then_else, clippy::needless_bool)]
   pub fn can_restrict_here(&self, val: SudokuValue) -> bool {
     // Why are you asking if you can further restrict here if it is
     // already too restricted?
@@ -2,6 +2,6 @@ mod grid_checker;
 mod group_checker;
 
 pub use self::grid_checker::{
-  AddChoiceResult, SudokuGridConflictChecker,
+  AddChoiceResult, EliminateCandidateResult, SudokuGridConflictChecker,
 };
 pub use self::group_checker::SudokuGroupConflictChecker;
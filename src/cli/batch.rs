@@ -0,0 +1,119 @@
+use super::json::Report;
+use super::options::{Options, COMMON_FLAGS};
+use super::puzzles::{read_puzzles, Output, Puzzle, Status};
+use super::{EXIT_FAILURE, EXIT_MULTIPLE_SOLUTIONS};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use sudoku_solving::batch::{
+  BatchOutcome, BatchSolver, BatchStatus, BatchSummary,
+};
+
+const USAGE: &str = "\
+usage: sudoku batch [OPTIONS] [FILE...]
+
+Solves puzzles like `sudoku solve`, on all cores. Solutions are printed
+in input order; a summary of the run goes to standard error at the end.
+
+  --jobs N        solve on N threads (default: one per core)
+  --node-limit N  give up on a puzzle after the solver makes N choices
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let options =
+    Options::parse(args, &["--jobs", "--node-limit"], &usage)?;
+  let mut batch_solver = BatchSolver::new();
+  // For both flags, 0 means the default.
+  match options.flag("--jobs", 0, &usage)? {
+    0 => {}
+    num_threads => {
+      batch_solver = batch_solver.with_threads(num_threads)
+    }
+  }
+  match options.flag("--node-limit", 0, &usage)? {
+    0 => {}
+    max_nodes => batch_solver = batch_solver.with_node_limit(max_nodes),
+  }
+
+  let mut output = Output::new(options.output_format);
+  let mut status = Status::new();
+  let mut summary = BatchSummary::new();
+  // Outcomes are consumed while puzzles are pushed, from inside
+  // `read_puzzles`, which holds the output; so they wait here until
+  // they can be written.
+  let finished = RefCell::new(VecDeque::new());
+  batch_solver.solve_all(
+    |feeder| {
+      read_puzzles(
+        &options,
+        &mut output,
+        &mut status,
+        |puzzle, output, status| {
+          let grid = puzzle.grid.clone();
+          feeder.push(puzzle, grid);
+          for (puzzle, outcome) in finished.borrow_mut().drain(..) {
+            write_outcome(&puzzle, &outcome, output, status);
+            summary.add(&outcome);
+          }
+        },
+      )
+    },
+    |puzzle, outcome| {
+      finished.borrow_mut().push_back((puzzle, outcome))
+    },
+  );
+  for (puzzle, outcome) in finished.into_inner() {
+    write_outcome(&puzzle, &outcome, &mut output, &mut status);
+    summary.add(&outcome);
+  }
+  output.flush();
+  eprint!("{}", summary);
+
+  Ok(status.exit_code())
+}
+
+fn write_outcome(
+  puzzle: &Puzzle,
+  outcome: &BatchOutcome,
+  output: &mut Output,
+  status: &mut Status,
+) {
+  let (report_status, error) = match outcome.status {
+    BatchStatus::Solved => ("solved", None),
+    BatchStatus::Unsolvable => ("unsolvable", Some("no solution")),
+    BatchStatus::MultipleSolutions => {
+      ("multiple_solutions", Some("more than one solution"))
+    }
+    BatchStatus::NodeLimitReached if outcome.solution.is_some() => (
+      "node_limit_reached",
+      Some("hit the node limit checking the solution is unique"),
+    ),
+    BatchStatus::NodeLimitReached => {
+      ("node_limit_reached", Some("hit the node limit"))
+    }
+  };
+  let mut report =
+    Report::new(&puzzle.source, &puzzle.grid, report_status)
+      .with_stat("num_nodes", outcome.stats.num_nodes)
+      .with_stat("num_backtracks", outcome.stats.num_backtracks)
+      .with_duration(outcome.duration);
+
+  // Like `sudoku solve`, a solution that may not be the only one is
+  // still printed, with a warning.
+  if let Some(error) = error {
+    let level = if outcome.solution.is_some() {
+      status.fail(EXIT_MULTIPLE_SOLUTIONS);
+      "warning"
+    } else {
+      status.fail(EXIT_FAILURE);
+      "error"
+    };
+    eprintln!("{}: {}: {}", level, puzzle.source, error);
+    report = report.with_error(error);
+  }
+  if let Some(solution) = &outcome.solution {
+    output.write_solution(puzzle, solution);
+    report = report.with_solution(solution);
+  }
+  output.write_report(&report);
+}
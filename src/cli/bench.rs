@@ -0,0 +1,461 @@
+use super::json::Report;
+use super::options::{usage_error, Options, COMMON_FLAGS};
+use super::puzzles::{Output, Status};
+use super::{EXIT_FAILURE, EXIT_IO_ERROR, EXIT_MALFORMED_INPUT};
+use std::fs;
+use std::time::{Duration, Instant};
+use sudoku_solving::{
+  batch::{BatchOutcome, BatchSolver, BatchSummary},
+  core::{SudokuGrid, SUDOKU_DIM_USIZE},
+  format::PuzzleLineReader,
+};
+
+const USAGE: &str = "\
+usage: sudoku bench [OPTIONS]
+
+Times each solver backend on the puzzle sets that ship with sudoku, and
+prints the throughput and solve time percentiles of every run. The sets
+never change, so results can be compared across machines and versions:
+`--save-baseline` keeps a run's results in a file, and `--baseline`
+compares a later run with them.
+
+Puzzle sets, of 50 puzzles with a unique solution each:
+  16x16/easy, 16x16/medium, 16x16/hard
+Only the sets of this build's grid size are run.
+
+Backends:
+  solver  one puzzle at a time, on one thread
+  batch   the batch solver, on all cores, like `sudoku batch`
+
+  --corpus NAME         run only this puzzle set; the size may be left
+                        out, as in `hard`
+  --backend NAME        run only this backend
+  --rounds N            solve each set N times (default 3)
+  --save-baseline FILE  write the results to FILE
+  --baseline FILE       compare the results with those in FILE
+  --threshold PCT       call it a regression when throughput falls, or
+                        the p99 time rises, by more than PCT percent
+                        (default 20; timings vary by a few percent
+                        from run to run)
+
+Exit status is 1 when a puzzle isn't solved, or on a regression.
+";
+
+// The bundled puzzle sets. Names start with the grid size, and a build
+// runs the sets of its own size only.
+const CORPORA: &[(&str, &str)] = &[
+  ("16x16/easy", include_str!("../../corpora/16x16/easy.txt")),
+  (
+    "16x16/medium",
+    include_str!("../../corpora/16x16/medium.txt"),
+  ),
+  ("16x16/hard", include_str!("../../corpora/16x16/hard.txt")),
+];
+
+const BASELINE_HEADER: &str = "\
+# sudoku bench baseline
+# CORPUS BACKEND PUZZLES_PER_SEC P50_MS P90_MS P99_MS MAX_MS
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let options = Options::parse(
+    args,
+    &[
+      "--corpus",
+      "--backend",
+      "--rounds",
+      "--save-baseline",
+      "--baseline",
+      "--threshold",
+    ],
+    &usage,
+  )?;
+  // With no FILE, the paths are just `-`.
+  if options.paths != ["-"] {
+    return Err(usage_error(
+      "bench takes no files; it runs the bundled puzzle sets",
+      &usage,
+    ));
+  }
+  // For the names and files, empty means not given.
+  let corpus_name: String =
+    options.flag("--corpus", String::new(), &usage)?;
+  let backend_name: String =
+    options.flag("--backend", String::new(), &usage)?;
+  let num_rounds: usize = options.flag("--rounds", 3, &usage)?;
+  let save_path: String =
+    options.flag("--save-baseline", String::new(), &usage)?;
+  let baseline_path: String =
+    options.flag("--baseline", String::new(), &usage)?;
+  let threshold: f64 = options.flag("--threshold", 20.0, &usage)?;
+  if num_rounds == 0 {
+    return Err(usage_error("--rounds must be at least 1", &usage));
+  }
+  if threshold.is_nan() || threshold < 0.0 {
+    return Err(usage_error("--threshold can't be negative", &usage));
+  }
+
+  let size_prefix = format!("{0}x{0}/", SUDOKU_DIM_USIZE);
+  let corpora: Vec<(&str, &str)> = CORPORA
+    .iter()
+    .cloned()
+    .filter(|(name, _)| name.starts_with(&size_prefix))
+    .filter(|(name, _)| {
+      corpus_name.is_empty()
+        || *name == corpus_name
+        || name[size_prefix.len()..] == *corpus_name
+    })
+    .collect();
+  if corpora.is_empty() {
+    return Err(usage_error(
+      &format!("unknown puzzle set {:?}", corpus_name),
+      &usage,
+    ));
+  }
+  let backends: Vec<Backend> = Backend::ALL
+    .iter()
+    .cloned()
+    .filter(|backend| {
+      backend_name.is_empty() || backend.name() == backend_name
+    })
+    .collect();
+  if backends.is_empty() {
+    return Err(usage_error(
+      &format!("unknown backend {:?}", backend_name),
+      &usage,
+    ));
+  }
+
+  // A bad baseline is better found out before the runs than after.
+  let baseline = if baseline_path.is_empty() {
+    None
+  } else {
+    match read_baseline(&baseline_path) {
+      Ok(baseline) => Some(baseline),
+      Err(exit_code) => return Ok(exit_code),
+    }
+  };
+
+  let mut output = Output::new(options.output_format);
+  let mut status = Status::new();
+  let mut results = vec![];
+  for &(corpus, text) in &corpora {
+    let puzzles = parse_corpus(text);
+    for &backend in &backends {
+      let (summary, duration) =
+        backend.solve_rounds(&puzzles, num_rounds);
+      let result =
+        BenchResult::new(corpus, backend, &summary, duration);
+      let source = format!("{} {}", corpus, backend.name());
+      let mut line = result.to_string();
+      let mut report_status = "ok";
+      let mut errors = vec![];
+
+      let num_unsolved = summary.num_puzzles() - summary.num_solved;
+      if num_unsolved > 0 {
+        report_status = "failed";
+        errors.push(format!(
+          "{} of {} solves didn't find a unique solution",
+          num_unsolved,
+          summary.num_puzzles()
+        ));
+      }
+
+      let baseline_result = baseline.as_ref().map(|baseline| {
+        baseline.iter().find(|baseline_result| {
+          baseline_result.corpus == result.corpus
+            && baseline_result.backend == result.backend
+        })
+      });
+      let comparison = match baseline_result {
+        None => None,
+        Some(None) => {
+          line.push_str("  (not in the baseline)");
+          None
+        }
+        Some(Some(baseline_result)) => {
+          Some(Comparison::new(&result, baseline_result))
+        }
+      };
+      if let Some(comparison) = &comparison {
+        line.push_str(&format!(
+          "  throughput {:+.1}%  p99 {:+.1}%",
+          comparison.throughput_change, comparison.p99_change
+        ));
+        let regressions = comparison.regressions(threshold);
+        if !regressions.is_empty() {
+          line.push_str("  REGRESSION");
+          if report_status == "ok" {
+            report_status = "regression";
+          }
+        }
+        errors.extend(regressions);
+      }
+
+      let mut report = Report::about_run(&source, report_status)
+        .with_stat("num_puzzles", summary.num_puzzles())
+        .with_stat(
+          "puzzles_per_sec",
+          format!("{:.1}", result.throughput),
+        )
+        .with_stat("p50_ms", format!("{:.3}", result.p50_ms))
+        .with_stat("p90_ms", format!("{:.3}", result.p90_ms))
+        .with_stat("p99_ms", format!("{:.3}", result.p99_ms))
+        .with_stat("max_ms", format!("{:.3}", result.max_ms));
+      if let Some(comparison) = &comparison {
+        report = report
+          .with_stat(
+            "throughput_change_pct",
+            format!("{:.1}", comparison.throughput_change),
+          )
+          .with_stat(
+            "p99_change_pct",
+            format!("{:.1}", comparison.p99_change),
+          );
+      }
+      for error in &errors {
+        eprintln!("error: {}: {}", source, error);
+        report = report.with_error(error);
+      }
+      if !errors.is_empty() {
+        status.fail(EXIT_FAILURE);
+      }
+      output.write_line(&line);
+      output.write_report(&report);
+      results.push(result);
+    }
+  }
+  output.flush();
+
+  if !save_path.is_empty() {
+    let mut text = BASELINE_HEADER.to_string();
+    for result in &results {
+      text.push_str(&result.to_baseline_line());
+      text.push('\n');
+    }
+    if let Err(error) = fs::write(&save_path, text) {
+      eprintln!("error: could not write {}: {}", save_path, error);
+      status.fail(EXIT_IO_ERROR);
+    }
+  }
+
+  Ok(status.exit_code())
+}
+
+// Backend is a way of running the solver over many puzzles.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Backend {
+  Solver,
+  Batch,
+}
+
+impl Backend {
+  const ALL: [Backend; 2] = [Backend::Solver, Backend::Batch];
+
+  fn name(self) -> &'static str {
+    match self {
+      Backend::Solver => "solver",
+      Backend::Batch => "batch",
+    }
+  }
+
+  // `solve_rounds` solves every puzzle `num_rounds` times, and returns
+  // the outcomes with the time it all took.
+  fn solve_rounds(
+    self,
+    puzzles: &[SudokuGrid],
+    num_rounds: usize,
+  ) -> (BatchSummary, Duration) {
+    let mut summary = BatchSummary::new();
+    let start = Instant::now();
+    for _ in 0..num_rounds {
+      match self {
+        Backend::Solver => {
+          for puzzle in puzzles {
+            summary.add(&BatchOutcome::solve(puzzle, None));
+          }
+        }
+        Backend::Batch => BatchSolver::new().solve_all(
+          |feeder| {
+            for puzzle in puzzles {
+              feeder.push((), puzzle.clone());
+            }
+          },
+          |(), outcome| summary.add(&outcome),
+        ),
+      }
+    }
+    (summary, start.elapsed())
+  }
+}
+
+// BenchResult is how fast one backend went on one puzzle set. Baseline
+// files hold one per line.
+#[derive(Clone, Debug)]
+struct BenchResult {
+  corpus: String,
+  backend: String,
+  // Puzzles solved per second of wall time.
+  throughput: f64,
+  p50_ms: f64,
+  p90_ms: f64,
+  p99_ms: f64,
+  max_ms: f64,
+}
+
+impl BenchResult {
+  fn new(
+    corpus: &str,
+    backend: Backend,
+    summary: &BatchSummary,
+    duration: Duration,
+  ) -> BenchResult {
+    let percentile_ms = |percent| {
+      let duration = summary.duration_percentile(percent).unwrap();
+      duration.as_secs_f64() * 1000.0
+    };
+    BenchResult {
+      corpus: corpus.to_string(),
+      backend: backend.name().to_string(),
+      throughput: summary.num_puzzles() as f64 / duration.as_secs_f64(),
+      p50_ms: percentile_ms(50.0),
+      p90_ms: percentile_ms(90.0),
+      p99_ms: percentile_ms(99.0),
+      max_ms: percentile_ms(100.0),
+    }
+  }
+
+  // `from_baseline_line` reads a line written by `to_baseline_line`.
+  // Returns None if it is malformed.
+  fn from_baseline_line(line: &str) -> Option<BenchResult> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 7 {
+      return None;
+    }
+    let mut numbers = vec![];
+    for field in &fields[2..] {
+      let number: f64 = field.parse().ok()?;
+      // Changes are relative to the baseline, so it can't be zero.
+      if !number.is_finite() || number <= 0.0 {
+        return None;
+      }
+      numbers.push(number);
+    }
+    Some(BenchResult {
+      corpus: fields[0].to_string(),
+      backend: fields[1].to_string(),
+      throughput: numbers[0],
+      p50_ms: numbers[1],
+      p90_ms: numbers[2],
+      p99_ms: numbers[3],
+      max_ms: numbers[4],
+    })
+  }
+
+  fn to_baseline_line(&self) -> String {
+    format!(
+      "{} {} {:.1} {:.4} {:.4} {:.4} {:.4}",
+      self.corpus,
+      self.backend,
+      self.throughput,
+      self.p50_ms,
+      self.p90_ms,
+      self.p99_ms,
+      self.max_ms
+    )
+  }
+}
+
+// `Display` writes a row of the results table:
+//
+//   16x16/hard    batch       22.7/s  p50  23.33ms  p90 121.02ms ...
+impl std::fmt::Display for BenchResult {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(
+      f,
+      "{:<13} {:<6} {:>9.1}/s  p50 {:>6.2}ms  p90 {:>6.2}ms  \
+       p99 {:>6.2}ms  max {:>6.2}ms",
+      self.corpus,
+      self.backend,
+      self.throughput,
+      self.p50_ms,
+      self.p90_ms,
+      self.p99_ms,
+      self.max_ms
+    )
+  }
+}
+
+// Comparison is how a result changed from the baseline, in percent.
+struct Comparison {
+  throughput_change: f64,
+  p99_change: f64,
+}
+
+impl Comparison {
+  fn new(result: &BenchResult, baseline: &BenchResult) -> Comparison {
+    let change = |value: f64, baseline_value: f64| {
+      (value / baseline_value - 1.0) * 100.0
+    };
+    Comparison {
+      throughput_change: change(result.throughput, baseline.throughput),
+      p99_change: change(result.p99_ms, baseline.p99_ms),
+    }
+  }
+
+  // `regressions` describes each change for the worse of more than
+  // `threshold` percent.
+  fn regressions(&self, threshold: f64) -> Vec<String> {
+    let mut regressions = vec![];
+    if -self.throughput_change > threshold {
+      regressions.push(format!(
+        "throughput fell {:.1}% below the baseline",
+        -self.throughput_change
+      ));
+    }
+    if self.p99_change > threshold {
+      regressions.push(format!(
+        "p99 time rose {:.1}% above the baseline",
+        self.p99_change
+      ));
+    }
+    regressions
+  }
+}
+
+fn parse_corpus(text: &str) -> Vec<SudokuGrid> {
+  PuzzleLineReader::new(text.as_bytes())
+    .map(|puzzle_line| {
+      puzzle_line.expect("the bundled puzzle sets parse").grid
+    })
+    .collect()
+}
+
+// `read_baseline` reads a file written by `--save-baseline`. Problems
+// are reported, and turned into the exit code.
+fn read_baseline(path: &str) -> Result<Vec<BenchResult>, i32> {
+  let text = fs::read_to_string(path).map_err(|error| {
+    eprintln!("error: could not read {}: {}", path, error);
+    EXIT_IO_ERROR
+  })?;
+  let mut baseline = vec![];
+  for (line_idx, line) in text.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    match BenchResult::from_baseline_line(line) {
+      Some(result) => baseline.push(result),
+      None => {
+        eprintln!(
+          "error: {}:{}: not a line of `sudoku bench` results",
+          path,
+          line_idx + 1
+        );
+        return Err(EXIT_MALFORMED_INPUT);
+      }
+    }
+  }
+  Ok(baseline)
+}
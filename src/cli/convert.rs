@@ -0,0 +1,33 @@
+use super::json::Report;
+use super::options::{Options, COMMON_FLAGS};
+use super::puzzles::{read_puzzles, Output, Status};
+
+const USAGE: &str = "\
+usage: sudoku convert [OPTIONS] [FILE...]
+
+Rewrites each puzzle in the `--to` format, without solving it.
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}\n{}", USAGE, COMMON_FLAGS);
+  let options = Options::parse(args, &[], &usage)?;
+
+  let mut output = Output::new(options.output_format);
+  let mut status = Status::new();
+  read_puzzles(
+    &options,
+    &mut output,
+    &mut status,
+    |puzzle, output, _| {
+      output.write_puzzle(&puzzle);
+      output.write_report(&Report::new(
+        &puzzle.source,
+        &puzzle.grid,
+        "converted",
+      ));
+    },
+  );
+  output.flush();
+
+  Ok(status.exit_code())
+}
@@ -0,0 +1,98 @@
+use super::json::Report;
+use super::options::{usage_error, Options, COMMON_FLAGS};
+use super::puzzles::{read_puzzles, Output, Status};
+use super::{EXIT_FAILURE, EXIT_MULTIPLE_SOLUTIONS};
+use sudoku_solving::solver::{SolutionCount, SudokuSolver};
+
+const USAGE: &str = "\
+usage: sudoku count [OPTIONS] [FILE...]
+
+Counts the solutions of each puzzle, printing one line per puzzle:
+`SOURCE: N`, or `SOURCE: at least N` when counting stopped at the
+limit, or `SOURCE: at least N, hit the node limit` when the solver gave
+up. Exits with status 1 if a puzzle has no solution (or none was found),
+and 3 if one may have more than one.
+
+  --limit N       stop counting at N solutions (default 1000)
+  --node-limit N  give up after the solver makes N choices
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let options =
+    Options::parse(args, &["--limit", "--node-limit"], &usage)?;
+  let max_solutions = options.flag("--limit", 1000, &usage)?;
+  let max_nodes = match options.flag("--node-limit", 0, &usage)? {
+    0 => None,
+    max_nodes => Some(max_nodes),
+  };
+  if max_solutions == 0 {
+    return Err(usage_error("--limit counts from 1", &usage));
+  }
+
+  let mut output = Output::new(options.output_format);
+  let mut status = Status::new();
+  read_puzzles(
+    &options,
+    &mut output,
+    &mut status,
+    |puzzle, output, status| {
+      let solution_count = SudokuSolver::count_solutions(
+        &puzzle.grid.filled_choices(),
+        max_solutions,
+        max_nodes,
+      );
+      let num_solutions = solution_count.num_solutions();
+      let count_text = match solution_count {
+        SolutionCount::Exactly(_) => num_solutions.to_string(),
+        SolutionCount::AtLeast(_) => {
+          format!("at least {}", num_solutions)
+        }
+        SolutionCount::NodeLimitReached(_) => {
+          format!("at least {}, hit the node limit", num_solutions)
+        }
+      };
+      output.write_line(&format!("{}: {}", puzzle.source, count_text));
+
+      // Like `sudoku batch`, a puzzle whose solution may not be the
+      // only one fails with EXIT_MULTIPLE_SOLUTIONS, and one with no
+      // known solution with EXIT_FAILURE. Stopping at `--limit 1` was
+      // asked for, so it doesn't count as a failure.
+      let (report_status, error) = match solution_count {
+        SolutionCount::Exactly(0) => {
+          ("unsolvable", Some("no solution"))
+        }
+        SolutionCount::Exactly(1) | SolutionCount::AtLeast(1) => {
+          ("solved", None)
+        }
+        SolutionCount::NodeLimitReached(0) => {
+          ("node_limit_reached", Some("hit the node limit"))
+        }
+        SolutionCount::NodeLimitReached(1) => (
+          "node_limit_reached",
+          Some("hit the node limit checking the solution is unique"),
+        ),
+        _ => ("multiple_solutions", Some("more than one solution")),
+      };
+      let mut report =
+        Report::new(&puzzle.source, &puzzle.grid, report_status)
+          .with_stat("num_solutions", num_solutions)
+          .with_stat(
+            "exact",
+            solution_count == SolutionCount::Exactly(num_solutions),
+          );
+      if let Some(error) = error {
+        status.fail(if num_solutions == 0 {
+          EXIT_FAILURE
+        } else {
+          EXIT_MULTIPLE_SOLUTIONS
+        });
+        report = report.with_error(error);
+      }
+      output.write_report(&report);
+    },
+  );
+  output.flush();
+
+  Ok(status.exit_code())
+}
@@ -0,0 +1,705 @@
+use super::options::{
+  is_supported_size, usage_error, Options, COMMON_FLAGS,
+};
+use super::puzzles::parse_puzzle;
+use super::{EXIT_IO_ERROR, EXIT_SUCCESS};
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use sudoku_solving::{
+  core::{SudokuChoice, SudokuGrid, SudokuPosition, SUDOKU_DIM_USIZE},
+  format::{GridFormatter, GridLayout, SymbolSet},
+  game::{GameHint, SudokuGame},
+  solver::{SearchControl, SolverEvent, SolverResult, SudokuSolver},
+};
+
+const USAGE: &str = "\
+usage: sudoku engine
+
+Runs the solver as an engine for front ends, which start it once and
+then talk to it over standard input and output, one command per line,
+much like chess engines speak UCI. A search runs in the background, so
+the engine keeps listening: `stop` cuts it short, and `isready` is
+answered at once.
+
+Commands:
+  engine                  answered with `id` lines, then `engineok`
+  isready                 answered with `readyok`
+  size N                  set the grid size; this build handles 16 only
+  position PUZZLE [moves MOVE...]
+                          load a puzzle, written on one line, and the
+                          entries made on it so far. A MOVE is like
+                          `r3c12=A`: row 3, column 12 holds A.
+  go [nodes N] [movetime MS] [solutions N]
+                          search the position for up to N solutions
+                          (default 2, enough to tell a unique one)
+  hint [nodes N] [movetime MS]
+                          find an entry to fill in or take back
+  stop                    stop the search
+  quit                    stop the search, and exit
+
+While searching, the engine sends `info nodes N backtracks N filled N
+time MS nps N` lines. `go` sends a `solution GRID` line per solution
+found; `hint` sends `hint MOVE`, or `wrong rRcC` for an entry that
+doesn't belong. Either ends with one `done STATUS` line, the status
+being one of solved, multiple_solutions, unsolvable, hint, wrong_entry,
+no_hint, node_limit_reached, time_limit_reached and stopped. Commands
+that fail are answered with `error MESSAGE`.
+";
+
+// How often a search sends an `info` line.
+const INFO_INTERVAL: Duration = Duration::from_millis(250);
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}\n{}", USAGE, COMMON_FLAGS);
+  let options = Options::parse(args, &[], &usage)?;
+  if options.paths != ["-"] {
+    return Err(usage_error("engine takes no files", &usage));
+  }
+
+  let mut engine = Engine::new(Replies::stdout());
+  let stdin = io::stdin();
+  for line in stdin.lock().lines() {
+    let line = match line {
+      Ok(line) => line,
+      Err(error) => {
+        eprintln!("error: could not read commands: {}", error);
+        engine.stop();
+        return Ok(EXIT_IO_ERROR);
+      }
+    };
+    if !engine.handle(&line) {
+      engine.stop();
+      return Ok(EXIT_SUCCESS);
+    }
+  }
+  // Scripts may send their commands and close our input straight away,
+  // so a search still running then is let finish.
+  engine.wait();
+
+  Ok(EXIT_SUCCESS)
+}
+
+// Engine is the state kept between commands.
+struct Engine {
+  position: Option<Position>,
+  search: Option<RunningSearch>,
+  replies: Replies,
+}
+
+// Position is a puzzle and the entries made on it.
+struct Position {
+  puzzle: SudokuGrid,
+  moves: Vec<SudokuChoice>,
+}
+
+struct RunningSearch {
+  is_stopped: Arc<AtomicBool>,
+  thread: JoinHandle<()>,
+}
+
+impl Engine {
+  fn new(replies: Replies) -> Engine {
+    Engine {
+      position: None,
+      search: None,
+      replies,
+    }
+  }
+
+  // `handle` runs a command, and returns false when it's time to quit.
+  fn handle(&mut self, line: &str) -> bool {
+    if self
+      .search
+      .as_ref()
+      .is_some_and(|search| search.thread.is_finished())
+    {
+      self.stop();
+    }
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (command, args) = match words.split_first() {
+      None => return true,
+      Some((command, args)) => (*command, args),
+    };
+    let result = match command {
+      "engine" => {
+        self.replies.send(&format!(
+          "id name sudoku_solving {}",
+          env!("CARGO_PKG_VERSION")
+        ));
+        self.replies.send(&format!("id size {}", SUDOKU_DIM_USIZE));
+        self.replies.send("engineok");
+        Ok(())
+      }
+      "isready" => {
+        self.replies.send("readyok");
+        Ok(())
+      }
+      "size" => set_size(args),
+      "position" => self.set_position(args),
+      "go" => self.go(args),
+      "hint" => self.hint(args),
+      "stop" => {
+        self.stop();
+        Ok(())
+      }
+      "quit" => return false,
+      _ => Err(format!("unknown command {:?}", command)),
+    };
+    if let Err(message) = result {
+      self.replies.send(&format!("error {}", message));
+    }
+    true
+  }
+
+  fn set_position(&mut self, args: &[&str]) -> Result<(), String> {
+    self.check_idle()?;
+    let (puzzle_text, args) = match args.split_first() {
+      None => return Err("position needs a puzzle".to_string()),
+      Some((puzzle_text, args)) => (*puzzle_text, args),
+    };
+    let puzzle = parse_puzzle(puzzle_text)?;
+    let moves = match args.split_first() {
+      None => vec![],
+      Some((&"moves", moves)) => moves
+        .iter()
+        .map(|&text| {
+          parse_move(text).ok_or_else(|| format!("bad move {:?}", text))
+        })
+        .collect::<Result<_, _>>()?,
+      Some((word, _)) => return Err(format!("unexpected {:?}", word)),
+    };
+
+    let position = Position { puzzle, moves };
+    // Check the position now, rather than at every search.
+    position.game()?;
+    self.position = Some(position);
+    Ok(())
+  }
+
+  fn go(&mut self, args: &[&str]) -> Result<(), String> {
+    let limits = Limits::parse(args, true)?;
+    let game = self.idle_position()?.game()?;
+    self.start(limits, move |progress| {
+      search_solutions(&game, limits, progress)
+    });
+    Ok(())
+  }
+
+  fn hint(&mut self, args: &[&str]) -> Result<(), String> {
+    let limits = Limits::parse(args, false)?;
+    let game = self.idle_position()?.game()?;
+    self.start(limits, move |progress| {
+      search_hint(&game, limits, progress)
+    });
+    Ok(())
+  }
+
+  // `start` runs `search` in the background, and sends its `done` line
+  // when it is over.
+  fn start<F>(&mut self, limits: Limits, search: F)
+  where
+    F: FnOnce(&mut Progress) -> &'static str + Send + 'static,
+  {
+    let is_stopped = Arc::new(AtomicBool::new(false));
+    let mut progress = Progress::new(
+      limits,
+      Arc::clone(&is_stopped),
+      self.replies.clone(),
+    );
+    let thread = thread::spawn(move || {
+      let status = search(&mut progress);
+      let status = match progress.stop_reason {
+        Some(stop_reason) => stop_reason,
+        None => status,
+      };
+      progress.send_info();
+      progress.replies.send(&format!("done {}", status));
+    });
+    self.search = Some(RunningSearch { is_stopped, thread });
+  }
+
+  // `stop` stops the search, if there is one, and waits until it is
+  // over.
+  fn stop(&mut self) {
+    if let Some(search) = self.search.take() {
+      search.is_stopped.store(true, Ordering::Relaxed);
+      let _ = search.thread.join();
+    }
+  }
+
+  fn wait(&mut self) {
+    if let Some(search) = self.search.take() {
+      let _ = search.thread.join();
+    }
+  }
+
+  fn check_idle(&self) -> Result<(), String> {
+    match self.search {
+      Some(_) => Err("already searching; send stop first".to_string()),
+      None => Ok(()),
+    }
+  }
+
+  fn idle_position(&self) -> Result<&Position, String> {
+    self.check_idle()?;
+    self
+      .position
+      .as_ref()
+      .ok_or_else(|| "no position; send position first".to_string())
+  }
+}
+
+impl Position {
+  fn game(&self) -> Result<SudokuGame, String> {
+    let mut game = SudokuGame::new(&self.puzzle)
+      .ok_or_else(|| "the givens clash".to_string())?;
+    for &choice in &self.moves {
+      if game.is_given(choice.position) {
+        return Err(format!("{} is a given", format_move(choice)));
+      }
+      let _ = game.place(choice.position, choice.value);
+    }
+    Ok(game)
+  }
+}
+
+fn set_size(args: &[&str]) -> Result<(), String> {
+  let size = match args {
+    [size] => *size,
+    _ => return Err("size needs one value".to_string()),
+  };
+  if !is_supported_size(size) {
+    return Err(format!(
+      "unsupported grid size {:?}: this build handles {}x{} grids only",
+      size, SUDOKU_DIM_USIZE, SUDOKU_DIM_USIZE
+    ));
+  }
+  Ok(())
+}
+
+// Limits are what `go` and `hint` are told to stop at.
+#[derive(Clone, Copy)]
+struct Limits {
+  max_nodes: Option<u64>,
+  time_limit: Option<Duration>,
+  max_solutions: usize,
+}
+
+impl Limits {
+  fn parse(
+    args: &[&str],
+    takes_solutions: bool,
+  ) -> Result<Limits, String> {
+    let mut limits = Limits {
+      max_nodes: None,
+      time_limit: None,
+      max_solutions: 2,
+    };
+    for pair in args.chunks(2) {
+      let (name, value) = match pair {
+        [name, value] => (*name, *value),
+        _ => return Err(format!("{} needs a value", pair[0])),
+      };
+      let number: u64 = value
+        .parse()
+        .map_err(|_| format!("bad value {:?} for {}", value, name))?;
+      match name {
+        "nodes" => limits.max_nodes = Some(number),
+        "movetime" => {
+          limits.time_limit = Some(Duration::from_millis(number))
+        }
+        "solutions" if takes_solutions && number > 0 => {
+          limits.max_solutions = number as usize
+        }
+        "solutions" if takes_solutions => {
+          return Err("solutions must be at least 1".to_string())
+        }
+        _ => return Err(format!("unknown limit {:?}", name)),
+      }
+    }
+    Ok(limits)
+  }
+}
+
+// Progress follows a search through the solver's events: it sends
+// `info` lines, and stops the search at the time limit or when asked
+// to.
+struct Progress {
+  is_stopped: Arc<AtomicBool>,
+  replies: Replies,
+  start: Instant,
+  deadline: Option<Instant>,
+  next_info: Instant,
+  num_events: u64,
+  num_nodes: u64,
+  num_backtracks: u64,
+  // The search's entries on top of the position.
+  num_placed: u64,
+  num_filled_before: u64,
+  // Why the search was stopped, as a `done` status.
+  stop_reason: Option<&'static str>,
+}
+
+impl Progress {
+  fn new(
+    limits: Limits,
+    is_stopped: Arc<AtomicBool>,
+    replies: Replies,
+  ) -> Progress {
+    let start = Instant::now();
+    Progress {
+      is_stopped,
+      replies,
+      start,
+      deadline: limits.time_limit.map(|time_limit| start + time_limit),
+      next_info: start + INFO_INTERVAL,
+      num_events: 0,
+      num_nodes: 0,
+      num_backtracks: 0,
+      num_placed: 0,
+      num_filled_before: 0,
+      stop_reason: None,
+    }
+  }
+
+  fn observe(&mut self, event: SolverEvent) -> SearchControl {
+    match event {
+      SolverEvent::Place(_) => {
+        self.num_nodes += 1;
+        self.num_placed += 1;
+      }
+      SolverEvent::Backtrack(_) => {
+        self.num_backtracks += 1;
+        self.num_placed -= 1;
+      }
+    }
+
+    // Looking at the clock after every event would slow the solver
+    // down, so it looks every so often.
+    self.num_events += 1;
+    if self.is_stopped.load(Ordering::Relaxed) {
+      self.stop_reason = Some("stopped");
+    } else if self.num_events.is_multiple_of(1024) {
+      let now = Instant::now();
+      if self.deadline.is_some_and(|deadline| now >= deadline) {
+        self.stop_reason = Some("time_limit_reached");
+      } else if now >= self.next_info {
+        self.send_info();
+        self.next_info = now + INFO_INTERVAL;
+      }
+    }
+
+    match self.stop_reason {
+      Some(_) => SearchControl::Stop,
+      None => SearchControl::Continue,
+    }
+  }
+
+  fn send_info(&self) {
+    let elapsed = self.start.elapsed();
+    let nodes_per_sec =
+      (self.num_nodes as f64 / elapsed.as_secs_f64().max(1e-3)) as u64;
+    self.replies.send(&format!(
+      "info nodes {} backtracks {} filled {} time {} nps {}",
+      self.num_nodes,
+      self.num_backtracks,
+      self.num_filled_before + self.num_placed,
+      elapsed.as_millis(),
+      nodes_per_sec
+    ));
+  }
+}
+
+fn search_solutions(
+  game: &SudokuGame,
+  limits: Limits,
+  progress: &mut Progress,
+) -> &'static str {
+  let grid = game.grid();
+  let given_choices = grid.filled_choices();
+  progress.num_filled_before = given_choices.len() as u64;
+  let mut solver = match SudokuSolver::new(&given_choices) {
+    Some(solver) => solver,
+    None => return "unsolvable",
+  };
+  if let Some(max_nodes) = limits.max_nodes {
+    solver = solver.with_node_limit(max_nodes);
+  }
+
+  let mut num_solutions = 0;
+  while num_solutions < limits.max_solutions {
+    match solver.next_solution_observed(|event| progress.observe(event))
+    {
+      SolverResult::Solved(solution_choices) => {
+        let mut solution = grid.clone();
+        for choice in solution_choices {
+          solution.place(choice);
+        }
+        progress
+          .replies
+          .send(&format!("solution {}", format_line(&solution)));
+        num_solutions += 1;
+      }
+      SolverResult::NoMoreSolutions => break,
+      SolverResult::NodeLimitReached => return "node_limit_reached",
+    }
+  }
+
+  match num_solutions {
+    0 => "unsolvable",
+    1 => "solved",
+    _ => "multiple_solutions",
+  }
+}
+
+fn search_hint(
+  game: &SudokuGame,
+  limits: Limits,
+  progress: &mut Progress,
+) -> &'static str {
+  progress.num_filled_before =
+    game.grid().filled_choices().len() as u64;
+  let max_nodes = limits.max_nodes.unwrap_or(u64::MAX);
+  match game.hint_observed(max_nodes, |event| progress.observe(event)) {
+    Some(GameHint::WrongEntry(position)) => {
+      progress
+        .replies
+        .send(&format!("wrong {}", format_position(position)));
+      "wrong_entry"
+    }
+    Some(GameHint::Value(choice)) => {
+      progress
+        .replies
+        .send(&format!("hint {}", format_move(choice)));
+      "hint"
+    }
+    None if game.is_solved() => "solved",
+    None => "no_hint",
+  }
+}
+
+// `parse_move` reads a move like `r3c12=A`. The REPL writes moves the
+// same way.
+pub fn parse_move(text: &str) -> Option<SudokuChoice> {
+  let (position_text, symbol_text) = text.split_once('=')?;
+  let position = parse_position(position_text)?;
+  let mut symbols = symbol_text.chars();
+  let value = match (symbols.next(), symbols.next()) {
+    (Some(symbol), None) => SymbolSet::default().value(symbol)?,
+    _ => return None,
+  };
+
+  Some(SudokuChoice::new(position, value))
+}
+
+// `parse_position` reads a position like `r3c12`; rows and columns
+// count from 1.
+pub fn parse_position(text: &str) -> Option<SudokuPosition> {
+  let (row_text, col_text) = text.strip_prefix('r')?.split_once('c')?;
+  let row_idx: usize = row_text.parse().ok()?;
+  let col_idx: usize = col_text.parse().ok()?;
+  let is_in_grid = |idx| (1..=SUDOKU_DIM_USIZE).contains(&idx);
+  if !is_in_grid(row_idx) || !is_in_grid(col_idx) {
+    return None;
+  }
+
+  Some(SudokuPosition::new(
+    (row_idx - 1) as u8,
+    (col_idx - 1) as u8,
+  ))
+}
+
+pub fn format_move(choice: SudokuChoice) -> String {
+  format!(
+    "{}={}",
+    format_position(choice.position),
+    SymbolSet::default().symbol(choice.value)
+  )
+}
+
+pub fn format_position(position: SudokuPosition) -> String {
+  let (row_idx, col_idx) = position.as_usize_pair();
+  format!("r{}c{}", row_idx + 1, col_idx + 1)
+}
+
+fn format_line(grid: &SudokuGrid) -> String {
+  GridFormatter::new()
+    .with_layout(GridLayout::SingleLine)
+    .with_symbols(SymbolSet::default())
+    .format(grid)
+    .trim_end()
+    .to_string()
+}
+
+// Replies is where the lines to the front end go: standard output, or
+// a buffer in tests. Searches send from their own thread.
+#[derive(Clone)]
+struct Replies(Arc<Mutex<dyn Write + Send>>);
+
+impl Replies {
+  fn stdout() -> Replies {
+    Replies(Arc::new(Mutex::new(io::stdout())))
+  }
+
+  // `send` writes a line to the front end. Once it has gone, there is
+  // no point going on.
+  fn send(&self, line: &str) {
+    let mut out = self.0.lock().unwrap();
+    if writeln!(out, "{}", line).and_then(|_| out.flush()).is_err() {
+      process::exit(EXIT_IO_ERROR);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::SmallRng;
+  use rand::SeedableRng;
+
+  // `run_script` feeds commands to an engine the way `run` does, and
+  // returns the lines it sent. `info` lines are left out, since how
+  // many there are depends on timing.
+  fn run_script(commands: &[&str]) -> Vec<String> {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let mut engine = Engine::new(Replies(buffer.clone()));
+    if commands.iter().all(|command| engine.handle(command)) {
+      engine.wait();
+    } else {
+      engine.stop();
+    }
+
+    let text =
+      String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+    text
+      .lines()
+      .filter(|line| !line.starts_with("info "))
+      .map(str::to_string)
+      .collect()
+  }
+
+  // `unique_puzzle` is a solution grid and, with its top row blanked,
+  // a puzzle whose only solution it is.
+  fn unique_puzzle() -> (SudokuGrid, SudokuGrid) {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let solution = SudokuGrid::from_choices(
+      &SudokuSolver::solve_randomly(&[], &mut rng).unwrap(),
+    );
+    let mut puzzle = solution.clone();
+    for position in &SudokuPosition::all()[..SUDOKU_DIM_USIZE] {
+      puzzle.clear(*position);
+    }
+    (solution, puzzle)
+  }
+
+  #[test]
+  fn answers_handshakes() {
+    let replies = run_script(&["engine", "isready", "size 16"]);
+    assert_eq!(
+      replies,
+      vec![
+        format!("id name sudoku_solving {}", env!("CARGO_PKG_VERSION")),
+        "id size 16".to_string(),
+        "engineok".to_string(),
+        "readyok".to_string(),
+      ]
+    );
+  }
+
+  #[test]
+  fn solves_and_hints_a_position() {
+    let (solution, puzzle) = unique_puzzle();
+    let top_left = SudokuPosition::new(0, 0);
+    let answer = solution.filled_choices()[0];
+    let position = format!("position {}", format_line(&puzzle));
+    let replies = run_script(&[&position, "go"]);
+    assert_eq!(
+      replies,
+      vec![
+        format!("solution {}", format_line(&solution)),
+        "done solved".to_string(),
+      ]
+    );
+
+    let replies = run_script(&[&position, "hint"]);
+    assert_eq!(
+      replies,
+      vec![format!("hint {}", format_move(answer)), "done hint".into()]
+    );
+
+    // Entering the top right cell's value in the top left cell is
+    // wrong.
+    let wrong = SudokuChoice::new(
+      top_left,
+      solution.filled_choices()[SUDOKU_DIM_USIZE - 1].value,
+    );
+    let moves = format!("{} moves {}", position, format_move(wrong));
+    let replies = run_script(&[&moves, "hint"]);
+    assert_eq!(replies, vec!["wrong r1c1", "done wrong_entry"]);
+  }
+
+  #[test]
+  fn stop_cuts_a_search_short() {
+    let empty = format_line(&SudokuGrid::default());
+    let position = format!("position {}", empty);
+    let replies = run_script(&[
+      &position,
+      "go solutions 1000000",
+      "position -",
+      "stop",
+      "isready",
+    ]);
+
+    // The search may send solutions at any point before it stops.
+    let rest: Vec<&String> = replies
+      .iter()
+      .filter(|line| !line.starts_with("solution "))
+      .collect();
+    assert_eq!(
+      rest,
+      vec![
+        "error already searching; send stop first",
+        "done stopped",
+        "readyok",
+      ]
+    );
+  }
+
+  #[test]
+  fn reports_bad_commands() {
+    let (_, puzzle) = unique_puzzle();
+    let position = format!("position {}", format_line(&puzzle));
+    let replies = run_script(&[
+      "go",
+      "frob",
+      "size 9",
+      "size 16x9",
+      &format!("{} moves r1c17=1", position),
+      &format!("{} after r1c1=1", position),
+      "go nodes",
+      "go solutions 0",
+      "quit",
+      "isready",
+    ]);
+    assert_eq!(
+      replies,
+      vec![
+        "error no position; send position first",
+        "error unknown command \"frob\"",
+        "error unsupported grid size \"9\": this build handles 16x16 \
+         grids only",
+        "error unsupported grid size \"16x9\": this build handles \
+         16x16 grids only",
+        "error bad move \"r1c17=1\"",
+        "error unexpected \"after\"",
+        "error nodes needs a value",
+        "error solutions must be at least 1",
+      ]
+    );
+  }
+}
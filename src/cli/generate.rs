@@ -0,0 +1,105 @@
+use super::json::Report;
+use super::options::{usage_error, Options, COMMON_FLAGS};
+use super::puzzles::{Output, Puzzle};
+use super::{EXIT_FAILURE, EXIT_SUCCESS};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use sudoku_solving::{
+  core::{
+    SudokuClueMask, SudokuGrid, SudokuPosition, SUDOKU_DIM_USIZE,
+  },
+  format::SdkMetadata,
+  generator::{GeneratorBudget, SudokuGenerator},
+  mutation::{MutationGoal, MutationSearch},
+};
+
+const USAGE: &str = "\
+usage: sudoku generate [OPTIONS]
+
+Generates puzzles with a unique solution, by drawing random solution
+grids and keeping the values of randomly chosen cells.
+
+  --count N         how many puzzles to generate (default 1)
+  --clues N         how many cells to keep (default 160)
+  --minimize STEPS  then try STEPS mutations to remove clues
+  --node-limit N    solver choices allowed when drawing a solution grid,
+                    and when checking a candidate is unique (default
+                    100000)
+  --seed N          seed the random choices, for repeatable output
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let command_flags =
+    ["--count", "--clues", "--minimize", "--node-limit", "--seed"];
+  let options = Options::parse(args, &command_flags, &usage)?;
+  if options.paths != ["-"] {
+    return Err(usage_error("generate takes no files", &usage));
+  }
+  let num_puzzles = options.flag("--count", 1, &usage)?;
+  let num_clues = options.flag("--clues", 160, &usage)?;
+  let num_minimize_steps = options.flag("--minimize", 0, &usage)?;
+  let max_nodes = options.flag("--node-limit", 100_000, &usage)?;
+  let seed =
+    options.flag("--seed", rand::thread_rng().gen(), &usage)?;
+  if num_clues > SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE {
+    return Err(usage_error(
+      "--clues is more than the number of cells",
+      &usage,
+    ));
+  }
+
+  let mut rng = StdRng::seed_from_u64(seed);
+  let budget = GeneratorBudget {
+    max_nodes_per_attempt: max_nodes,
+    ..GeneratorBudget::default()
+  };
+  let mut output = Output::new(options.output_format);
+  for _ in 0..num_puzzles {
+    let mut positions = SudokuPosition::all();
+    positions.shuffle(&mut rng);
+    let mask = SudokuClueMask::from_positions(&positions[..num_clues]);
+
+    let mut given_choices =
+      match SudokuGenerator::fill_clue_mask(&mask, budget, &mut rng) {
+        Some(given_choices) => given_choices,
+        None => {
+          let error =
+            format!("no puzzle with {} clues found", num_clues);
+          eprintln!("error: {}; try more clues", error);
+          output.write_report(&Report::without_puzzle(
+            "generated",
+            "failed",
+            &error,
+          ));
+          output.flush();
+          return Ok(EXIT_FAILURE);
+        }
+      };
+    if num_minimize_steps > 0 {
+      let search = MutationSearch::new(MutationGoal::FewerClues)
+        .with_max_steps(num_minimize_steps)
+        .with_node_limit(max_nodes);
+      if let Some(result) = search.search(&given_choices, &mut rng) {
+        given_choices = result.puzzle;
+      }
+    }
+
+    let puzzle = Puzzle {
+      source: "generated".to_string(),
+      grid: SudokuGrid::from_choices(&given_choices),
+      metadata: SdkMetadata::default(),
+      pencil_marks: None,
+    };
+    output.write_puzzle(&puzzle);
+    output.write_report(
+      &Report::new(&puzzle.source, &puzzle.grid, "generated")
+        .with_stat("num_clues", given_choices.len())
+        .with_stat("seed", seed),
+    );
+  }
+  output.flush();
+
+  Ok(EXIT_SUCCESS)
+}
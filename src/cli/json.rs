@@ -0,0 +1,565 @@
+use std::fmt::{self, Display, Write};
+use std::time::Duration;
+use sudoku_solving::{
+  core::SudokuGrid,
+  format::{GridFormatter, GridLayout, SymbolSet},
+};
+
+// Report is what a command found out about one puzzle, written as one
+// line of JSON by `--to json`:
+//
+//   {"source": "in.txt:3", "input": "1.3...", "status": "solved",
+//    "solutions": ["123..."], "stats": {"num_nodes": 312},
+//    "errors": []}
+//
+// Every report has all six keys, so that tools like `jq` can rely on
+// them. Grids are written on one line, like `--to line` does; `input`
+// is null when there was no puzzle to read.
+pub struct Report {
+  source: String,
+  input: Option<SudokuGrid>,
+  status: &'static str,
+  solutions: Vec<SudokuGrid>,
+  // The values are already JSON.
+  stats: Vec<(&'static str, String)>,
+  errors: Vec<String>,
+}
+
+impl Report {
+  pub fn new(
+    source: &str,
+    input: &SudokuGrid,
+    status: &'static str,
+  ) -> Report {
+    Report {
+      source: source.to_string(),
+      input: Some(input.clone()),
+      status,
+      solutions: vec![],
+      stats: vec![],
+      errors: vec![],
+    }
+  }
+
+  // `without_puzzle` reports a failure with no puzzle to show, like an
+  // input that couldn't be read.
+  pub fn without_puzzle(
+    source: &str,
+    status: &'static str,
+    error: &str,
+  ) -> Report {
+    Report::about_run(source, status).with_error(error)
+  }
+
+  // `about_run` reports on work that wasn't about one puzzle, like a
+  // benchmark of a whole puzzle set.
+  pub fn about_run(source: &str, status: &'static str) -> Report {
+    Report {
+      source: source.to_string(),
+      input: None,
+      status,
+      solutions: vec![],
+      stats: vec![],
+      errors: vec![],
+    }
+  }
+
+  pub fn with_solution(mut self, solution: &SudokuGrid) -> Report {
+    self.solutions.push(solution.clone());
+    self
+  }
+
+  // `with_stat` adds a number (or a boolean) to the stats.
+  pub fn with_stat<V: Display>(
+    mut self,
+    name: &'static str,
+    value: V,
+  ) -> Report {
+    self.stats.push((name, value.to_string()));
+    self
+  }
+
+  // `with_duration` adds how long the work took, as `time_ms`.
+  pub fn with_duration(self, duration: Duration) -> Report {
+    let millis = duration.as_secs_f64() * 1000.0;
+    self.with_stat("time_ms", format!("{:.3}", millis))
+  }
+
+  pub fn with_error(mut self, error: &str) -> Report {
+    self.errors.push(error.to_string());
+    self
+  }
+
+  pub fn to_json(&self) -> String {
+    let mut json = String::new();
+    json.push_str("{\"source\": ");
+    push_string(&mut json, &self.source);
+    json.push_str(", \"input\": ");
+    match &self.input {
+      None => json.push_str("null"),
+      Some(input) => push_string(&mut json, &format_line(input)),
+    }
+    json.push_str(", \"status\": ");
+    push_string(&mut json, self.status);
+
+    json.push_str(", \"solutions\": [");
+    for (idx, solution) in self.solutions.iter().enumerate() {
+      if idx > 0 {
+        json.push_str(", ");
+      }
+      push_string(&mut json, &format_line(solution));
+    }
+    json.push_str("], \"stats\": {");
+    for (idx, (name, value)) in self.stats.iter().enumerate() {
+      if idx > 0 {
+        json.push_str(", ");
+      }
+      push_string(&mut json, name);
+      json.push_str(": ");
+      json.push_str(value);
+    }
+    json.push_str("}, \"errors\": [");
+    for (idx, error) in self.errors.iter().enumerate() {
+      if idx > 0 {
+        json.push_str(", ");
+      }
+      push_string(&mut json, error);
+    }
+    json.push_str("]}");
+
+    json
+  }
+}
+
+fn format_line(grid: &SudokuGrid) -> String {
+  GridFormatter::new()
+    .with_layout(GridLayout::SingleLine)
+    .with_symbols(SymbolSet::default())
+    .format(grid)
+    .trim_end()
+    .to_string()
+}
+
+// `push_string` writes `text` as a JSON string.
+fn push_string(json: &mut String, text: &str) {
+  json.push('"');
+  for c in text.chars() {
+    match c {
+      '"' => json.push_str("\\\""),
+      '\\' => json.push_str("\\\\"),
+      '\n' => json.push_str("\\n"),
+      '\r' => json.push_str("\\r"),
+      '\t' => json.push_str("\\t"),
+      c if (c as u32) < 0x20 => {
+        write!(json, "\\u{:04x}", c as u32).unwrap()
+      }
+      c => json.push(c),
+    }
+  }
+  json.push('"');
+}
+
+// JsonValue is a parsed JSON document, like the body of a `sudoku
+// serve` request. Object keys keep their order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<JsonValue>),
+  Object(Vec<(String, JsonValue)>),
+}
+
+// JsonError says where a JSON document stops making sense, as a byte
+// offset.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonError {
+  pub offset: usize,
+  pub message: &'static str,
+}
+
+impl fmt::Display for JsonError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "bad JSON at byte {}: {}", self.offset, self.message)
+  }
+}
+
+impl JsonValue {
+  pub fn parse(text: &str) -> Result<JsonValue, JsonError> {
+    let mut parser = JsonParser {
+      bytes: text.as_bytes(),
+      offset: 0,
+    };
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.offset < parser.bytes.len() {
+      return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+  }
+
+  // `get` is the value of a key, for an object.
+  pub fn get(&self, key: &str) -> Option<&JsonValue> {
+    match self {
+      JsonValue::Object(members) => members
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      JsonValue::String(text) => Some(text),
+      _ => None,
+    }
+  }
+
+  // `as_u64` is the value of a whole, non-negative number.
+  pub fn as_u64(&self) -> Option<u64> {
+    match *self {
+      JsonValue::Number(number)
+        if number >= 0.0 && number.fract() == 0.0 && number < 2e19 =>
+      {
+        Some(number as u64)
+      }
+      _ => None,
+    }
+  }
+}
+
+// How deeply arrays and objects may nest, so that a hostile document
+// can't overflow the stack.
+const MAX_JSON_DEPTH: usize = 64;
+
+struct JsonParser<'a> {
+  bytes: &'a [u8],
+  offset: usize,
+}
+
+impl<'a> JsonParser<'a> {
+  fn parse_value(
+    &mut self,
+    depth: usize,
+  ) -> Result<JsonValue, JsonError> {
+    if depth > MAX_JSON_DEPTH {
+      return Err(self.error("nested too deeply"));
+    }
+    self.skip_whitespace();
+    match self.peek() {
+      Some(b'{') => self.parse_object(depth),
+      Some(b'[') => self.parse_array(depth),
+      Some(b'"') => self.parse_string().map(JsonValue::String),
+      Some(b't') => self.parse_literal("true", JsonValue::Bool(true)),
+      Some(b'f') => self.parse_literal("false", JsonValue::Bool(false)),
+      Some(b'n') => self.parse_literal("null", JsonValue::Null),
+      Some(b'-') | Some(b'0'..=b'9') => self.parse_number(),
+      Some(_) => Err(self.error("expected a value")),
+      None => Err(self.error("unexpected end")),
+    }
+  }
+
+  fn parse_object(
+    &mut self,
+    depth: usize,
+  ) -> Result<JsonValue, JsonError> {
+    self.offset += 1;
+    let mut members = vec![];
+    self.skip_whitespace();
+    if self.peek() == Some(b'}') {
+      self.offset += 1;
+      return Ok(JsonValue::Object(members));
+    }
+    loop {
+      self.skip_whitespace();
+      if self.peek() != Some(b'"') {
+        return Err(self.error("expected a key"));
+      }
+      let key = self.parse_string()?;
+      self.skip_whitespace();
+      self.expect(b':', "expected `:`")?;
+      let value = self.parse_value(depth + 1)?;
+      members.push((key, value));
+
+      self.skip_whitespace();
+      match self.peek() {
+        Some(b',') => self.offset += 1,
+        Some(b'}') => {
+          self.offset += 1;
+          return Ok(JsonValue::Object(members));
+        }
+        _ => return Err(self.error("expected `,` or `}`")),
+      }
+    }
+  }
+
+  fn parse_array(
+    &mut self,
+    depth: usize,
+  ) -> Result<JsonValue, JsonError> {
+    self.offset += 1;
+    let mut values = vec![];
+    self.skip_whitespace();
+    if self.peek() == Some(b']') {
+      self.offset += 1;
+      return Ok(JsonValue::Array(values));
+    }
+    loop {
+      values.push(self.parse_value(depth + 1)?);
+      self.skip_whitespace();
+      match self.peek() {
+        Some(b',') => self.offset += 1,
+        Some(b']') => {
+          self.offset += 1;
+          return Ok(JsonValue::Array(values));
+        }
+        _ => return Err(self.error("expected `,` or `]`")),
+      }
+    }
+  }
+
+  fn parse_string(&mut self) -> Result<String, JsonError> {
+    self.offset += 1;
+    let mut bytes = vec![];
+    loop {
+      let byte = match self.peek() {
+        None => return Err(self.error("unterminated string")),
+        Some(byte) => byte,
+      };
+      self.offset += 1;
+      match byte {
+        b'"' => break,
+        b'\\' => {
+          let escape = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated string"))?;
+          self.offset += 1;
+          let unescaped = match escape {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => self.parse_unicode_escape()?,
+            _ => return Err(self.error("bad escape")),
+          };
+          let mut utf8 = [0; 4];
+          bytes.extend_from_slice(
+            unescaped.encode_utf8(&mut utf8).as_bytes(),
+          );
+        }
+        byte if byte < 0x20 => {
+          return Err(self.error("control character in string"))
+        }
+        byte => bytes.push(byte),
+      }
+    }
+    // The input was a &str, and escapes are whole characters, so this
+    // is still UTF-8.
+    Ok(String::from_utf8(bytes).unwrap())
+  }
+
+  // `parse_unicode_escape` reads the digits after `\u`, and the low
+  // half of a surrogate pair after them.
+  fn parse_unicode_escape(&mut self) -> Result<char, JsonError> {
+    let high = self.parse_hex4()?;
+    if !(0xd800..0xdc00).contains(&high) {
+      return char::from_u32(high)
+        .ok_or_else(|| self.error("bad \\u escape"));
+    }
+    if self.bytes[self.offset..].starts_with(b"\\u") {
+      self.offset += 2;
+      let low = self.parse_hex4()?;
+      if (0xdc00..0xe000).contains(&low) {
+        let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+        return char::from_u32(code)
+          .ok_or_else(|| self.error("bad \\u escape"));
+      }
+    }
+    Err(self.error("unpaired surrogate"))
+  }
+
+  fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+    let digits = self
+      .bytes
+      .get(self.offset..self.offset + 4)
+      .and_then(|digits| std::str::from_utf8(digits).ok())
+      .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+      .ok_or_else(|| self.error("bad \\u escape"))?;
+    self.offset += 4;
+    Ok(digits)
+  }
+
+  fn parse_number(&mut self) -> Result<JsonValue, JsonError> {
+    let start = self.offset;
+    while let Some(byte) = self.peek() {
+      match byte {
+        b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9' => {
+          self.offset += 1
+        }
+        _ => break,
+      }
+    }
+    // Rust accepts a little more than JSON does, like a leading `+` in
+    // the exponent's place; that does no harm.
+    std::str::from_utf8(&self.bytes[start..self.offset])
+      .unwrap()
+      .parse()
+      .map(JsonValue::Number)
+      .map_err(|_| JsonError {
+        offset: start,
+        message: "bad number",
+      })
+  }
+
+  fn parse_literal(
+    &mut self,
+    literal: &str,
+    value: JsonValue,
+  ) -> Result<JsonValue, JsonError> {
+    if !self.bytes[self.offset..].starts_with(literal.as_bytes()) {
+      return Err(self.error("expected a value"));
+    }
+    self.offset += literal.len();
+    Ok(value)
+  }
+
+  fn expect(
+    &mut self,
+    byte: u8,
+    message: &'static str,
+  ) -> Result<(), JsonError> {
+    if self.peek() != Some(byte) {
+      return Err(self.error(message));
+    }
+    self.offset += 1;
+    Ok(())
+  }
+
+  fn skip_whitespace(&mut self) {
+    while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') =
+      self.peek()
+    {
+      self.offset += 1;
+    }
+  }
+
+  fn peek(&self) -> Option<u8> {
+    self.bytes.get(self.offset).cloned()
+  }
+
+  fn error(&self, message: &'static str) -> JsonError {
+    JsonError {
+      offset: self.offset,
+      message,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reports_have_every_key() {
+    let json = Report::about_run("bench", "done").to_json();
+    assert_eq!(
+      json,
+      "{\"source\": \"bench\", \"input\": null, \"status\": \"done\", \
+       \"solutions\": [], \"stats\": {}, \"errors\": []}"
+    );
+
+    let grid = SudokuGrid::default();
+    let json = Report::new("in.txt:3", &grid, "solved")
+      .with_solution(&grid)
+      .with_stat("num_nodes", 312)
+      .with_stat("exact", true)
+      .to_json();
+    let line = ".".repeat(256);
+    assert_eq!(
+      json,
+      format!(
+        "{{\"source\": \"in.txt:3\", \"input\": \"{}\", \
+         \"status\": \"solved\", \"solutions\": [\"{}\"], \
+         \"stats\": {{\"num_nodes\": 312, \"exact\": true}}, \
+         \"errors\": []}}",
+        line, line
+      )
+    );
+  }
+
+  #[test]
+  fn escapes_strings() {
+    let mut json = String::new();
+    push_string(&mut json, "a \"b\" \\ c\nd\te\u{1}\u{e9}");
+    assert_eq!(json, "\"a \\\"b\\\" \\\\ c\\nd\\te\\u0001\u{e9}\"");
+
+    let report =
+      Report::without_puzzle("C:\\in \"1\".txt", "error", "")
+        .with_error("line 1:\tbad\r\n");
+    let value = JsonValue::parse(&report.to_json()).unwrap();
+    assert_eq!(
+      value.get("source").and_then(JsonValue::as_str),
+      Some("C:\\in \"1\".txt")
+    );
+    assert_eq!(
+      value.get("errors"),
+      Some(&JsonValue::Array(vec![
+        JsonValue::String(String::new()),
+        JsonValue::String("line 1:\tbad\r\n".to_string()),
+      ]))
+    );
+  }
+
+  #[test]
+  fn parses_documents() {
+    let value = JsonValue::parse(
+      " {\"puzzle\": \"1.\", \"limit\": 2, \"flags\": [true, null], \
+       \"escaped\": \"\\u00e9\\ud83e\\udde9\\/\"} ",
+    )
+    .unwrap();
+    assert_eq!(
+      value.get("puzzle").and_then(JsonValue::as_str),
+      Some("1.")
+    );
+    assert_eq!(value.get("limit").and_then(JsonValue::as_u64), Some(2));
+    assert_eq!(
+      value.get("flags"),
+      Some(&JsonValue::Array(vec![
+        JsonValue::Bool(true),
+        JsonValue::Null,
+      ]))
+    );
+    assert_eq!(
+      value.get("escaped").and_then(JsonValue::as_str),
+      Some("\u{e9}\u{1f9e9}/")
+    );
+    assert_eq!(JsonValue::Number(-1.0).as_u64(), None);
+    assert_eq!(JsonValue::Number(1.5).as_u64(), None);
+  }
+
+  #[test]
+  fn reports_where_documents_go_wrong() {
+    let error = |text: &str| JsonValue::parse(text).unwrap_err();
+    assert_eq!(
+      error("{\"a\" 1}"),
+      JsonError {
+        offset: 5,
+        message: "expected `:`",
+      }
+    );
+    assert_eq!(error("[1, 2] x").message, "trailing characters");
+    assert_eq!(error("\"abc").message, "unterminated string");
+    assert_eq!(error("\"\\ud83e\"").message, "unpaired surrogate");
+    assert_eq!(
+      error("\"a\nb\"").message,
+      "control character in string"
+    );
+    assert_eq!(error(&"[".repeat(100)).message, "nested too deeply");
+  }
+}
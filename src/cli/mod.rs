@@ -0,0 +1,94 @@
+mod batch;
+mod bench;
+mod convert;
+mod count;
+mod engine;
+mod generate;
+mod json;
+mod options;
+mod play;
+mod puzzles;
+mod rate;
+mod repl;
+mod serve;
+mod solve;
+mod terminal;
+mod validate;
+mod watch;
+
+// Exit codes shared by the commands.
+pub const EXIT_SUCCESS: i32 = 0;
+// The command's answer is "no": a puzzle is unsolvable, a grid is
+// invalid, no puzzle could be generated...
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_MALFORMED_INPUT: i32 = 2;
+pub const EXIT_MULTIPLE_SOLUTIONS: i32 = 3;
+// These two come from BSD's sysexits.h.
+pub const EXIT_USAGE: i32 = 64;
+pub const EXIT_IO_ERROR: i32 = 74;
+
+const USAGE: &str = "\
+usage: sudoku COMMAND [OPTIONS] [FILE...]
+
+Commands:
+  solve     solve puzzles and print their solutions
+  batch     solve puzzles on all cores, with a summary of the run
+  count     count the solutions of puzzles, up to a limit
+  generate  generate new puzzles
+  rate      rate how hard puzzles are
+  validate  check that grids are completely and correctly filled
+  convert   rewrite puzzles in another format
+  play      play a puzzle in the terminal
+  repl      explore a puzzle with commands at a prompt
+  watch     watch the solver's search, step by step
+  serve     answer solve, count, hint... requests over HTTP
+  engine    talk to a front end over a line protocol, like UCI
+  bench     time the solver on the bundled puzzle sets
+
+Commands read each FILE in turn, or standard input when there are no
+files or a FILE is `-`. Run `sudoku COMMAND --help` for a command's
+options.
+
+Exit status: 0 on success, 1 when the answer is no (an unsolvable
+puzzle, an invalid grid...), 2 for malformed input, 3 for a puzzle with
+more than one solution, 64 for bad arguments and 74 for I/O errors.
+";
+
+// `run` runs the command named by the first argument, and returns the
+// exit code.
+pub fn run(args: &[String]) -> i32 {
+  let (command, command_args) = match args.split_first() {
+    None => return options::usage_error("missing command", USAGE),
+    Some((command, command_args)) => (command.as_str(), command_args),
+  };
+
+  match command {
+    "-h" | "--help" | "help" => {
+      print!("{}", USAGE);
+      EXIT_SUCCESS
+    }
+    _ => {
+      let result = match command {
+        "solve" => solve::run(command_args),
+        "batch" => batch::run(command_args),
+        "bench" => bench::run(command_args),
+        "count" => count::run(command_args),
+        "generate" => generate::run(command_args),
+        "rate" => rate::run(command_args),
+        "validate" => validate::run(command_args),
+        "convert" => convert::run(command_args),
+        "play" => play::run(command_args),
+        "repl" => repl::run(command_args),
+        "watch" => watch::run(command_args),
+        "serve" => serve::run(command_args),
+        "engine" => engine::run(command_args),
+        _ => {
+          let message = format!("unknown command {:?}", command);
+          return options::usage_error(&message, USAGE);
+        }
+      };
+      // Commands stop early with an Err, on bad arguments or `--help`.
+      result.unwrap_or_else(|exit_code| exit_code)
+    }
+  }
+}
@@ -0,0 +1,175 @@
+use super::puzzles::{InputFormat, OutputFormat};
+use super::{EXIT_SUCCESS, EXIT_USAGE};
+use std::str::FromStr;
+use sudoku_solving::core::SUDOKU_DIM_USIZE;
+
+// The flags every command takes, for the commands' help texts.
+pub const COMMON_FLAGS: &str =
+  "  --size N       the side of the grid; this build handles 16 only
+  --from FORMAT  how the input is written: line (one puzzle per line),
+                 ss, sdk or pm. By default this is guessed from the
+                 file extension, or else from the contents.
+  --to FORMAT    how grids are written: grid (the default), line, ss,
+                 sdk or pm; or json, for one JSON object per puzzle
+                 with its input, status, solutions, stats and errors.
+  -h, --help     print this help
+";
+
+// Options are a command's parsed arguments.
+pub struct Options {
+  // None means guessing the format of each input.
+  pub input_format: Option<InputFormat>,
+  pub output_format: OutputFormat,
+  // The inputs; `-` is standard input.
+  pub paths: Vec<String>,
+  // The command's own flags, with their values.
+  flags: Vec<(String, String)>,
+}
+
+impl Options {
+  // `parse` reads the common flags, the flags in `command_flags` (which
+  // all take a value) and the paths. When there is nothing left to do,
+  // because of bad arguments or `--help`, it prints what's needed and
+  // returns the exit code.
+  pub fn parse(
+    args: &[String],
+    command_flags: &[&str],
+    usage: &str,
+  ) -> Result<Options, i32> {
+    match Options::try_parse(args, command_flags) {
+      Ok(Some(options)) => Ok(options),
+      Ok(None) => {
+        print!("{}", usage);
+        Err(EXIT_SUCCESS)
+      }
+      Err(message) => Err(usage_error(&message, usage)),
+    }
+  }
+
+  // `flag` is the value of a command flag, or `default` if it wasn't
+  // given. A value that doesn't parse is reported like `parse` reports
+  // bad arguments.
+  pub fn flag<T: FromStr>(
+    &self,
+    name: &str,
+    default: T,
+    usage: &str,
+  ) -> Result<T, i32> {
+    let value = self
+      .flags
+      .iter()
+      .rev()
+      .find(|(flag, _)| flag == name)
+      .map(|(_, value)| value);
+    match value {
+      None => Ok(default),
+      Some(value) => value.parse().map_err(|_| {
+        usage_error(
+          &format!("bad value {:?} for {}", value, name),
+          usage,
+        )
+      }),
+    }
+  }
+
+  fn try_parse(
+    args: &[String],
+    command_flags: &[&str],
+  ) -> Result<Option<Options>, String> {
+    let mut options = Options {
+      input_format: None,
+      output_format: OutputFormat::Grid,
+      paths: vec![],
+      flags: vec![],
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+      let arg = arg.as_str();
+      if arg == "-h" || arg == "--help" {
+        return Ok(None);
+      }
+      if arg == "-" || !arg.starts_with('-') {
+        options.paths.push(arg.to_string());
+        continue;
+      }
+
+      let is_known = ["--size", "--from", "--to"].contains(&arg)
+        || command_flags.contains(&arg);
+      if !is_known {
+        return Err(format!("unknown option {:?}", arg));
+      }
+      let value = args
+        .next()
+        .ok_or_else(|| format!("{} needs a value", arg))?;
+      match arg {
+        "--size" => {
+          if !is_supported_size(value) {
+            return Err(format!(
+              "unsupported grid size {:?}: this build handles {}x{} \
+               grids only",
+              value, SUDOKU_DIM_USIZE, SUDOKU_DIM_USIZE
+            ));
+          }
+        }
+        "--from" => {
+          options.input_format =
+            Some(InputFormat::from_name(value).ok_or_else(|| {
+              format!("unknown input format {:?}", value)
+            })?);
+        }
+        "--to" => {
+          options.output_format = OutputFormat::from_name(value)
+            .ok_or_else(|| {
+              format!("unknown output format {:?}", value)
+            })?;
+        }
+        _ => options.flags.push((arg.to_string(), value.clone())),
+      }
+    }
+    if options.paths.is_empty() {
+      options.paths.push("-".to_string());
+    }
+
+    Ok(Some(options))
+  }
+}
+
+// `is_supported_size` is whether a grid size, written like `16` or
+// `16x16`, is the one this build handles.
+pub fn is_supported_size(text: &str) -> bool {
+  let side = match text.split_once('x') {
+    None => text,
+    Some((num_rows, num_cols)) if num_rows == num_cols => num_rows,
+    Some(_) => return false,
+  };
+  side.parse() == Ok(SUDOKU_DIM_USIZE)
+}
+
+pub fn usage_error(message: &str, usage: &str) -> i32 {
+  eprintln!("error: {}\n\n{}", message, usage);
+  EXIT_USAGE
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sizes_must_be_16_by_16() {
+    for size in &["16", "16x16"] {
+      assert!(is_supported_size(size), "{}", size);
+    }
+    for size in
+      &["9", "9x9", "16x9", "16xfoo", "16x", "x16", "16x16x16"]
+    {
+      assert!(!is_supported_size(size), "{}", size);
+    }
+
+    let args: Vec<String> = ["--size", "16x9"]
+      .iter()
+      .map(|arg| arg.to_string())
+      .collect();
+    assert!(Options::try_parse(&args, &[]).is_err());
+  }
+}
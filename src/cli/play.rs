@@ -0,0 +1,321 @@
+use super::options::{usage_error, Options, COMMON_FLAGS};
+use super::puzzles::{read_puzzles, Output, Puzzle, Status};
+use super::terminal::{
+  grid_lines, Key, Terminal, BOLD, CYAN, DIM, GREEN, RED, RESET,
+  REVERSE,
+};
+use super::{EXIT_FAILURE, EXIT_IO_ERROR, EXIT_SUCCESS};
+use std::io;
+use sudoku_solving::{
+  core::{SudokuCell, SudokuPosition, SUDOKU_DIM_USIZE},
+  format::{PencilMarkGrid, SymbolSet},
+  game::{GameHint, SudokuGame},
+};
+
+const USAGE: &str = "\
+usage: sudoku play [OPTIONS] [FILE]
+
+Plays a puzzle in the terminal. Conflicting entries are shown in red as
+soon as they are made. When you quit, the grid as it stands is printed
+in the `--to` format, so you can pick up where you left off; the exit
+status is 0 if the puzzle was solved.
+
+Keys:
+  arrows or h j k l  move
+  1-9, A-G           fill in a value, or toggle a pencil mark
+  m                  switch between values and pencil marks
+  space, backspace   clear a value, then the pencil marks
+  u, r               undo, redo
+  ?                  ask for a hint
+  v                  check the grid
+  q                  quit
+
+  --puzzle N      play the Nth puzzle of the input (default 1)
+  --node-limit N  choices the solver may make for a hint (default
+                  1000000)
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let options =
+    Options::parse(args, &["--puzzle", "--node-limit"], &usage)?;
+  let puzzle_number: usize = options.flag("--puzzle", 1, &usage)?;
+  let max_nodes = options.flag("--node-limit", 1_000_000, &usage)?;
+  if puzzle_number == 0 {
+    return Err(usage_error("--puzzle counts from 1", &usage));
+  }
+
+  let mut output = Output::new(options.output_format);
+  let mut status = Status::new();
+  let mut puzzles = vec![];
+  read_puzzles(&options, &mut output, &mut status, |puzzle, _, _| {
+    puzzles.push(puzzle)
+  });
+  let puzzle = match puzzles.into_iter().nth(puzzle_number - 1) {
+    Some(puzzle) => puzzle,
+    None => {
+      eprintln!("error: there is no puzzle {} to play", puzzle_number);
+      status.fail(EXIT_FAILURE);
+      return Ok(status.exit_code());
+    }
+  };
+  let game = match puzzle.new_game() {
+    Some(game) => game,
+    None => {
+      eprintln!("error: {}: the givens clash", puzzle.source);
+      return Ok(EXIT_FAILURE);
+    }
+  };
+
+  let mut player = Player::new(game, max_nodes);
+  if let Err(error) = player.play() {
+    eprintln!("error: terminal: {}", error);
+    return Ok(EXIT_IO_ERROR);
+  }
+
+  // A position read as pencil marks is written back with the
+  // candidates that are left.
+  let grid = player.game.grid();
+  let pencil_marks = puzzle.pencil_marks.as_ref().map(|_| {
+    PencilMarkGrid::from_checker(grid, player.game.grid_checker())
+  });
+  output.write_puzzle(&Puzzle {
+    grid: grid.clone(),
+    pencil_marks,
+    ..puzzle
+  });
+  output.flush();
+  if player.game.is_solved() {
+    Ok(EXIT_SUCCESS)
+  } else {
+    Ok(EXIT_FAILURE)
+  }
+}
+
+// Player is the state of the screen around a game.
+struct Player {
+  game: SudokuGame,
+  max_nodes: u64,
+  symbols: SymbolSet,
+  cursor: (usize, usize),
+  // Whether values keys toggle pencil marks.
+  is_marking: bool,
+  // What happened last, for the status line.
+  message: String,
+}
+
+impl Player {
+  fn new(game: SudokuGame, max_nodes: u64) -> Player {
+    Player {
+      game,
+      max_nodes,
+      symbols: SymbolSet::default(),
+      cursor: (0, 0),
+      is_marking: false,
+      message: String::new(),
+    }
+  }
+
+  fn play(&mut self) -> io::Result<()> {
+    let mut terminal = Terminal::open()?;
+    loop {
+      terminal.draw(&self.screen())?;
+      match terminal.read_key()? {
+        Key::Char('q') | Key::Escape | Key::Interrupt => return Ok(()),
+        key => self.handle_key(key),
+      }
+    }
+  }
+
+  fn handle_key(&mut self, key: Key) {
+    self.message.clear();
+    let (row_idx, col_idx) = self.cursor;
+    let last_idx = SUDOKU_DIM_USIZE - 1;
+    match key {
+      Key::Up | Key::Char('k') => {
+        self.cursor.0 =
+          if row_idx == 0 { last_idx } else { row_idx - 1 }
+      }
+      Key::Down | Key::Char('j') => {
+        self.cursor.0 =
+          if row_idx == last_idx { 0 } else { row_idx + 1 }
+      }
+      Key::Left | Key::Char('h') => {
+        self.cursor.1 =
+          if col_idx == 0 { last_idx } else { col_idx - 1 }
+      }
+      Key::Right | Key::Char('l') => {
+        self.cursor.1 =
+          if col_idx == last_idx { 0 } else { col_idx + 1 }
+      }
+      Key::Char('m') => self.is_marking = !self.is_marking,
+      Key::Char(' ')
+      | Key::Char('.')
+      | Key::Backspace
+      | Key::Delete => {
+        self.make_move(|game, position| game.clear(position).did_move())
+      }
+      Key::Char('u') => match self.game.undo() {
+        Some(position) => self.move_cursor(position),
+        None => self.message = "Nothing to undo.".to_string(),
+      },
+      Key::Char('r') => match self.game.redo() {
+        Some(position) => self.move_cursor(position),
+        None => self.message = "Nothing to redo.".to_string(),
+      },
+      Key::Char('?') => self.hint(),
+      Key::Char('v') => self.check(),
+      Key::Char(symbol) => {
+        if let Some(value) = self.symbols.value(symbol) {
+          let is_marking = self.is_marking;
+          self.make_move(|game, position| {
+            if is_marking {
+              game.toggle_pencil_mark(position, value).did_move()
+            } else {
+              game.place(position, value).did_move()
+            }
+          });
+        }
+      }
+      _ => {}
+    }
+  }
+
+  // `make_move` makes a move at the cursor.
+  fn make_move<F>(&mut self, make_move: F)
+  where
+    F: FnOnce(&mut SudokuGame, SudokuPosition) -> bool,
+  {
+    let position = self.position();
+    if self.game.is_given(position) {
+      self.message = "That's a given.".to_string();
+    } else if make_move(&mut self.game, position)
+      && self.game.is_solved()
+    {
+      self.message = format!("{}Solved! Press q to quit.", GREEN);
+    }
+  }
+
+  fn hint(&mut self) {
+    match self.game.hint(self.max_nodes) {
+      Some(GameHint::WrongEntry(position)) => {
+        self.move_cursor(position);
+        self.message = format!("{}Hint: this entry is wrong.", RED);
+      }
+      Some(GameHint::Value(choice)) => {
+        self.move_cursor(choice.position);
+        let _ = self.game.place(choice.position, choice.value);
+        self.message = format!(
+          "Hint: {} goes here.",
+          self.symbols.symbol(choice.value)
+        );
+      }
+      None if self.game.is_solved() => {
+        self.message = "It's solved already.".to_string()
+      }
+      None => {
+        self.message = "The solver has no hint to give.".to_string()
+      }
+    }
+  }
+
+  fn check(&mut self) {
+    let positions = SudokuPosition::all();
+    let num_conflicts = positions
+      .iter()
+      .filter(|&&position| self.game.is_conflict(position))
+      .count();
+    let num_empty_cells = positions
+      .iter()
+      .filter(|&&position| {
+        self.game.grid().cell(position) == SudokuCell::Empty
+      })
+      .count();
+
+    self.message = if self.game.is_solved() {
+      format!("{}Solved!", GREEN)
+    } else if num_conflicts > 0 {
+      format!("{}Entries in conflict: {}.", RED, num_conflicts)
+    } else {
+      format!("No conflicts. Empty cells: {}.", num_empty_cells)
+    };
+  }
+
+  fn position(&self) -> SudokuPosition {
+    SudokuPosition::new(self.cursor.0 as u8, self.cursor.1 as u8)
+  }
+
+  fn move_cursor(&mut self, position: SudokuPosition) {
+    self.cursor = position.as_usize_pair();
+  }
+
+  // `screen` draws the grid with box borders, then the status lines.
+  fn screen(&self) -> Vec<String> {
+    let mut lines =
+      grid_lines(|row_idx, col_idx| self.cell_text(row_idx, col_idx));
+
+    let position = self.position();
+    let marks: Vec<String> = self
+      .game
+      .pencil_marks(position)
+      .iter()
+      .map(|&value| self.symbols.symbol(value).to_string())
+      .collect();
+    lines.push(String::new());
+    lines.push(format!(
+      "Row {}, column {}. Typing {}. Pencil marks: {}",
+      self.cursor.0 + 1,
+      self.cursor.1 + 1,
+      if self.is_marking {
+        "pencil marks"
+      } else {
+        "values"
+      },
+      if marks.is_empty() {
+        "none".to_string()
+      } else {
+        marks.join(" ")
+      },
+    ));
+    lines.push(self.message.clone());
+    lines.push(format!(
+      "{}arrows move, 1-9 A-G fill in, m marks, space clears, u/r \
+       undo/redo, ? hint, v check, q quit",
+      DIM
+    ));
+
+    lines
+  }
+
+  // `cell_text` is a cell's symbol, styled: givens are bold, entries
+  // cyan, conflicts red, and the cursor is in reverse video. An empty
+  // cell is `.`, or `*` if it has pencil marks.
+  fn cell_text(&self, row_idx: usize, col_idx: usize) -> String {
+    let position = SudokuPosition::new(row_idx as u8, col_idx as u8);
+    let (style, symbol) = match self.game.grid().cell(position) {
+      SudokuCell::Filled(value) => {
+        let style = if self.game.is_given(position) {
+          BOLD
+        } else if self.game.is_conflict(position) {
+          RED
+        } else {
+          CYAN
+        };
+        (style, self.symbols.symbol(value))
+      }
+      SudokuCell::Empty
+        if self.game.pencil_marks(position).is_empty() =>
+      {
+        (DIM, '.')
+      }
+      SudokuCell::Empty => (DIM, '*'),
+    };
+    let cursor_style = if self.cursor == (row_idx, col_idx) {
+      REVERSE
+    } else {
+      ""
+    };
+
+    format!("{}{}{}{}", style, cursor_style, symbol, RESET)
+  }
+}
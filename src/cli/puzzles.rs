@@ -0,0 +1,518 @@
+use super::json::Report;
+use super::options::Options;
+use super::{EXIT_IO_ERROR, EXIT_MALFORMED_INPUT, EXIT_SUCCESS};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process;
+use sudoku_solving::{
+  core::{SudokuGrid, SUDOKU_DIM_USIZE},
+  format::{
+    GridFormatter, GridLayout, ParseError, PencilMarkGrid,
+    PuzzleLineReader, SdkMetadata, SdkPuzzle, SimpleSudokuFormat,
+    SymbolSet,
+  },
+  game::SudokuGame,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputFormat {
+  Lines,
+  SimpleSudoku,
+  Sdk,
+  PencilMarks,
+}
+
+impl InputFormat {
+  pub fn from_name(name: &str) -> Option<InputFormat> {
+    match name {
+      "line" | "lines" => Some(InputFormat::Lines),
+      "ss" => Some(InputFormat::SimpleSudoku),
+      "sdk" => Some(InputFormat::Sdk),
+      "pm" => Some(InputFormat::PencilMarks),
+      _ => None,
+    }
+  }
+
+  fn from_path(path: &str) -> Option<InputFormat> {
+    let extension = path.rsplit('.').next()?.to_ascii_lowercase();
+    match extension.as_str() {
+      "ss" => Some(InputFormat::SimpleSudoku),
+      "sdk" => Some(InputFormat::Sdk),
+      "pm" => Some(InputFormat::PencilMarks),
+      _ => None,
+    }
+  }
+
+  // `from_contents` guesses the format of text with no telling
+  // extension: pencil marks have rows of one token per cell, some with
+  // several candidates; `.sdk` files have `#A`-style metadata or
+  // `[Puzzle]` section headers; and one-puzzle-per-line files have a
+  // line as long as a whole grid.
+  fn from_contents(text: &str) -> InputFormat {
+    let lines: Vec<&str> = text
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty() && !line.starts_with("//"))
+      .collect();
+    let is_pencil_marks = |line: &str| {
+      let tokens: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || c == '|')
+        .filter(|token| !token.is_empty())
+        .collect();
+      // A cell lists at most every value.
+      let num_candidates: Vec<usize> =
+        tokens.iter().map(|token| token.chars().count()).collect();
+      tokens.len() == SUDOKU_DIM_USIZE
+        && num_candidates.iter().any(|&num| num > 1)
+        && num_candidates.iter().all(|&num| num <= SUDOKU_DIM_USIZE)
+    };
+    let is_sdk = |line: &str| {
+      line.starts_with('[')
+        || (line.starts_with('#')
+          && line[1..].starts_with(|c: char| c.is_ascii_alphabetic()))
+    };
+    let num_cells = SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE;
+
+    if lines.iter().any(|line| is_pencil_marks(line)) {
+      return InputFormat::PencilMarks;
+    }
+    match lines.into_iter().find(|line| !line.starts_with("# ")) {
+      Some(line) if is_sdk(line) => InputFormat::Sdk,
+      Some(line)
+        if line.split_whitespace().next().unwrap().chars().count()
+          == num_cells =>
+      {
+        InputFormat::Lines
+      }
+      _ => InputFormat::SimpleSudoku,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputFormat {
+  Grid,
+  Line,
+  SimpleSudoku,
+  Sdk,
+  PencilMarks,
+  // One JSON report per puzzle; see Report.
+  Json,
+}
+
+impl OutputFormat {
+  pub fn from_name(name: &str) -> Option<OutputFormat> {
+    match name {
+      "grid" => Some(OutputFormat::Grid),
+      "line" | "lines" => Some(OutputFormat::Line),
+      "ss" => Some(OutputFormat::SimpleSudoku),
+      "sdk" => Some(OutputFormat::Sdk),
+      "pm" => Some(OutputFormat::PencilMarks),
+      "json" => Some(OutputFormat::Json),
+      _ => None,
+    }
+  }
+}
+
+// Puzzle is a puzzle read from the input, with where it came from for
+// messages.
+pub struct Puzzle {
+  pub source: String,
+  pub grid: SudokuGrid,
+  pub metadata: SdkMetadata,
+  // `pencil_marks` are the candidates of a position read with `--from
+  // pm`, which may rule out more than the filled cells do.
+  pub pencil_marks: Option<PencilMarkGrid>,
+}
+
+impl Puzzle {
+  // `new_game` starts a game on the puzzle, keeping any candidates its
+  // pencil marks eliminated. Returns None if the givens clash.
+  pub fn new_game(&self) -> Option<SudokuGame> {
+    match self.pencil_marks {
+      Some(ref pencil_marks) => Some(SudokuGame::from_checker(
+        &self.grid,
+        pencil_marks.to_checker()?,
+      )),
+      None => SudokuGame::new(&self.grid),
+    }
+  }
+}
+
+// Status tracks how a command is going. When several things fail, the
+// first failure decides the exit code.
+pub struct Status {
+  exit_code: i32,
+}
+
+impl Status {
+  pub fn new() -> Status {
+    Status {
+      exit_code: EXIT_SUCCESS,
+    }
+  }
+
+  pub fn fail(&mut self, exit_code: i32) {
+    if self.exit_code == EXIT_SUCCESS {
+      self.exit_code = exit_code;
+    }
+  }
+
+  pub fn exit_code(&self) -> i32 {
+    self.exit_code
+  }
+}
+
+// `read_puzzles` hands every puzzle of every input to `handle_puzzle`,
+// in order. Inputs that can't be read or parsed are reported and
+// skipped.
+pub fn read_puzzles<F>(
+  options: &Options,
+  output: &mut Output,
+  status: &mut Status,
+  mut handle_puzzle: F,
+) where
+  F: FnMut(Puzzle, &mut Output, &mut Status),
+{
+  for path in &options.paths {
+    read_input(
+      path,
+      options.input_format,
+      output,
+      status,
+      &mut handle_puzzle,
+    );
+  }
+}
+
+fn read_input<F>(
+  path: &str,
+  input_format: Option<InputFormat>,
+  output: &mut Output,
+  status: &mut Status,
+  handle_puzzle: &mut F,
+) where
+  F: FnMut(Puzzle, &mut Output, &mut Status),
+{
+  let name = if path == "-" { "<stdin>" } else { path };
+  let mut reader: Box<dyn BufRead> = if path == "-" {
+    Box::new(BufReader::new(io::stdin()))
+  } else {
+    match File::open(path) {
+      Ok(file) => Box::new(BufReader::new(file)),
+      Err(error) => {
+        report_input_error(name, &error, EXIT_IO_ERROR, output, status);
+        return;
+      }
+    }
+  };
+
+  // Collections of one puzzle per line can be huge, so when we know
+  // that's what we have, we stream them.
+  let input_format =
+    input_format.or_else(|| InputFormat::from_path(path));
+  if input_format == Some(InputFormat::Lines) {
+    read_lines(name, reader, output, status, handle_puzzle);
+    return;
+  }
+
+  let mut text = String::new();
+  if let Err(error) = reader.read_to_string(&mut text) {
+    report_input_error(name, &error, EXIT_IO_ERROR, output, status);
+    return;
+  }
+  let input_format =
+    input_format.unwrap_or_else(|| InputFormat::from_contents(&text));
+
+  if input_format == InputFormat::Lines {
+    read_lines(name, text.as_bytes(), output, status, handle_puzzle);
+    return;
+  }
+  match parse_whole_text(&text, input_format) {
+    Ok(puzzle) => handle_puzzle(
+      Puzzle {
+        source: name.to_string(),
+        ..puzzle
+      },
+      output,
+      status,
+    ),
+    Err(error) => report_input_error(
+      name,
+      &error,
+      EXIT_MALFORMED_INPUT,
+      output,
+      status,
+    ),
+  }
+}
+
+// `parse_puzzle` reads the one puzzle in `text`, guessing its format
+// from the contents.
+pub fn parse_puzzle(text: &str) -> Result<SudokuGrid, String> {
+  let input_format = InputFormat::from_contents(text);
+  if input_format != InputFormat::Lines {
+    return parse_whole_text(text, input_format)
+      .map(|puzzle| puzzle.grid)
+      .map_err(|error| error.to_string());
+  }
+
+  let mut puzzle_lines = PuzzleLineReader::new(text.as_bytes());
+  let puzzle_line = puzzle_lines
+    .next()
+    .ok_or_else(|| "no puzzle was given".to_string())?
+    .map_err(|error| error.to_string())?;
+  if puzzle_lines.next().is_some() {
+    return Err("expected one puzzle, found more".to_string());
+  }
+  Ok(puzzle_line.grid)
+}
+
+// `parse_whole_text` reads the puzzle of a format with one puzzle per
+// file. The caller fills in its source.
+fn parse_whole_text(
+  text: &str,
+  input_format: InputFormat,
+) -> Result<Puzzle, ParseError> {
+  let puzzle = |grid| Puzzle {
+    source: String::new(),
+    grid,
+    metadata: SdkMetadata::default(),
+    pencil_marks: None,
+  };
+  match input_format {
+    InputFormat::Lines => unreachable!("lines hold many puzzles"),
+    InputFormat::SimpleSudoku => {
+      SimpleSudokuFormat::new().parse(text).map(puzzle)
+    }
+    InputFormat::Sdk => {
+      text.parse::<SdkPuzzle>().map(|sdk_puzzle| Puzzle {
+        metadata: sdk_puzzle.metadata,
+        ..puzzle(sdk_puzzle.puzzle)
+      })
+    }
+    InputFormat::PencilMarks => {
+      text.parse::<PencilMarkGrid>().map(|pencil_marks| Puzzle {
+        pencil_marks: Some(pencil_marks.clone()),
+        ..puzzle(pencil_marks.grid())
+      })
+    }
+  }
+}
+
+fn read_lines<R: BufRead, F>(
+  name: &str,
+  reader: R,
+  output: &mut Output,
+  status: &mut Status,
+  handle_puzzle: &mut F,
+) where
+  F: FnMut(Puzzle, &mut Output, &mut Status),
+{
+  for puzzle_line in PuzzleLineReader::new(reader) {
+    match puzzle_line {
+      Ok(puzzle_line) => handle_puzzle(
+        Puzzle {
+          source: format!("{}:{}", name, puzzle_line.line),
+          grid: puzzle_line.grid,
+          metadata: SdkMetadata::default(),
+          pencil_marks: None,
+        },
+        output,
+        status,
+      ),
+      Err(error) => report_input_error(
+        name,
+        &error,
+        EXIT_MALFORMED_INPUT,
+        output,
+        status,
+      ),
+    }
+  }
+}
+
+// `report_input_error` reports an input that can't be read or parsed,
+// on standard error and, with `--to json`, as a report.
+fn report_input_error(
+  name: &str,
+  error: &dyn fmt::Display,
+  exit_code: i32,
+  output: &mut Output,
+  status: &mut Status,
+) {
+  eprintln!("error: {}: {}", name, error);
+  status.fail(exit_code);
+
+  let report_status = if exit_code == EXIT_IO_ERROR {
+    "io_error"
+  } else {
+    "malformed"
+  };
+  output.write_report(&Report::without_puzzle(
+    name,
+    report_status,
+    &error.to_string(),
+  ));
+}
+
+// Output writes grids to standard output in the chosen format. With
+// `--to json`, only reports are written, and grids and lines are left
+// out; otherwise it's the other way around.
+pub struct Output {
+  format: OutputFormat,
+  out: io::BufWriter<io::Stdout>,
+  num_written: usize,
+}
+
+impl Output {
+  pub fn new(format: OutputFormat) -> Output {
+    Output {
+      format,
+      out: io::BufWriter::new(io::stdout()),
+      num_written: 0,
+    }
+  }
+
+  // `write_puzzle` writes the puzzle as it was read.
+  pub fn write_puzzle(&mut self, puzzle: &Puzzle) {
+    let sdk_puzzle = SdkPuzzle {
+      metadata: puzzle.metadata.clone(),
+      puzzle: puzzle.grid.clone(),
+      state: None,
+    };
+    self.write_grid(
+      &puzzle.grid,
+      sdk_puzzle,
+      puzzle.pencil_marks.as_ref(),
+    );
+  }
+
+  // `write_solution` writes the solution. In `.sdk` files, it goes in
+  // the `[State]` section, after the puzzle.
+  pub fn write_solution(
+    &mut self,
+    puzzle: &Puzzle,
+    solution: &SudokuGrid,
+  ) {
+    let sdk_puzzle = SdkPuzzle {
+      metadata: puzzle.metadata.clone(),
+      puzzle: puzzle.grid.clone(),
+      state: Some(solution.clone()),
+    };
+    self.write_grid(solution, sdk_puzzle, None);
+  }
+
+  // `write_line` writes a line of text, like a count or a rating.
+  pub fn write_line(&mut self, line: &str) {
+    if self.format == OutputFormat::Json {
+      return;
+    }
+    let result = writeln!(self.out, "{}", line);
+    check_write(result);
+  }
+
+  pub fn write_report(&mut self, report: &Report) {
+    if self.format != OutputFormat::Json {
+      return;
+    }
+    let result = writeln!(self.out, "{}", report.to_json());
+    check_write(result);
+  }
+
+  pub fn flush(&mut self) {
+    let result = self.out.flush();
+    check_write(result);
+  }
+
+  // `write_grid` writes the grid. With `--to pm`, the `pencil_marks`
+  // are written instead, if given.
+  fn write_grid(
+    &mut self,
+    grid: &SudokuGrid,
+    sdk_puzzle: SdkPuzzle,
+    pencil_marks: Option<&PencilMarkGrid>,
+  ) {
+    let text = match self.format {
+      OutputFormat::Grid => GridFormatter::new().format(grid),
+      OutputFormat::Line => GridFormatter::new()
+        .with_layout(GridLayout::SingleLine)
+        .with_symbols(SymbolSet::default())
+        .format(grid),
+      OutputFormat::SimpleSudoku => {
+        SimpleSudokuFormat::new().format(grid)
+      }
+      OutputFormat::Sdk => sdk_puzzle.to_string(),
+      OutputFormat::PencilMarks => match pencil_marks {
+        Some(pencil_marks) => pencil_marks.to_string(),
+        None => PencilMarkGrid::from_grid(grid).to_string(),
+      },
+      OutputFormat::Json => return,
+    };
+
+    // Grids are separated by a blank line; lines need no separator.
+    let separator =
+      if self.num_written > 0 && self.format != OutputFormat::Line {
+        "\n"
+      } else {
+        ""
+      };
+    let result = write!(self.out, "{}{}", separator, text);
+    check_write(result);
+    self.num_written += 1;
+  }
+}
+
+// There is no point going on once we can't write, so `check_write`
+// exits.
+fn check_write(result: io::Result<()>) {
+  if let Err(error) = result {
+    eprintln!("error: could not write output: {}", error);
+    process::exit(EXIT_IO_ERROR);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use sudoku_solving::core::SudokuPosition;
+
+  #[test]
+  fn from_contents_tells_the_formats_apart() {
+    let grid = SudokuGrid::default();
+    let pencil_marks = PencilMarkGrid::from_grid(&grid).to_string();
+    let simple_sudoku = SimpleSudokuFormat::new().format(&grid);
+    let line = ".".repeat(SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE);
+    let sdk = format!("#AAnne\n{}\n", simple_sudoku);
+
+    assert_eq!(
+      InputFormat::from_contents(&pencil_marks),
+      InputFormat::PencilMarks
+    );
+    assert_eq!(
+      InputFormat::from_contents(&simple_sudoku),
+      InputFormat::SimpleSudoku
+    );
+    assert_eq!(
+      InputFormat::from_contents(&format!("# comment\n{} 4.5\n", line)),
+      InputFormat::Lines
+    );
+    assert_eq!(InputFormat::from_contents(&sdk), InputFormat::Sdk);
+  }
+
+  #[test]
+  fn pencil_marks_keep_their_eliminations() {
+    let mut pencil_marks =
+      PencilMarkGrid::from_grid(&SudokuGrid::default()).to_string();
+    // Cross out the 1 in the top left cell.
+    pencil_marks =
+      pencil_marks.replacen("123456789ABCDEFG", "23456789ABCDEFG ", 1);
+    let puzzle =
+      parse_whole_text(&pencil_marks, InputFormat::PencilMarks)
+        .unwrap();
+    let game = puzzle.new_game().unwrap();
+    let top_left = SudokuPosition::new(0, 0);
+    assert_eq!(game.candidates(top_left).len(), SUDOKU_DIM_USIZE - 1);
+  }
+}
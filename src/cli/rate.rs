@@ -0,0 +1,56 @@
+use super::json::Report;
+use super::options::{Options, COMMON_FLAGS};
+use super::puzzles::{read_puzzles, Output, Status};
+use super::EXIT_FAILURE;
+use sudoku_solving::mutation::MutationSearch;
+
+const USAGE: &str = "\
+usage: sudoku rate [OPTIONS] [FILE...]
+
+Rates each puzzle by how many choices the solver makes to solve it and
+prove its solution unique, printing one line per puzzle:
+`SOURCE: RATING`. Puzzles without a unique solution can't be rated.
+
+  --node-limit N  give up on a puzzle after N choices (default
+                  1000000)
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let options = Options::parse(args, &["--node-limit"], &usage)?;
+  let max_nodes = options.flag("--node-limit", 1_000_000, &usage)?;
+
+  let mut output = Output::new(options.output_format);
+  let mut status = Status::new();
+  read_puzzles(
+    &options,
+    &mut output,
+    &mut status,
+    |puzzle, output, status| match MutationSearch::rate(
+      &puzzle.grid.filled_choices(),
+      max_nodes,
+    ) {
+      Some(difficulty) => {
+        output
+          .write_line(&format!("{}: {}", puzzle.source, difficulty));
+        output.write_report(
+          &Report::new(&puzzle.source, &puzzle.grid, "rated")
+            .with_stat("difficulty", difficulty),
+        );
+      }
+      None => {
+        let error =
+          format!("no unique solution within {} choices", max_nodes);
+        eprintln!("error: {}: {}", puzzle.source, error);
+        status.fail(EXIT_FAILURE);
+        output.write_report(
+          &Report::new(&puzzle.source, &puzzle.grid, "unrated")
+            .with_error(&error),
+        );
+      }
+    },
+  );
+  output.flush();
+
+  Ok(status.exit_code())
+}
@@ -0,0 +1,481 @@
+use super::engine::{
+  format_move, format_position, parse_move, parse_position,
+};
+use super::options::{usage_error, Options, COMMON_FLAGS};
+use super::puzzles::{parse_puzzle, read_puzzles, Output, Status};
+use super::{EXIT_FAILURE, EXIT_IO_ERROR, EXIT_SUCCESS};
+use std::fmt::Write as _;
+use std::io::{self, BufRead, IsTerminal, Write};
+use sudoku_solving::{
+  core::{SudokuGrid, SudokuPosition},
+  format::{GridFormatter, PencilMarkGrid, SymbolSet},
+  game::{StepReason, SudokuGame},
+  solver::{SolutionCount, SudokuSolver},
+};
+
+const USAGE: &str = "\
+usage: sudoku repl [OPTIONS] [FILE]
+
+Loads a puzzle and reads commands for it from standard input, to
+test-solve and debug it by hand. Standard input holds the commands, so
+the puzzle comes from FILE, or from the `load` command.
+
+Commands:
+  load PUZZLE      start over on a puzzle written on one line
+  show             print the grid
+  marks            print the candidates of every cell
+  candidates rRcC  list the values that could go in a cell (or `c`)
+  place rRcC=V     fill in a value (or `p`)
+  remove rRcC      empty a cell (or `r`)
+  step             fill in one single, and say why
+  singles          fill in singles until there are none left
+  count [N]        count the solutions, up to N (default 1000)
+  undo [N]         take back the last N moves (default 1)
+  redo [N]         make N undone moves again (default 1)
+  help             list the commands
+  quit             exit
+
+Rows and columns count from 1, so `r1c16` is the top right cell.
+
+  --puzzle N      load the Nth puzzle of FILE (default 1)
+  --node-limit N  give up counting after the solver makes N choices
+                  (default 1000000)
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let options =
+    Options::parse(args, &["--puzzle", "--node-limit"], &usage)?;
+  let puzzle_number: usize = options.flag("--puzzle", 1, &usage)?;
+  let max_nodes = options.flag("--node-limit", 1_000_000, &usage)?;
+  if puzzle_number == 0 {
+    return Err(usage_error("--puzzle counts from 1", &usage));
+  }
+
+  let mut repl = Repl::new(max_nodes);
+  // With no FILE, the paths are just `-`.
+  if options.paths != ["-"] {
+    let mut output = Output::new(options.output_format);
+    let mut status = Status::new();
+    let mut puzzles = vec![];
+    read_puzzles(&options, &mut output, &mut status, |puzzle, _, _| {
+      puzzles.push(puzzle)
+    });
+    let puzzle = match puzzles.into_iter().nth(puzzle_number - 1) {
+      Some(puzzle) => puzzle,
+      None => {
+        eprintln!(
+          "error: there is no puzzle {} to load",
+          puzzle_number
+        );
+        status.fail(EXIT_FAILURE);
+        return Ok(status.exit_code());
+      }
+    };
+    match puzzle.new_game() {
+      Some(game) => repl.game = Some(game),
+      None => {
+        eprintln!("error: {}: the givens clash", puzzle.source);
+        return Ok(EXIT_FAILURE);
+      }
+    }
+  }
+
+  let stdin = io::stdin();
+  let is_interactive = stdin.is_terminal();
+  let mut lines = stdin.lock().lines();
+  loop {
+    if is_interactive {
+      print!("> ");
+      let _ = io::stdout().flush();
+    }
+    let line = match lines.next() {
+      None => break,
+      Some(Ok(line)) => line,
+      Some(Err(error)) => {
+        eprintln!("error: could not read commands: {}", error);
+        return Ok(EXIT_IO_ERROR);
+      }
+    };
+    let mut out = String::new();
+    let result = repl.handle(&line, &mut out);
+    print!("{}", out);
+    match result {
+      Ok(true) => {}
+      Ok(false) => break,
+      Err(message) => eprintln!("error: {}", message),
+    }
+  }
+
+  Ok(EXIT_SUCCESS)
+}
+
+// Repl is the puzzle being explored.
+struct Repl {
+  game: Option<SudokuGame>,
+  max_nodes: u64,
+  symbols: SymbolSet,
+}
+
+impl Repl {
+  fn new(max_nodes: u64) -> Repl {
+    Repl {
+      game: None,
+      max_nodes,
+      symbols: SymbolSet::default(),
+    }
+  }
+
+  fn load(&mut self, puzzle: &SudokuGrid) -> Result<(), String> {
+    let game = SudokuGame::new(puzzle)
+      .ok_or_else(|| "the givens clash".to_string())?;
+    self.game = Some(game);
+    Ok(())
+  }
+
+  // `handle` runs a command, writing what it has to say to `out`, and
+  // returns false when it's time to quit.
+  fn handle(
+    &mut self,
+    line: &str,
+    out: &mut String,
+  ) -> Result<bool, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (command, args) = match words.split_first() {
+      None => return Ok(true),
+      Some((command, args)) => (*command, args),
+    };
+
+    match (command, args) {
+      ("quit", []) | ("exit", []) => return Ok(false),
+      ("help", []) => out.push_str(USAGE),
+      ("load", [puzzle_text]) => {
+        self.load(&parse_puzzle(puzzle_text)?)?;
+        self.show(out)?;
+      }
+      ("show", []) => self.show(out)?,
+      ("marks", []) => {
+        let game = self.game()?;
+        write!(
+          out,
+          "{}",
+          PencilMarkGrid::from_checker(
+            game.grid(),
+            game.grid_checker()
+          )
+        )
+        .unwrap();
+      }
+      ("candidates", [position_text]) | ("c", [position_text]) => {
+        let position = parse_position(position_text)
+          .ok_or_else(|| format!("bad cell {:?}", position_text))?;
+        let candidates: Vec<String> = self
+          .game()?
+          .candidates(position)
+          .into_iter()
+          .map(|value| self.symbols.symbol(value).to_string())
+          .collect();
+        writeln!(
+          out,
+          "{}: {}",
+          format_position(position),
+          candidates.join(" ")
+        )
+        .unwrap();
+      }
+      ("place", [move_text]) | ("p", [move_text]) => {
+        let choice = parse_move(move_text)
+          .ok_or_else(|| format!("bad move {:?}", move_text))?;
+        let game = self.game_mut()?;
+        if game.is_given(choice.position) {
+          return Err("that cell holds a given".to_string());
+        }
+        if !game.place(choice.position, choice.value).did_move() {
+          return Err("that value is there already".to_string());
+        }
+        if game.is_conflict(choice.position) {
+          writeln!(
+            out,
+            "{} clashes with the grid",
+            format_move(choice)
+          )
+          .unwrap();
+        }
+      }
+      ("remove", [position_text]) | ("r", [position_text]) => {
+        let position = parse_position(position_text)
+          .ok_or_else(|| format!("bad cell {:?}", position_text))?;
+        let game = self.game_mut()?;
+        if game.is_given(position) {
+          return Err("that cell holds a given".to_string());
+        }
+        if !game.clear(position).did_move() {
+          return Err("that cell is empty already".to_string());
+        }
+      }
+      ("step", []) => {
+        if !self.step(out)? {
+          writeln!(out, "{}", self.no_step_reason()?).unwrap();
+        }
+      }
+      ("singles", []) => {
+        let mut num_steps = 0;
+        while self.step(out)? {
+          num_steps += 1;
+        }
+        writeln!(out, "filled in {} singles", num_steps).unwrap();
+        if num_steps == 0 {
+          writeln!(out, "{}", self.no_step_reason()?).unwrap();
+        }
+      }
+      ("count", _) if args.len() <= 1 => {
+        let max_solutions = match args.first() {
+          None => 1000,
+          Some(text) => {
+            text.parse().map_err(|_| format!("bad limit {:?}", text))?
+          }
+        };
+        let solution_count = SudokuSolver::count_solutions(
+          &self.game()?.grid().filled_choices(),
+          max_solutions,
+          Some(self.max_nodes),
+        );
+        match solution_count {
+          SolutionCount::Exactly(num_solutions) => {
+            writeln!(out, "{} solutions", num_solutions).unwrap()
+          }
+          SolutionCount::AtLeast(num_solutions) => {
+            writeln!(out, "at least {} solutions", num_solutions)
+              .unwrap()
+          }
+          SolutionCount::NodeLimitReached(num_solutions) => writeln!(
+            out,
+            "at least {} solutions; hit the node limit",
+            num_solutions
+          )
+          .unwrap(),
+        }
+      }
+      ("undo", _) | ("redo", _) if args.len() <= 1 => {
+        let num_moves: usize = match args.first() {
+          None => 1,
+          Some(text) => text
+            .parse()
+            .map_err(|_| format!("bad number {:?}", text))?,
+        };
+        let game = self.game_mut()?;
+        for _ in 0..num_moves {
+          let position = if command == "undo" {
+            game.undo()
+          } else {
+            game.redo()
+          };
+          match position {
+            Some(position) => {
+              writeln!(out, "{} {}", command, format_position(position))
+                .unwrap()
+            }
+            None => {
+              writeln!(out, "nothing more to {}", command).unwrap();
+              break;
+            }
+          }
+        }
+      }
+      _ => {
+        return Err(format!(
+          "unknown command {:?}; try `help`",
+          line.trim()
+        ))
+      }
+    }
+    Ok(true)
+  }
+
+  // `step` fills in a single, and says which and why. Returns false
+  // when there is none.
+  fn step(&mut self, out: &mut String) -> Result<bool, String> {
+    let game = self.game_mut()?;
+    let step = match game.next_step() {
+      Some(step) => step,
+      None => return Ok(false),
+    };
+    let _ = game.place(step.choice.position, step.choice.value);
+
+    let reason = match step.reason {
+      StepReason::NakedSingle => "naked single",
+      StepReason::HiddenSingleInRow => "hidden single in its row",
+      StepReason::HiddenSingleInColumn => "hidden single in its column",
+      StepReason::HiddenSingleInBox => "hidden single in its box",
+    };
+    writeln!(out, "{}: {}", format_move(step.choice), reason).unwrap();
+    Ok(true)
+  }
+
+  fn no_step_reason(&self) -> Result<&'static str, String> {
+    let game = self.game()?;
+    let has_conflicts = SudokuPosition::all()
+      .into_iter()
+      .any(|position| game.is_conflict(position));
+    Ok(if has_conflicts {
+      "no singles while entries clash; undo or remove them first"
+    } else if game.is_solved() {
+      "no singles: the puzzle is solved"
+    } else {
+      "no singles"
+    })
+  }
+
+  fn show(&self, out: &mut String) -> Result<(), String> {
+    let game = self.game()?;
+    // Moves write values as symbols, so the grid does too.
+    let formatter =
+      GridFormatter::new().with_symbols(self.symbols.clone());
+    out.push_str(&formatter.format(game.grid()));
+    if game.is_solved() {
+      writeln!(out, "solved").unwrap();
+    }
+    Ok(())
+  }
+
+  fn game(&self) -> Result<&SudokuGame, String> {
+    self.game.as_ref().ok_or_else(no_puzzle)
+  }
+
+  fn game_mut(&mut self) -> Result<&mut SudokuGame, String> {
+    self.game.as_mut().ok_or_else(no_puzzle)
+  }
+}
+
+fn no_puzzle() -> String {
+  "no puzzle; use `load` first".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::SmallRng;
+  use rand::SeedableRng;
+  use sudoku_solving::core::SUDOKU_DIM_USIZE;
+  use sudoku_solving::format::GridLayout;
+
+  // `run_script` runs commands the way `run` does, and returns what
+  // each one said, errors included, until `quit`.
+  fn run_script(repl: &mut Repl, commands: &[&str]) -> Vec<String> {
+    let mut replies = vec![];
+    for command in commands {
+      let mut out = String::new();
+      match repl.handle(command, &mut out) {
+        Ok(true) => replies.push(out),
+        Ok(false) => break,
+        Err(message) => replies.push(format!("error: {}", message)),
+      }
+    }
+    replies
+  }
+
+  fn load_command(grid: &SudokuGrid) -> String {
+    let line = GridFormatter::new()
+      .with_layout(GridLayout::SingleLine)
+      .with_symbols(SymbolSet::default())
+      .format(grid);
+    format!("load {}", line.trim_end())
+  }
+
+  // `unique_puzzle` is a solution grid and, with its top row blanked,
+  // a puzzle whose only solution it is.
+  fn unique_puzzle() -> (SudokuGrid, SudokuGrid) {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let solution = SudokuGrid::from_choices(
+      &SudokuSolver::solve_randomly(&[], &mut rng).unwrap(),
+    );
+    let mut puzzle = solution.clone();
+    for position in &SudokuPosition::all()[..SUDOKU_DIM_USIZE] {
+      puzzle.clear(*position);
+    }
+    (solution, puzzle)
+  }
+
+  #[test]
+  fn plays_through_a_puzzle() {
+    let (solution, puzzle) = unique_puzzle();
+    let answer = format_move(solution.filled_choices()[0]);
+    let symbol = &answer["r1c1=".len()..];
+    let mut repl = Repl::new(1_000_000);
+    let replies = run_script(
+      &mut repl,
+      &[
+        &load_command(&puzzle),
+        "c r1c1",
+        "count",
+        &format!("place {}", answer),
+        "undo",
+        "redo",
+        "undo 2",
+        "singles",
+        "step",
+        "show",
+        "quit",
+        "show",
+      ],
+    );
+
+    assert_eq!(replies.len(), 10);
+    assert_eq!(replies[1], format!("r1c1: {}\n", symbol));
+    assert_eq!(replies[2], "1 solutions\n");
+    assert_eq!(replies[3], "");
+    assert_eq!(replies[4], "undo r1c1\n");
+    assert_eq!(replies[5], "redo r1c1\n");
+    assert_eq!(replies[6], "undo r1c1\nnothing more to undo\n");
+    assert!(replies[7].ends_with("filled in 16 singles\n"));
+    assert_eq!(replies[8], "no singles: the puzzle is solved\n");
+    assert!(replies[9].ends_with("solved\n"));
+  }
+
+  #[test]
+  fn reports_bad_commands() {
+    let (_, puzzle) = unique_puzzle();
+    let mut repl = Repl::new(1_000_000);
+    let replies = run_script(
+      &mut repl,
+      &[
+        "show",
+        "frob",
+        &load_command(&puzzle),
+        "c r0c1",
+        "place r1c1",
+        "remove r2c1",
+        "remove r1c1",
+        "count many",
+      ],
+    );
+    assert_eq!(
+      replies
+        .iter()
+        .filter(|reply| reply.starts_with("error: "))
+        .collect::<Vec<_>>(),
+      vec![
+        "error: no puzzle; use `load` first",
+        "error: unknown command \"frob\"; try `help`",
+        "error: bad cell \"r0c1\"",
+        "error: bad move \"r1c1\"",
+        "error: that cell holds a given",
+        "error: that cell is empty already",
+        "error: bad limit \"many\"",
+      ]
+    );
+  }
+
+  #[test]
+  fn counts_within_the_node_limit() {
+    let mut repl = Repl::new(10);
+    let replies = run_script(
+      &mut repl,
+      &[&load_command(&SudokuGrid::default()), "count"],
+    );
+    assert_eq!(
+      replies[1],
+      "at least 0 solutions; hit the node limit\n"
+    );
+  }
+}
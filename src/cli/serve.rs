@@ -0,0 +1,861 @@
+use super::json::{JsonValue, Report};
+use super::options::{usage_error, Options, COMMON_FLAGS};
+use super::puzzles::parse_puzzle;
+use super::validate::find_problem;
+use super::EXIT_IO_ERROR;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use sudoku_solving::{
+  core::SUDOKU_DIM_USIZE,
+  core::{
+    SudokuCell, SudokuChoice, SudokuClueMask, SudokuGrid,
+    SudokuPosition,
+  },
+  game::{GameHint, SudokuGame},
+  generator::{GeneratorBudget, SudokuGenerator},
+  solver::{SearchControl, SolverEvent, SolverResult, SudokuSolver},
+};
+
+const USAGE: &str = "\
+usage: sudoku serve [OPTIONS]
+
+Answers HTTP requests, for programs that would rather not run a command
+per puzzle. Every endpoint takes a POST whose body is a JSON object,
+and answers with a JSON report shaped like the ones `--to json` writes:
+
+  POST /solve     {\"puzzle\": \"...\"}
+  POST /count     {\"puzzle\": \"...\", \"limit\": 1000}
+  POST /validate  {\"puzzle\": \"...\"}
+  POST /hint      {\"puzzle\": \"...\", \"grid\": \"...\"}
+  POST /rate      {\"puzzle\": \"...\"}
+  POST /generate  {\"clues\": 160, \"seed\": 1}
+
+Puzzles are text in any format the other commands read. For a hint,
+`grid` is the progress made on the puzzle, givens included. Requests
+may also set `node_limit` and `time_limit_ms`, which can lower the
+server's limits but not raise them; a search that hits one has the
+status `node_limit_reached` or `time_limit_reached`. Bad requests are
+answered with an HTTP error and a report with the status `bad_request`.
+
+  --address ADDR    listen on ADDR (default 127.0.0.1:8080). With port
+                    0, a free port is chosen; the address is printed to
+                    standard error either way.
+  --jobs N          answer N requests at once (default: one per core)
+  --node-limit N    solver choices allowed per request (default
+                    1000000)
+  --time-limit MS   milliseconds allowed per request (default 10000)
+";
+
+// The longest request line and headers, and body, we read.
+const MAX_HEAD_LEN: usize = 64 << 10;
+const MAX_BODY_LEN: usize = 1 << 20;
+// How long a client may take to send its request, or read the answer.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let command_flags =
+    ["--address", "--jobs", "--node-limit", "--time-limit"];
+  let options = Options::parse(args, &command_flags, &usage)?;
+  if options.paths != ["-"] {
+    return Err(usage_error("serve takes no files", &usage));
+  }
+  let address =
+    options.flag("--address", "127.0.0.1:8080".to_string(), &usage)?;
+  let num_threads = match options.flag("--jobs", 0, &usage)? {
+    0 => thread::available_parallelism().map_or(1, |num| num.get()),
+    num_threads => num_threads,
+  };
+  let limits = Limits {
+    max_nodes: options.flag("--node-limit", 1_000_000, &usage)?,
+    time_limit: Duration::from_millis(options.flag(
+      "--time-limit",
+      10_000,
+      &usage,
+    )?),
+  };
+
+  let listener = match TcpListener::bind(&address) {
+    Ok(listener) => listener,
+    Err(error) => {
+      eprintln!("error: could not listen on {}: {}", address, error);
+      return Ok(EXIT_IO_ERROR);
+    }
+  };
+  match listener.local_addr() {
+    Ok(local_address) => eprintln!("listening on {}", local_address),
+    Err(error) => {
+      eprintln!("error: could not listen on {}: {}", address, error);
+      return Ok(EXIT_IO_ERROR);
+    }
+  }
+
+  serve(listener, num_threads, limits);
+  Ok(EXIT_IO_ERROR)
+}
+
+// `serve` answers connections on `num_threads` workers. It only returns
+// if the workers are all gone.
+fn serve(listener: TcpListener, num_threads: usize, limits: Limits) {
+  // Connections wait in the queue until a worker is free.
+  let (connection_sender, connection_receiver) =
+    mpsc::channel::<TcpStream>();
+  let connection_receiver = Arc::new(Mutex::new(connection_receiver));
+  for _ in 0..num_threads {
+    let connection_receiver = Arc::clone(&connection_receiver);
+    thread::spawn(move || loop {
+      // The lock is released as soon as a connection is taken.
+      let connection = connection_receiver.lock().unwrap().recv();
+      match connection {
+        Ok(stream) => handle_connection(stream, limits),
+        Err(_) => return,
+      }
+    });
+  }
+
+  for stream in listener.incoming() {
+    match stream {
+      Ok(stream) => {
+        if connection_sender.send(stream).is_err() {
+          break;
+        }
+      }
+      // Errors here are about one connection, like a client that gave
+      // up before being accepted; the next may be fine.
+      Err(error) => eprintln!("warning: could not accept: {}", error),
+    }
+  }
+}
+
+// Limits bound the work done for a request. The server's are the most
+// a request can ask for.
+#[derive(Clone, Copy)]
+struct Limits {
+  max_nodes: u64,
+  time_limit: Duration,
+}
+
+// Search is a request's limits, once its clock has started.
+struct Search {
+  max_nodes: u64,
+  deadline: Instant,
+}
+
+impl Search {
+  // `observer` stops the solver at the deadline. Looking at the clock
+  // after every event would slow the solver down, so it looks every so
+  // often.
+  fn observer(&self) -> impl FnMut(SolverEvent) -> SearchControl {
+    let deadline = self.deadline;
+    let mut num_events: u32 = 0;
+    move |_| {
+      num_events = num_events.wrapping_add(1);
+      if num_events.is_multiple_of(1024) && Instant::now() >= deadline {
+        SearchControl::Stop
+      } else {
+        SearchControl::Continue
+      }
+    }
+  }
+
+  fn solver(
+    &self,
+    given_choices: &[SudokuChoice],
+  ) -> Option<SudokuSolver> {
+    SudokuSolver::new(given_choices)
+      .map(|solver| solver.with_node_limit(self.max_nodes))
+  }
+
+  fn is_out_of_time(&self) -> bool {
+    Instant::now() >= self.deadline
+  }
+
+  // `limit_status` is the status for a search that gave up.
+  fn limit_status(&self) -> &'static str {
+    if self.is_out_of_time() {
+      "time_limit_reached"
+    } else {
+      "node_limit_reached"
+    }
+  }
+}
+
+// HttpError is a request we won't answer with a report of our own.
+struct HttpError {
+  status_code: u16,
+  message: String,
+}
+
+impl HttpError {
+  fn new(status_code: u16, message: &str) -> HttpError {
+    HttpError {
+      status_code,
+      message: message.to_string(),
+    }
+  }
+}
+
+fn handle_connection(mut stream: TcpStream, limits: Limits) {
+  let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+  let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+
+  let (path, report) = match read_request(&stream) {
+    Ok((path, body)) => {
+      let report = answer(&path, &body, limits);
+      (path, report)
+    }
+    Err(error) => ("request".to_string(), Err(error)),
+  };
+  let (status_code, report) = match report {
+    Ok(report) => (200, report),
+    Err(error) => (
+      error.status_code,
+      Report::without_puzzle(&path, "bad_request", &error.message),
+    ),
+  };
+
+  let body = format!("{}\n", report.to_json());
+  let response = format!(
+    "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\n\
+     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+    status_code,
+    reason_phrase(status_code),
+    body.len(),
+    body
+  );
+  // If the client has gone, there is no one to tell.
+  let _ = stream.write_all(response.as_bytes());
+}
+
+// `read_request` reads a POST, and returns its path and body.
+fn read_request(
+  stream: &TcpStream,
+) -> Result<(String, String), HttpError> {
+  let mut reader =
+    BufReader::new(stream.take((MAX_HEAD_LEN + MAX_BODY_LEN) as u64));
+  let request_line = read_line(&mut reader)?;
+  let mut parts = request_line.split_whitespace();
+  let (method, path) = match (parts.next(), parts.next()) {
+    (Some(method), Some(path)) => (method, path.to_string()),
+    _ => return Err(HttpError::new(400, "malformed request line")),
+  };
+
+  let mut content_len = None;
+  loop {
+    let header = read_line(&mut reader)?;
+    if header.is_empty() {
+      break;
+    }
+    if let Some((name, value)) = header.split_once(':') {
+      if name.trim().eq_ignore_ascii_case("content-length") {
+        content_len = Some(value.trim().parse().map_err(|_| {
+          HttpError::new(400, "malformed Content-Length")
+        })?);
+      }
+    }
+  }
+
+  if method != "POST" {
+    return Err(HttpError::new(405, "endpoints take a POST"));
+  }
+  let content_len: usize = match content_len {
+    None => return Err(HttpError::new(411, "no Content-Length")),
+    Some(content_len) if content_len > MAX_BODY_LEN => {
+      return Err(HttpError::new(413, "the body is too long"))
+    }
+    Some(content_len) => content_len,
+  };
+  let mut body = vec![0; content_len];
+  reader.read_exact(&mut body).map_err(|error| {
+    HttpError::new(400, &format!("could not read the body: {}", error))
+  })?;
+  let body = String::from_utf8(body)
+    .map_err(|_| HttpError::new(400, "the body is not UTF-8"))?;
+
+  Ok((path, body))
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, HttpError> {
+  let mut line = String::new();
+  match reader.read_line(&mut line) {
+    Ok(0) => Err(HttpError::new(400, "the request ended early")),
+    Ok(_) => Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string()),
+    Err(ref error) if error.kind() == io::ErrorKind::InvalidData => {
+      Err(HttpError::new(400, "the request is not UTF-8"))
+    }
+    Err(error) => Err(HttpError::new(
+      408,
+      &format!("could not read the request: {}", error),
+    )),
+  }
+}
+
+fn reason_phrase(status_code: u16) -> &'static str {
+  match status_code {
+    200 => "OK",
+    400 => "Bad Request",
+    404 => "Not Found",
+    405 => "Method Not Allowed",
+    408 => "Request Timeout",
+    411 => "Length Required",
+    413 => "Payload Too Large",
+    _ => "Error",
+  }
+}
+
+type Endpoint = fn(&Request) -> Result<Report, String>;
+
+// `answer` runs the endpoint at `path` on the request body.
+fn answer(
+  path: &str,
+  body: &str,
+  limits: Limits,
+) -> Result<Report, HttpError> {
+  let start = Instant::now();
+  let (fields, endpoint): (&[&str], Endpoint) = match path {
+    "/solve" => (&["puzzle"], solve),
+    "/count" => (&["puzzle", "limit"], count),
+    "/validate" => (&["puzzle"], validate),
+    "/hint" => (&["puzzle", "grid"], hint),
+    "/rate" => (&["puzzle"], rate),
+    "/generate" => (&["clues", "seed"], generate),
+    _ => {
+      let message = format!("no endpoint {}", path);
+      return Err(HttpError::new(404, &message));
+    }
+  };
+
+  let request = Request::parse(path, body, fields, limits, start)
+    .map_err(|message| HttpError::new(400, &message))?;
+  let report: Report = endpoint(&request)
+    .map_err(|message| HttpError::new(400, &message))?;
+  Ok(report.with_duration(start.elapsed()))
+}
+
+// Request is the body of a request, checked to have only the fields
+// its endpoint knows.
+struct Request<'a> {
+  path: &'a str,
+  body: JsonValue,
+  search: Search,
+}
+
+impl<'a> Request<'a> {
+  fn parse(
+    path: &'a str,
+    body: &str,
+    fields: &[&str],
+    limits: Limits,
+    start: Instant,
+  ) -> Result<Request<'a>, String> {
+    let body =
+      JsonValue::parse(body).map_err(|error| error.to_string())?;
+    match body {
+      JsonValue::Object(ref members) => {
+        for (name, _) in members {
+          let is_known = fields.contains(&name.as_str())
+            || name == "node_limit"
+            || name == "time_limit_ms";
+          if !is_known {
+            return Err(format!("unknown field {:?}", name));
+          }
+        }
+      }
+      _ => return Err("the body must be a JSON object".to_string()),
+    }
+
+    let mut request = Request {
+      path,
+      body,
+      search: Search {
+        max_nodes: limits.max_nodes,
+        deadline: start + limits.time_limit,
+      },
+    };
+    if let Some(max_nodes) = request.number("node_limit")? {
+      request.search.max_nodes = max_nodes.min(limits.max_nodes);
+    }
+    if let Some(time_limit) = request.number("time_limit_ms")? {
+      let time_limit =
+        Duration::from_millis(time_limit).min(limits.time_limit);
+      request.search.deadline = start + time_limit;
+    }
+    Ok(request)
+  }
+
+  fn number(&self, name: &str) -> Result<Option<u64>, String> {
+    match self.body.get(name) {
+      None | Some(JsonValue::Null) => Ok(None),
+      Some(value) => value.as_u64().map(Some).ok_or_else(|| {
+        format!("{} must be a whole number, at least 0", name)
+      }),
+    }
+  }
+
+  fn grid(&self, name: &str) -> Result<Option<SudokuGrid>, String> {
+    match self.body.get(name) {
+      None | Some(JsonValue::Null) => Ok(None),
+      Some(value) => {
+        let text = value
+          .as_str()
+          .ok_or_else(|| format!("{} must be a string", name))?;
+        parse_puzzle(text)
+          .map(Some)
+          .map_err(|error| format!("{}: {}", name, error))
+      }
+    }
+  }
+
+  fn puzzle(&self) -> Result<SudokuGrid, String> {
+    self
+      .grid("puzzle")?
+      .ok_or_else(|| "missing field \"puzzle\"".to_string())
+  }
+}
+
+// Solutions is what a search for up to two solutions found.
+struct Solutions {
+  solutions: Vec<SudokuGrid>,
+  // Whether the search was cut short before settling how many
+  // solutions there are.
+  is_cut_short: bool,
+  num_nodes: u64,
+  num_backtracks: u64,
+}
+
+// `find_solutions` looks for a solution, and then for a second one to
+// tell whether it is unique. Returns None if the givens clash.
+fn find_solutions(
+  puzzle: &SudokuGrid,
+  search: &Search,
+) -> Option<Solutions> {
+  let mut solver = search.solver(&puzzle.filled_choices())?;
+  let mut observer = search.observer();
+  let mut solutions = vec![];
+  let mut is_cut_short = false;
+  while solutions.len() < 2 {
+    match solver.next_solution_observed(&mut observer) {
+      SolverResult::Solved(solution_choices) => {
+        let mut solution = puzzle.clone();
+        for choice in solution_choices {
+          solution.place(choice);
+        }
+        solutions.push(solution);
+      }
+      SolverResult::NoMoreSolutions => break,
+      SolverResult::NodeLimitReached => {
+        is_cut_short = true;
+        break;
+      }
+    }
+  }
+
+  Some(Solutions {
+    solutions,
+    is_cut_short,
+    num_nodes: solver.stats().num_nodes,
+    num_backtracks: solver.stats().num_backtracks,
+  })
+}
+
+fn solve(request: &Request) -> Result<Report, String> {
+  let puzzle = request.puzzle()?;
+  let found = match find_solutions(&puzzle, &request.search) {
+    Some(found) => found,
+    None => {
+      return Ok(
+        Report::new(request.path, &puzzle, "unsolvable")
+          .with_error("the givens clash"),
+      )
+    }
+  };
+
+  let (report_status, error) =
+    match (found.solutions.len(), found.is_cut_short) {
+      (0, false) => ("unsolvable", Some("no solution")),
+      (1, false) => ("solved", None),
+      (0, true) => (request.search.limit_status(), Some("gave up")),
+      (1, true) => (
+        request.search.limit_status(),
+        Some("gave up checking the solution is unique"),
+      ),
+      _ => ("multiple_solutions", Some("more than one solution")),
+    };
+  let mut report = Report::new(request.path, &puzzle, report_status)
+    .with_stat("num_nodes", found.num_nodes)
+    .with_stat("num_backtracks", found.num_backtracks);
+  for solution in &found.solutions {
+    report = report.with_solution(solution);
+  }
+  if let Some(error) = error {
+    report = report.with_error(error);
+  }
+  Ok(report)
+}
+
+fn count(request: &Request) -> Result<Report, String> {
+  let puzzle = request.puzzle()?;
+  let max_solutions = request.number("limit")?.unwrap_or(1000);
+  if max_solutions < 2 {
+    return Err("limit must be at least 2".to_string());
+  }
+  let mut solver = match request.search.solver(&puzzle.filled_choices())
+  {
+    Some(solver) => solver,
+    None => {
+      return Ok(
+        Report::new(request.path, &puzzle, "unsolvable")
+          .with_stat("num_solutions", 0)
+          .with_stat("exact", true),
+      )
+    }
+  };
+
+  let mut observer = request.search.observer();
+  let mut num_solutions = 0;
+  let mut is_exact = false;
+  let mut is_cut_short = false;
+  while num_solutions < max_solutions {
+    match solver.next_solution_observed(&mut observer) {
+      SolverResult::Solved(_) => num_solutions += 1,
+      SolverResult::NoMoreSolutions => {
+        is_exact = true;
+        break;
+      }
+      SolverResult::NodeLimitReached => {
+        is_cut_short = true;
+        break;
+      }
+    }
+  }
+
+  // Like `sudoku count`, a count stopped before a second solution
+  // settles nothing.
+  let report_status = match (num_solutions, is_exact) {
+    (0, true) => "unsolvable",
+    (1, true) => "solved",
+    (0, false) | (1, false) => request.search.limit_status(),
+    _ => "multiple_solutions",
+  };
+  let mut report = Report::new(request.path, &puzzle, report_status)
+    .with_stat("num_solutions", num_solutions)
+    .with_stat("exact", is_exact)
+    .with_stat("num_nodes", solver.stats().num_nodes);
+  if is_cut_short {
+    let error = if request.search.is_out_of_time() {
+      "counting stopped at the time limit"
+    } else {
+      "counting stopped at the node limit"
+    };
+    report = report.with_error(error);
+  }
+  Ok(report)
+}
+
+fn validate(request: &Request) -> Result<Report, String> {
+  let puzzle = request.puzzle()?;
+  Ok(match find_problem(&puzzle) {
+    None => Report::new(request.path, &puzzle, "valid"),
+    Some(problem) => {
+      Report::new(request.path, &puzzle, "invalid").with_error(&problem)
+    }
+  })
+}
+
+// `hint` answers with the status `hint` and the `row`, `column` and
+// `value` to fill in, or `wrong_entry` and the `row` and `column` of an
+// entry to take back. Rows and columns count from 1.
+fn hint(request: &Request) -> Result<Report, String> {
+  let puzzle = request.puzzle()?;
+  let grid = request.grid("grid")?.unwrap_or_else(|| puzzle.clone());
+  let mut game = match SudokuGame::new(&puzzle) {
+    Some(game) => game,
+    None => {
+      return Ok(
+        Report::new(request.path, &grid, "unsolvable")
+          .with_error("the givens clash"),
+      )
+    }
+  };
+  for position in SudokuPosition::all() {
+    let value = match grid.cell(position) {
+      SudokuCell::Filled(value) => value,
+      SudokuCell::Empty if game.is_given(position) => {
+        return Err("grid is missing a given".to_string())
+      }
+      SudokuCell::Empty => continue,
+    };
+    if game.is_given(position) {
+      if puzzle.cell(position) != SudokuCell::Filled(value) {
+        return Err("grid changes a given".to_string());
+      }
+    } else {
+      let _ = game.place(position, value);
+    }
+  }
+
+  let search = &request.search;
+  let report = match game
+    .hint_observed(search.max_nodes, search.observer())
+  {
+    Some(GameHint::WrongEntry(position)) => {
+      let (row_idx, col_idx) = position.as_usize_pair();
+      Report::new(request.path, &grid, "wrong_entry")
+        .with_stat("row", row_idx + 1)
+        .with_stat("column", col_idx + 1)
+    }
+    Some(GameHint::Value(choice)) => {
+      let (row_idx, col_idx) = choice.position.as_usize_pair();
+      Report::new(request.path, &grid, "hint")
+        .with_stat("row", row_idx + 1)
+        .with_stat("column", col_idx + 1)
+        .with_stat("value", choice.value.as_u8_value())
+    }
+    None if game.is_solved() => {
+      Report::new(request.path, &grid, "solved")
+    }
+    None if search.is_out_of_time() => {
+      Report::new(request.path, &grid, "time_limit_reached")
+        .with_error("gave up")
+    }
+    None => Report::new(request.path, &grid, "no_hint").with_error(
+      "the puzzle has no solution, or the solver gave up at the node \
+       limit",
+    ),
+  };
+  Ok(report)
+}
+
+fn rate(request: &Request) -> Result<Report, String> {
+  let puzzle = request.puzzle()?;
+  let found = match find_solutions(&puzzle, &request.search) {
+    Some(found) => found,
+    None => {
+      return Ok(
+        Report::new(request.path, &puzzle, "unsolvable")
+          .with_error("the givens clash"),
+      )
+    }
+  };
+
+  // As with `sudoku rate`, the rating is the number of choices made to
+  // solve the puzzle and prove its solution unique.
+  let report = match (found.solutions.len(), found.is_cut_short) {
+    (1, false) => Report::new(request.path, &puzzle, "rated")
+      .with_stat("difficulty", found.num_nodes),
+    (0, false) => Report::new(request.path, &puzzle, "unsolvable")
+      .with_error("no solution"),
+    (_, true) => {
+      Report::new(request.path, &puzzle, request.search.limit_status())
+        .with_error("gave up")
+    }
+    _ => Report::new(request.path, &puzzle, "multiple_solutions")
+      .with_error("more than one solution"),
+  };
+  Ok(report)
+}
+
+// `generate` draws random solution grids until one gives a puzzle with
+// a unique solution, like `sudoku generate`. Each candidate is checked
+// within the node limit.
+fn generate(request: &Request) -> Result<Report, String> {
+  let num_cells = SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE;
+  let num_clues = request.number("clues")?.unwrap_or(160) as usize;
+  if num_clues > num_cells {
+    return Err("clues is more than the number of cells".to_string());
+  }
+  let seed = match request.number("seed")? {
+    Some(seed) => seed,
+    None => rand::thread_rng().gen(),
+  };
+
+  let mut rng = StdRng::seed_from_u64(seed);
+  let mut positions = SudokuPosition::all();
+  positions.shuffle(&mut rng);
+  let mask = SudokuClueMask::from_positions(&positions[..num_clues]);
+  let budget = GeneratorBudget {
+    max_attempts: 1,
+    max_nodes_per_attempt: request.search.max_nodes,
+  };
+
+  for _ in 0..GeneratorBudget::default().max_attempts {
+    if request.search.is_out_of_time() {
+      return Ok(
+        Report::without_puzzle(
+          request.path,
+          "time_limit_reached",
+          "gave up",
+        )
+        .with_stat("seed", seed),
+      );
+    }
+    if let Some(given_choices) =
+      SudokuGenerator::fill_clue_mask_observed(
+        &mask,
+        budget,
+        &mut rng,
+        request.search.observer(),
+      )
+    {
+      let puzzle = SudokuGrid::from_choices(&given_choices);
+      return Ok(
+        Report::new(request.path, &puzzle, "generated")
+          .with_stat("num_clues", given_choices.len())
+          .with_stat("seed", seed),
+      );
+    }
+  }
+
+  let error = format!("no puzzle with {} clues found", num_clues);
+  Ok(
+    Report::without_puzzle(request.path, "failed", &error)
+      .with_stat("seed", seed),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::SmallRng;
+  use std::net::SocketAddr;
+  use sudoku_solving::format::{GridFormatter, GridLayout, SymbolSet};
+
+  // `start` serves on a free local port for the rest of the test run.
+  fn start() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let limits = Limits {
+      max_nodes: 1_000_000,
+      time_limit: Duration::from_secs(10),
+    };
+    thread::spawn(move || serve(listener, 2, limits));
+    address
+  }
+
+  // `send` sends a raw request, and returns the status code and the
+  // report of the reply.
+  fn send(address: SocketAddr, request: &str) -> (u16, JsonValue) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status_code = response
+      .split_whitespace()
+      .nth(1)
+      .and_then(|status_code| status_code.parse().ok())
+      .unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    (status_code, JsonValue::parse(body.trim_end()).unwrap())
+  }
+
+  fn post(address: SocketAddr, path: &str, body: &str) -> JsonValue {
+    let request = format!(
+      "POST {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+      path,
+      body.len(),
+      body
+    );
+    let (status_code, report) = send(address, &request);
+    assert_eq!(status_code, 200);
+    report
+  }
+
+  fn status(report: &JsonValue) -> &str {
+    report.get("status").and_then(JsonValue::as_str).unwrap()
+  }
+
+  fn stat<'a>(report: &'a JsonValue, name: &str) -> &'a JsonValue {
+    report
+      .get("stats")
+      .and_then(|stats| stats.get(name))
+      .unwrap()
+  }
+
+  fn format_line(grid: &SudokuGrid) -> String {
+    GridFormatter::new()
+      .with_layout(GridLayout::SingleLine)
+      .with_symbols(SymbolSet::default())
+      .format(grid)
+      .trim_end()
+      .to_string()
+  }
+
+  // `unique_puzzle` is a solution grid and, with its top row blanked,
+  // a puzzle whose only solution it is.
+  fn unique_puzzle() -> (SudokuGrid, SudokuGrid) {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let solution = SudokuGrid::from_choices(
+      &SudokuSolver::solve_randomly(&[], &mut rng).unwrap(),
+    );
+    let mut puzzle = solution.clone();
+    for position in &SudokuPosition::all()[..SUDOKU_DIM_USIZE] {
+      puzzle.clear(*position);
+    }
+    (solution, puzzle)
+  }
+
+  #[test]
+  fn solves_and_counts() {
+    let address = start();
+    let (solution, puzzle) = unique_puzzle();
+    let body = format!("{{\"puzzle\": \"{}\"}}", format_line(&puzzle));
+
+    let report = post(address, "/solve", &body);
+    assert_eq!(status(&report), "solved");
+    assert_eq!(
+      report.get("solutions"),
+      Some(&JsonValue::Array(vec![JsonValue::String(format_line(
+        &solution
+      ))]))
+    );
+
+    let report = post(address, "/count", &body);
+    assert_eq!(status(&report), "solved");
+    assert_eq!(stat(&report, "num_solutions").as_u64(), Some(1));
+    assert_eq!(stat(&report, "exact"), &JsonValue::Bool(true));
+  }
+
+  #[test]
+  fn reports_the_limit_a_search_hit() {
+    let address = start();
+    let empty = format_line(&SudokuGrid::default());
+    let body =
+      format!("{{\"puzzle\": \"{}\", \"node_limit\": 100}}", empty);
+
+    // Even the first solution takes more than 100 choices.
+    let report = post(address, "/solve", &body);
+    assert_eq!(status(&report), "node_limit_reached");
+    let report = post(address, "/count", &body);
+    assert_eq!(status(&report), "node_limit_reached");
+    assert_eq!(stat(&report, "exact"), &JsonValue::Bool(false));
+
+    let report = post(address, "/generate", "{\"time_limit_ms\": 0}");
+    assert_eq!(status(&report), "time_limit_reached");
+  }
+
+  #[test]
+  fn refuses_bodies_without_a_good_length() {
+    let address = start();
+
+    let (status_code, report) =
+      send(address, "POST /solve HTTP/1.1\r\n\r\n");
+    assert_eq!(status_code, 411);
+    assert_eq!(status(&report), "bad_request");
+
+    let request = format!(
+      "POST /solve HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+      MAX_BODY_LEN + 1
+    );
+    let (status_code, report) = send(address, &request);
+    assert_eq!(status_code, 413);
+    assert_eq!(status(&report), "bad_request");
+  }
+}
@@ -0,0 +1,120 @@
+use super::json::Report;
+use super::options::{Options, COMMON_FLAGS};
+use super::puzzles::{read_puzzles, Output, Puzzle, Status};
+use super::{EXIT_FAILURE, EXIT_MULTIPLE_SOLUTIONS};
+use std::time::Instant;
+use sudoku_solving::solver::{SolverResult, SudokuSolver};
+
+const USAGE: &str = "\
+usage: sudoku solve [OPTIONS] [FILE...]
+
+Solves each puzzle and prints its solution. A puzzle with more than one
+solution is reported, and its first solution printed. So is a puzzle
+the solver gave up on before telling its solution is unique.
+
+  --node-limit N  give up on a puzzle after the solver makes N choices
+                  (default 1000000)
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let options = Options::parse(args, &["--node-limit"], &usage)?;
+  let max_nodes = options.flag("--node-limit", 1_000_000, &usage)?;
+
+  let mut output = Output::new(options.output_format);
+  let mut status = Status::new();
+  read_puzzles(
+    &options,
+    &mut output,
+    &mut status,
+    |puzzle, output, status| {
+      solve_puzzle(puzzle, max_nodes, output, status)
+    },
+  );
+  output.flush();
+
+  Ok(status.exit_code())
+}
+
+fn solve_puzzle(
+  puzzle: Puzzle,
+  max_nodes: u64,
+  output: &mut Output,
+  status: &mut Status,
+) {
+  let start = Instant::now();
+  let given_choices = puzzle.grid.filled_choices();
+  let mut solver = match SudokuSolver::new(&given_choices) {
+    None => {
+      let error = "the givens clash";
+      eprintln!("error: {}: {}", puzzle.source, error);
+      status.fail(EXIT_FAILURE);
+      output.write_report(
+        &Report::new(&puzzle.source, &puzzle.grid, "unsolvable")
+          .with_error(error),
+      );
+      return;
+    }
+    Some(solver) => solver.with_node_limit(max_nodes),
+  };
+
+  let mut solutions = vec![];
+  let mut is_cut_short = false;
+  // A second solution is only looked for to tell that there is one.
+  while solutions.len() < 2 {
+    match solver.next_solution() {
+      SolverResult::Solved(solution_choices) => {
+        let mut solution = puzzle.grid.clone();
+        for choice in solution_choices {
+          solution.place(choice);
+        }
+        solutions.push(solution);
+      }
+      SolverResult::NoMoreSolutions => break,
+      SolverResult::NodeLimitReached => {
+        is_cut_short = true;
+        break;
+      }
+    }
+  }
+
+  let (report_status, error) = match (solutions.len(), is_cut_short) {
+    (0, false) => ("unsolvable", Some("no solution")),
+    (1, false) => ("solved", None),
+    (0, true) => ("node_limit_reached", Some("hit the node limit")),
+    (1, true) => (
+      "node_limit_reached",
+      Some("hit the node limit checking the solution is unique"),
+    ),
+    _ => ("multiple_solutions", Some("more than one solution")),
+  };
+  let mut report =
+    Report::new(&puzzle.source, &puzzle.grid, report_status)
+      .with_stat("num_nodes", solver.stats().num_nodes)
+      .with_stat("num_backtracks", solver.stats().num_backtracks)
+      .with_duration(start.elapsed());
+  for solution in &solutions {
+    report = report.with_solution(solution);
+  }
+
+  // Like `sudoku batch`, a solution that may not be the only one is
+  // still printed, with a warning.
+  if let Some(error) = error {
+    if solutions.is_empty() {
+      eprintln!("error: {}: {}", puzzle.source, error);
+      status.fail(EXIT_FAILURE);
+    } else {
+      eprintln!(
+        "warning: {}: {}; printing the first solution",
+        puzzle.source, error
+      );
+      status.fail(EXIT_MULTIPLE_SOLUTIONS);
+    }
+    report = report.with_error(error);
+  }
+
+  if let Some(solution) = solutions.first() {
+    output.write_solution(&puzzle, solution);
+  }
+  output.write_report(&report);
+}
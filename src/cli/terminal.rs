@@ -0,0 +1,226 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use sudoku_solving::core::{SUDOKU_DIM_SQRT_USIZE, SUDOKU_DIM_USIZE};
+
+// Terminal is the user's terminal, taken over for an interactive
+// command: keys are read one at a time as they are pressed, and the
+// screen is redrawn whole. It talks to `/dev/tty` rather than standard
+// input and output, so that puzzles can still be piped in, and it
+// switches modes with `stty`, so it needs a Unix-like system.
+//
+// The terminal is given back as it was when the Terminal is dropped.
+pub struct Terminal {
+  tty: File,
+  // What `stty -g` said before we changed anything.
+  saved_mode: String,
+  // A thread reads the terminal, so that we can wait for a key with a
+  // timeout. It stops at the first error.
+  input: Receiver<io::Result<Vec<u8>>>,
+  // Bytes read but not turned into keys yet.
+  pending: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Key {
+  Up,
+  Down,
+  Left,
+  Right,
+  Backspace,
+  Delete,
+  Enter,
+  Escape,
+  // Ctrl-C, which raw mode hands to us instead of killing the process.
+  Interrupt,
+  Char(char),
+}
+
+// ANSI styles for `Terminal::draw`.
+pub const BOLD: &str = "\x1b[1m";
+pub const DIM: &str = "\x1b[2m";
+pub const REVERSE: &str = "\x1b[7m";
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const CYAN: &str = "\x1b[36m";
+pub const RESET: &str = "\x1b[0m";
+
+impl Terminal {
+  pub fn open() -> io::Result<Terminal> {
+    let tty =
+      OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let saved_mode = stty(&tty, &["-g"])?.trim().to_string();
+    stty(&tty, &["raw", "-echo"])?;
+
+    let mut input_tty = tty.try_clone()?;
+    let (input_sender, input) = mpsc::channel();
+    thread::spawn(move || loop {
+      let mut bytes = [0; 64];
+      let result = input_tty
+        .read(&mut bytes)
+        .map(|num_bytes| bytes[..num_bytes].to_vec());
+      let is_err = result.is_err();
+      if input_sender.send(result).is_err() || is_err {
+        return;
+      }
+    });
+
+    let mut terminal = Terminal {
+      tty,
+      saved_mode,
+      input,
+      pending: vec![],
+    };
+    // Switch to the alternate screen, and hide the cursor.
+    terminal.tty.write_all(b"\x1b[?1049h\x1b[?25l")?;
+    Ok(terminal)
+  }
+
+  // `draw` replaces what is on the screen with `lines`.
+  pub fn draw(&mut self, lines: &[String]) -> io::Result<()> {
+    let mut screen = String::from("\x1b[H\x1b[2J");
+    for line in lines {
+      // Raw mode doesn't turn "\n" into "\r\n" for us.
+      screen.push_str(line);
+      screen.push_str(RESET);
+      screen.push_str("\r\n");
+    }
+    self.tty.write_all(screen.as_bytes())?;
+    self.tty.flush()
+  }
+
+  // `read_key` waits for the next key press.
+  pub fn read_key(&mut self) -> io::Result<Key> {
+    loop {
+      if let Some(key) = self.next_pending_key() {
+        return Ok(key);
+      }
+      match self.input.recv() {
+        Ok(bytes) => self.pending.extend_from_slice(&bytes?),
+        Err(_) => return Err(input_closed()),
+      }
+    }
+  }
+
+  // `read_key_timeout` waits for the next key press, but no longer
+  // than `timeout`. Returns None if no key was pressed in time.
+  pub fn read_key_timeout(
+    &mut self,
+    timeout: Duration,
+  ) -> io::Result<Option<Key>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+      if let Some(key) = self.next_pending_key() {
+        return Ok(Some(key));
+      }
+      let timeout = deadline.saturating_duration_since(Instant::now());
+      match self.input.recv_timeout(timeout) {
+        Ok(bytes) => self.pending.extend_from_slice(&bytes?),
+        Err(RecvTimeoutError::Timeout) => return Ok(None),
+        Err(RecvTimeoutError::Disconnected) => {
+          return Err(input_closed())
+        }
+      }
+    }
+  }
+
+  // `next_pending_key` takes the first key off what was read but not
+  // used yet. Unknown keys are dropped. An escape sequence arrives all
+  // at once, so it is never split across reads.
+  fn next_pending_key(&mut self) -> Option<Key> {
+    while !self.pending.is_empty() {
+      let (key, key_len) = parse_key(&self.pending);
+      self.pending.drain(..key_len);
+      if key.is_some() {
+        return key;
+      }
+    }
+    None
+  }
+}
+
+impl Drop for Terminal {
+  fn drop(&mut self) {
+    // Show the cursor, and leave the alternate screen.
+    let _ = self.tty.write_all(b"\x1b[?25h\x1b[?1049l");
+    let _ = self.tty.flush();
+    let _ = stty(&self.tty, &[self.saved_mode.as_str()]);
+  }
+}
+
+// `grid_lines` lays out a grid with box borders, two columns per cell.
+// `cell_text` gives the (styled) symbol in each cell.
+pub fn grid_lines<F>(cell_text: F) -> Vec<String>
+where
+  F: Fn(usize, usize) -> String,
+{
+  let box_border =
+    vec!["-".repeat(2 * SUDOKU_DIM_SQRT_USIZE); SUDOKU_DIM_SQRT_USIZE]
+      .join("-+");
+
+  let mut lines = vec![];
+  for row_idx in 0..SUDOKU_DIM_USIZE {
+    if row_idx > 0 && row_idx % SUDOKU_DIM_SQRT_USIZE == 0 {
+      lines.push(box_border.clone());
+    }
+    let mut line = String::new();
+    for col_idx in 0..SUDOKU_DIM_USIZE {
+      if col_idx > 0 && col_idx % SUDOKU_DIM_SQRT_USIZE == 0 {
+        line.push_str(" |");
+      }
+      line.push(' ');
+      line.push_str(&cell_text(row_idx, col_idx));
+    }
+    lines.push(line);
+  }
+
+  lines
+}
+
+fn input_closed() -> io::Error {
+  io::Error::new(io::ErrorKind::UnexpectedEof, "the terminal closed")
+}
+
+fn stty(tty: &File, args: &[&str]) -> io::Result<String> {
+  let output = Command::new("stty")
+    .args(args)
+    .stdin(Stdio::from(tty.try_clone()?))
+    .stderr(Stdio::inherit())
+    .output()?;
+  if !output.status.success() {
+    return Err(io::Error::other("stty failed"));
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// `parse_key` reads the key at the start of `bytes`, and how many bytes
+// it takes up. The key is None when we don't know it.
+fn parse_key(bytes: &[u8]) -> (Option<Key>, usize) {
+  if bytes[0] == 0x1b {
+    let (key, key_len) = match bytes[1..] {
+      [b'[', b'A', ..] | [b'O', b'A', ..] => (Some(Key::Up), 3),
+      [b'[', b'B', ..] | [b'O', b'B', ..] => (Some(Key::Down), 3),
+      [b'[', b'C', ..] | [b'O', b'C', ..] => (Some(Key::Right), 3),
+      [b'[', b'D', ..] | [b'O', b'D', ..] => (Some(Key::Left), 3),
+      [b'[', b'3', b'~', ..] => (Some(Key::Delete), 4),
+      // Some other sequence: skip it all.
+      [b'[', ..] | [b'O', ..] => (None, bytes.len()),
+      _ => (Some(Key::Escape), 1),
+    };
+    return (key, key_len);
+  }
+
+  let key = match bytes[0] {
+    0x03 => Some(Key::Interrupt),
+    0x7f | 0x08 => Some(Key::Backspace),
+    b'\r' | b'\n' => Some(Key::Enter),
+    byte if byte.is_ascii_graphic() || byte == b' ' => {
+      Some(Key::Char(byte as char))
+    }
+    _ => None,
+  };
+  (key, 1)
+}
@@ -0,0 +1,93 @@
+use super::json::Report;
+use super::options::{Options, COMMON_FLAGS};
+use super::puzzles::{read_puzzles, Output, Status};
+use super::EXIT_FAILURE;
+use sudoku_solving::core::{
+  SudokuBox, SudokuCell, SudokuGrid, SudokuPosition,
+};
+
+const USAGE: &str = "\
+usage: sudoku validate [OPTIONS] [FILE...]
+
+Checks that each grid is completely filled and breaks no rule,
+printing one line per grid: `SOURCE: valid`, or what is wrong.
+";
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}\n{}", USAGE, COMMON_FLAGS);
+  let options = Options::parse(args, &[], &usage)?;
+
+  let mut output = Output::new(options.output_format);
+  let mut status = Status::new();
+  read_puzzles(
+    &options,
+    &mut output,
+    &mut status,
+    |puzzle, output, status| match find_problem(&puzzle.grid) {
+      None => {
+        output.write_line(&format!("{}: valid", puzzle.source));
+        output.write_report(&Report::new(
+          &puzzle.source,
+          &puzzle.grid,
+          "valid",
+        ));
+      }
+      Some(problem) => {
+        output.write_line(&format!("{}: {}", puzzle.source, problem));
+        status.fail(EXIT_FAILURE);
+        output.write_report(
+          &Report::new(&puzzle.source, &puzzle.grid, "invalid")
+            .with_error(&problem),
+        );
+      }
+    },
+  );
+  output.flush();
+
+  Ok(status.exit_code())
+}
+
+// `find_problem` describes the first clash in the grid, or else its
+// empty cells. Returns None for a valid, filled grid.
+pub fn find_problem(grid: &SudokuGrid) -> Option<String> {
+  let mut num_empty_cells = 0;
+  for position in SudokuPosition::all() {
+    let value = match grid.cell(position) {
+      SudokuCell::Empty => {
+        num_empty_cells += 1;
+        continue;
+      }
+      SudokuCell::Filled(value) => value,
+    };
+
+    let (row_idx, col_idx) = position.as_usize_pair();
+    let box_positions = SudokuBox::for_position(position)
+      .positions()
+      .collect::<Vec<_>>();
+    let clash = SudokuPosition::all().into_iter().find(|&peer| {
+      let (peer_row_idx, peer_col_idx) = peer.as_usize_pair();
+      let is_peer = peer != position
+        && (peer_row_idx == row_idx
+          || peer_col_idx == col_idx
+          || box_positions.contains(&peer));
+      is_peer && grid.cell(peer) == SudokuCell::Filled(value)
+    });
+    if let Some(peer) = clash {
+      let (peer_row_idx, peer_col_idx) = peer.as_usize_pair();
+      return Some(format!(
+        "{} at row {}, column {} clashes with row {}, column {}",
+        value.as_u8_value(),
+        row_idx + 1,
+        col_idx + 1,
+        peer_row_idx + 1,
+        peer_col_idx + 1
+      ));
+    }
+  }
+
+  if num_empty_cells > 0 {
+    Some(format!("{} empty cells", num_empty_cells))
+  } else {
+    None
+  }
+}
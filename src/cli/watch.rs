@@ -0,0 +1,284 @@
+use super::options::{usage_error, Options, COMMON_FLAGS};
+use super::puzzles::{read_puzzles, Output, Status};
+use super::terminal::{
+  grid_lines, Key, Terminal, BOLD, CYAN, DIM, GREEN, RED, RESET,
+};
+use super::{EXIT_FAILURE, EXIT_IO_ERROR, EXIT_SUCCESS};
+use std::io;
+use std::time::Duration;
+use sudoku_solving::{
+  core::{SudokuCell, SudokuGrid, SudokuPosition},
+  format::SymbolSet,
+  solver::{SearchControl, SolverEvent, SolverResult, SudokuSolver},
+};
+
+const USAGE: &str = "\
+usage: sudoku watch [OPTIONS] [FILE]
+
+Shows the solver at work on a puzzle, redrawing the grid as the
+backtracking search tries values and takes them back. Givens are bold,
+the search's tentative values cyan; the latest value is green, and a
+cell just emptied by backtracking is a red dot. The exit status is 0
+if a solution was found.
+
+Keys:
+  space  pause or resume
+  n      take one step, when paused
+  + -    go faster or slower
+  q      quit
+
+  --puzzle N      watch the Nth puzzle of the input (default 1)
+  --delay MS      milliseconds between steps (default 50)
+  --node-limit N  give up after the solver makes N choices
+";
+
+// The slowest `-` goes, in milliseconds.
+const MAX_DELAY_MILLIS: u64 = 5000;
+
+pub fn run(args: &[String]) -> Result<i32, i32> {
+  let usage = format!("{}{}", USAGE, COMMON_FLAGS);
+  let options = Options::parse(
+    args,
+    &["--puzzle", "--delay", "--node-limit"],
+    &usage,
+  )?;
+  let puzzle_number: usize = options.flag("--puzzle", 1, &usage)?;
+  let delay_millis = options.flag("--delay", 50, &usage)?;
+  let max_nodes = options.flag("--node-limit", 0, &usage)?;
+  if puzzle_number == 0 {
+    return Err(usage_error("--puzzle counts from 1", &usage));
+  }
+
+  let mut output = Output::new(options.output_format);
+  let mut status = Status::new();
+  let mut puzzles = vec![];
+  read_puzzles(&options, &mut output, &mut status, |puzzle, _, _| {
+    puzzles.push(puzzle)
+  });
+  let puzzle = match puzzles.into_iter().nth(puzzle_number - 1) {
+    Some(puzzle) => puzzle,
+    None => {
+      eprintln!("error: there is no puzzle {} to watch", puzzle_number);
+      status.fail(EXIT_FAILURE);
+      return Ok(status.exit_code());
+    }
+  };
+  let mut solver =
+    match SudokuSolver::new(&puzzle.grid.filled_choices()) {
+      Some(solver) => solver,
+      None => {
+        eprintln!("error: {}: the givens clash", puzzle.source);
+        return Ok(EXIT_FAILURE);
+      }
+    };
+  if max_nodes > 0 {
+    solver = solver.with_node_limit(max_nodes);
+  }
+
+  let result = Terminal::open().and_then(|terminal| {
+    let mut watcher =
+      Watcher::new(terminal, &puzzle.grid, delay_millis);
+    watcher.watch(&mut solver)
+  });
+  match result {
+    Ok(true) => Ok(EXIT_SUCCESS),
+    Ok(false) => Ok(EXIT_FAILURE),
+    Err(error) => {
+      eprintln!("error: terminal: {}", error);
+      Ok(EXIT_IO_ERROR)
+    }
+  }
+}
+
+// Watcher draws the search as the solver reports its events.
+struct Watcher {
+  terminal: Terminal,
+  givens: SudokuGrid,
+  // The givens and the search's current choices.
+  grid: SudokuGrid,
+  symbols: SymbolSet,
+  last_event: Option<SolverEvent>,
+  num_nodes: u64,
+  num_backtracks: u64,
+  delay_millis: u64,
+  is_paused: bool,
+  message: String,
+  // Whether the user quit before the search was done.
+  has_quit: bool,
+  // An error from the terminal, which stopped the search.
+  error: Option<io::Error>,
+}
+
+impl Watcher {
+  fn new(
+    terminal: Terminal,
+    puzzle: &SudokuGrid,
+    delay_millis: u64,
+  ) -> Watcher {
+    Watcher {
+      terminal,
+      givens: puzzle.clone(),
+      grid: puzzle.clone(),
+      symbols: SymbolSet::default(),
+      last_event: None,
+      num_nodes: 0,
+      num_backtracks: 0,
+      delay_millis,
+      is_paused: false,
+      message: String::new(),
+      has_quit: false,
+      error: None,
+    }
+  }
+
+  // `watch` runs the search to the first solution, or until the user
+  // quits, and returns whether a solution was found.
+  fn watch(&mut self, solver: &mut SudokuSolver) -> io::Result<bool> {
+    let result =
+      solver.next_solution_observed(|event| self.step(event));
+    if let Some(error) = self.error.take() {
+      return Err(error);
+    }
+    if self.has_quit {
+      return Ok(false);
+    }
+
+    let is_solved = match result {
+      SolverResult::Solved(_) => {
+        self.message = format!(
+          "{}Solved after {} choices. Press q to quit.",
+          GREEN, self.num_nodes
+        );
+        true
+      }
+      SolverResult::NoMoreSolutions => {
+        self.message =
+          format!("{}There is no solution. Press q to quit.", RED);
+        false
+      }
+      SolverResult::NodeLimitReached => {
+        self.message =
+          format!("{}Gave up at the node limit. Press q to quit.", RED);
+        false
+      }
+    };
+    self.last_event = None;
+    self.draw()?;
+    loop {
+      match self.terminal.read_key()? {
+        Key::Char('q') | Key::Escape | Key::Interrupt => {
+          return Ok(is_solved)
+        }
+        _ => {}
+      }
+    }
+  }
+
+  fn step(&mut self, event: SolverEvent) -> SearchControl {
+    match event {
+      SolverEvent::Place(choice) => {
+        self.grid.place(choice);
+        self.num_nodes += 1;
+      }
+      SolverEvent::Backtrack(choice) => {
+        self.grid.clear(choice.position);
+        self.num_backtracks += 1;
+      }
+    }
+    self.last_event = Some(event);
+
+    match self.wait() {
+      Ok(control) => control,
+      Err(error) => {
+        self.error = Some(error);
+        SearchControl::Stop
+      }
+    }
+  }
+
+  // `wait` draws the grid and waits before the next step, handling
+  // keys meanwhile.
+  fn wait(&mut self) -> io::Result<SearchControl> {
+    loop {
+      self.draw()?;
+      let key = if self.is_paused {
+        self.terminal.read_key()?
+      } else {
+        let delay = Duration::from_millis(self.delay_millis);
+        match self.terminal.read_key_timeout(delay)? {
+          None => return Ok(SearchControl::Continue),
+          Some(key) => key,
+        }
+      };
+
+      match key {
+        Key::Char('q') | Key::Escape | Key::Interrupt => {
+          self.has_quit = true;
+          return Ok(SearchControl::Stop);
+        }
+        Key::Char(' ') => {
+          self.is_paused = !self.is_paused;
+          if !self.is_paused {
+            return Ok(SearchControl::Continue);
+          }
+        }
+        Key::Char('n') if self.is_paused => {
+          return Ok(SearchControl::Continue)
+        }
+        Key::Char('+') | Key::Char('=') => self.delay_millis /= 2,
+        Key::Char('-') => {
+          self.delay_millis =
+            (self.delay_millis * 2).clamp(1, MAX_DELAY_MILLIS)
+        }
+        _ => {}
+      }
+    }
+  }
+
+  fn draw(&mut self) -> io::Result<()> {
+    let mut lines = grid_lines(|row_idx, col_idx| {
+      self.cell_text(SudokuPosition::new(row_idx as u8, col_idx as u8))
+    });
+    lines.push(String::new());
+    lines.push(format!(
+      "Choices: {}. Backtracks: {}. Delay: {} ms.{}",
+      self.num_nodes,
+      self.num_backtracks,
+      self.delay_millis,
+      if self.is_paused { " Paused." } else { "" }
+    ));
+    lines.push(self.message.clone());
+    lines.push(format!(
+      "{}space pauses, n steps, + and - change speed, q quits",
+      DIM
+    ));
+
+    self.terminal.draw(&lines)
+  }
+
+  fn cell_text(&self, position: SudokuPosition) -> String {
+    let is_latest = match self.last_event {
+      Some(SolverEvent::Place(choice))
+      | Some(SolverEvent::Backtrack(choice)) => {
+        choice.position == position
+      }
+      None => false,
+    };
+    let (style, symbol) = match self.grid.cell(position) {
+      SudokuCell::Filled(value) => {
+        let style = if self.givens.cell(position) != SudokuCell::Empty {
+          BOLD
+        } else if is_latest {
+          GREEN
+        } else {
+          CYAN
+        };
+        (style, self.symbols.symbol(value))
+      }
+      SudokuCell::Empty if is_latest => (RED, '.'),
+      SudokuCell::Empty => (DIM, '.'),
+    };
+
+    format!("{}{}{}", style, symbol, RESET)
+  }
+}
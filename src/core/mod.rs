@@ -1,6 +1,7 @@
 mod sudoku_box;
 mod sudoku_cell;
 mod sudoku_choice;
+mod sudoku_clue_mask;
 mod sudoku_grid;
 mod sudoku_position;
 mod sudoku_value;
@@ -14,6 +15,7 @@ pub const SUDOKU_DIM_USIZE: usize =
 pub use self::sudoku_box::SudokuBox;
 pub use self::sudoku_cell::SudokuCell;
 pub use self::sudoku_choice::SudokuChoice;
+pub use self::sudoku_clue_mask::SudokuClueMask;
 pub use self::sudoku_grid::SudokuGrid;
 pub use self::sudoku_position::SudokuPosition;
 pub use self::sudoku_value::SudokuValue;
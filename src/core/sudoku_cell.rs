@@ -1,10 +1,37 @@
 use super::SudokuValue;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 // SudokuCell is a cell in the 9x9 Sudoku grid. It can be Empty (if
 // nothing has been written into the cell), or Filled (if we have chosen
 // a value here).
-#[derive(Clone, Copy, Debug)]
+//
+// In JSON an empty cell is `null` and a filled one is its value.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Serialize, Deserialize),
+  serde(from = "Option<SudokuValue>", into = "Option<SudokuValue>")
+)]
 pub enum SudokuCell {
   Empty,
   Filled(SudokuValue),
 }
+
+impl From<Option<SudokuValue>> for SudokuCell {
+  fn from(value: Option<SudokuValue>) -> SudokuCell {
+    match value {
+      None => SudokuCell::Empty,
+      Some(value) => SudokuCell::Filled(value),
+    }
+  }
+}
+
+impl From<SudokuCell> for Option<SudokuValue> {
+  fn from(cell: SudokuCell) -> Option<SudokuValue> {
+    match cell {
+      SudokuCell::Empty => None,
+      SudokuCell::Filled(value) => Some(value),
+    }
+  }
+}
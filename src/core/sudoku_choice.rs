@@ -1,10 +1,15 @@
 use super::SudokuPosition;
 use super::SudokuValue;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 // A SudokuChoice is a chosen value for a chosen position. When we make
 // a "choice," we are penciling in the given value at the specified
 // position.
+//
+// In JSON a choice is `{"position": {"row": 0, "col": 8}, "value": 5}`.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SudokuChoice {
   pub position: SudokuPosition,
   pub value: SudokuValue,
@@ -0,0 +1,62 @@
+use super::{SudokuPosition, SUDOKU_DIM_USIZE};
+
+// A SudokuClueMask marks the positions of the grid that should hold a
+// clue. Designers like to draw these as pictures (hearts, letters...)
+// and then ask us to find digits that make a proper puzzle.
+#[derive(Clone, Copy, Default)]
+pub struct SudokuClueMask {
+  cells: [[bool; SUDOKU_DIM_USIZE]; SUDOKU_DIM_USIZE],
+}
+
+impl SudokuClueMask {
+  pub fn from_positions(
+    positions: &[SudokuPosition],
+  ) -> SudokuClueMask {
+    let mut mask = SudokuClueMask::default();
+    for &position in positions {
+      mask.set(position, true);
+    }
+
+    mask
+  }
+
+  pub fn set(&mut self, position: SudokuPosition, is_clue: bool) {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    self.cells[row_idx][col_idx] = is_clue;
+  }
+
+  pub fn contains(&self, position: SudokuPosition) -> bool {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    self.cells[row_idx][col_idx]
+  }
+
+  // `positions` returns the masked positions in left-to-right,
+  // top-to-bottom order.
+  pub fn positions(&self) -> Vec<SudokuPosition> {
+    SudokuPosition::all()
+      .into_iter()
+      .filter(|&position| self.contains(position))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn positions_come_back_in_reading_order() {
+    let positions = [
+      SudokuPosition::new(15, 0),
+      SudokuPosition::new(0, 15),
+      SudokuPosition::new(3, 4),
+    ];
+    let mask = SudokuClueMask::from_positions(&positions);
+    assert!(mask.contains(SudokuPosition::new(3, 4)));
+    assert!(!mask.contains(SudokuPosition::new(4, 3)));
+    assert_eq!(
+      mask.positions(),
+      vec![positions[1], positions[2], positions[0]]
+    );
+  }
+}
@@ -1,19 +1,69 @@
-use super::{SudokuCell, SudokuChoice, SUDOKU_DIM_USIZE};
-use std::fmt;
+use super::{
+  SudokuCell, SudokuChoice, SudokuPosition, SUDOKU_DIM_USIZE,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 // Represents the 9x9 Sudoku grid.
+//
+// In JSON a grid is an array of rows, top to bottom, and each row is an
+// array of cells, left to right: `[[5, null, ...], ...]`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Serialize, Deserialize),
+  serde(transparent)
+)]
 pub struct SudokuGrid {
   // 9x9 cells, each of which can be empty, or filled with a
   // SudokuValue.
   cells: [[SudokuCell; SUDOKU_DIM_USIZE]; SUDOKU_DIM_USIZE],
 }
 
-impl SudokuGrid {
-  pub fn default() -> SudokuGrid {
+impl Default for SudokuGrid {
+  fn default() -> SudokuGrid {
     let cells =
       [[SudokuCell::Empty; SUDOKU_DIM_USIZE]; SUDOKU_DIM_USIZE];
     SudokuGrid { cells }
   }
+}
+
+impl SudokuGrid {
+  // `from_choices` builds a grid with the given SudokuChoices filled in.
+  // Every other cell is empty.
+  pub fn from_choices(choices: &[SudokuChoice]) -> SudokuGrid {
+    let mut grid = SudokuGrid::default();
+    for &choice in choices {
+      grid.place(choice);
+    }
+
+    grid
+  }
+
+  // `cell` returns what is written at the given SudokuPosition.
+  pub fn cell(&self, position: SudokuPosition) -> SudokuCell {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    self.cells[row_idx][col_idx]
+  }
+
+  // `filled_choices` returns a SudokuChoice for every filled cell, in
+  // left-to-right, top-to-bottom order.
+  pub fn filled_choices(&self) -> Vec<SudokuChoice> {
+    SudokuPosition::all()
+      .into_iter()
+      .filter_map(|position| match self.cell(position) {
+        SudokuCell::Empty => None,
+        SudokuCell::Filled(value) => {
+          Some(SudokuChoice::new(position, value))
+        }
+      }).collect()
+  }
+
+  // `clear` erases whatever was written at the given SudokuPosition.
+  pub fn clear(&mut self, position: SudokuPosition) {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    self.cells[row_idx][col_idx] = SudokuCell::Empty;
+  }
 
   // `place` plays a SudokuChoice: it writes the given SudokuValue in at
   // the given SudokuPosition in the grid.
@@ -26,24 +76,42 @@ impl SudokuGrid {
   }
 }
 
-impl fmt::Display for SudokuGrid {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    for row in &self.cells {
-      for idx in 0..(row.len()) {
-        match row[idx] {
-          SudokuCell::Empty => write!(f, ".")?,
-          SudokuCell::Filled(value) => write!(f, "{:02}", value.as_u8_value())?
-        }
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+  use super::super::{SudokuValue, SUDOKU_DIM_U8};
+  use super::*;
+  use serde_json;
 
+  #[test]
+  fn json_is_rows_of_cells_with_null_for_empty() {
+    let mut grid = SudokuGrid::default();
+    grid.place(SudokuChoice::new(
+      SudokuPosition::new(0, 0),
+      SudokuValue::new(5),
+    ));
+    grid.place(SudokuChoice::new(
+      SudokuPosition::new(SUDOKU_DIM_U8 - 1, 1),
+      SudokuValue::new(16),
+    ));
 
-        if idx < row.len() - 1 {
-          write!(f, "|")?;
-        }
-      }
-
-      writeln!(f, "")?;
+    let json = serde_json::to_value(&grid).unwrap();
+    let rows = json.as_array().unwrap();
+    assert_eq!(rows.len(), SUDOKU_DIM_USIZE);
+    for row in rows {
+      assert_eq!(row.as_array().unwrap().len(), SUDOKU_DIM_USIZE);
     }
+    assert_eq!(rows[0][0], serde_json::json!(5));
+    assert_eq!(rows[0][1], serde_json::Value::Null);
+    assert_eq!(rows[SUDOKU_DIM_USIZE - 1][1], serde_json::json!(16));
+
+    let parsed: SudokuGrid = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed, grid);
+  }
 
-    Ok(())
+  #[test]
+  fn json_rejects_out_of_range_cells() {
+    let mut json = serde_json::to_value(SudokuGrid::default()).unwrap();
+    json[0][0] = serde_json::json!(17);
+    assert!(serde_json::from_value::<SudokuGrid>(json).is_err());
   }
 }
@@ -1,7 +1,19 @@
 use super::SUDOKU_DIM_U8;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
 
 // A SudokuPosition is a position in the 9x9 Sudoku grid.
+//
+// In JSON a position is `{"row": 0, "col": 8}`, counting from zero at
+// the top left.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Serialize, Deserialize),
+  serde(try_from = "JsonSudokuPosition", into = "JsonSudokuPosition")
+)]
 pub struct SudokuPosition {
   row_idx: u8,
   col_idx: u8,
@@ -40,3 +52,61 @@ impl SudokuPosition {
     (self.row_idx as usize, self.col_idx as usize)
   }
 }
+
+// JsonSudokuPosition is the unchecked JSON form of a SudokuPosition.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct JsonSudokuPosition {
+  row: u8,
+  col: u8,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<JsonSudokuPosition> for SudokuPosition {
+  type Error = String;
+
+  fn try_from(
+    json: JsonSudokuPosition,
+  ) -> Result<SudokuPosition, String> {
+    if json.row < SUDOKU_DIM_U8 && json.col < SUDOKU_DIM_U8 {
+      Ok(SudokuPosition::new(json.row, json.col))
+    } else {
+      Err(format!(
+        "sudoku position must be within the {}x{} grid, got ({}, {})",
+        SUDOKU_DIM_U8, SUDOKU_DIM_U8, json.row, json.col
+      ))
+    }
+  }
+}
+
+#[cfg(feature = "serde")]
+impl From<SudokuPosition> for JsonSudokuPosition {
+  fn from(position: SudokuPosition) -> JsonSudokuPosition {
+    JsonSudokuPosition {
+      row: position.row_idx,
+      col: position.col_idx,
+    }
+  }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+  use super::*;
+  use serde_json;
+
+  #[test]
+  fn json_names_the_row_and_col() {
+    let position = SudokuPosition::new(3, 15);
+    let json = serde_json::to_string(&position).unwrap();
+    assert_eq!(json, r#"{"row":3,"col":15}"#);
+    let parsed: SudokuPosition = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, position);
+  }
+
+  #[test]
+  fn json_rejects_positions_off_the_grid() {
+    for json in &[r#"{"row":16,"col":0}"#, r#"{"row":0,"col":16}"#] {
+      assert!(serde_json::from_str::<SudokuPosition>(json).is_err());
+    }
+  }
+}
@@ -1,8 +1,19 @@
 pub use super::SUDOKU_DIM_U8;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 
 // A SudokuValue is a number 1 through 9 (inclusive). Zero is *not* a
 // valid Sudoku value.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+//
+// In JSON a SudokuValue is just the number, like `5`. Out of range
+// numbers are rejected.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Serialize, Deserialize),
+  serde(try_from = "u8", into = "u8")
+)]
 pub struct SudokuValue(u8);
 
 impl SudokuValue {
@@ -11,10 +22,15 @@ impl SudokuValue {
   }
 
   pub fn new(val: u8) -> SudokuValue {
-    assert!(1 <= val && val <= SUDOKU_DIM_U8);
+    assert!((1..=SUDOKU_DIM_U8).contains(&val));
     SudokuValue(val)
   }
 
+  // `all` returns every SudokuValue, in increasing order.
+  pub fn all() -> Vec<SudokuValue> {
+    (1..=SUDOKU_DIM_U8).map(SudokuValue).collect()
+  }
+
   // `as_u8_value` returns the numeric value 1 through 9 (inclusive).
   // Should be used only for display.
   pub fn as_u8_value(self) -> u8 {
@@ -39,3 +55,44 @@ impl SudokuValue {
     }
   }
 }
+
+impl TryFrom<u8> for SudokuValue {
+  type Error = String;
+
+  fn try_from(val: u8) -> Result<SudokuValue, String> {
+    if (1..=SUDOKU_DIM_U8).contains(&val) {
+      Ok(SudokuValue(val))
+    } else {
+      Err(format!(
+        "sudoku value must be 1 through {}, got {}",
+        SUDOKU_DIM_U8, val
+      ))
+    }
+  }
+}
+
+impl From<SudokuValue> for u8 {
+  fn from(value: SudokuValue) -> u8 {
+    value.as_u8_value()
+  }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+  use super::*;
+  use serde_json;
+
+  #[test]
+  fn json_is_the_bare_number() {
+    let value = SudokuValue::new(16);
+    assert_eq!(serde_json::to_string(&value).unwrap(), "16");
+    let parsed: SudokuValue = serde_json::from_str("16").unwrap();
+    assert_eq!(parsed, value);
+  }
+
+  #[test]
+  fn json_rejects_out_of_range_values() {
+    assert!(serde_json::from_str::<SudokuValue>("0").is_err());
+    assert!(serde_json::from_str::<SudokuValue>("17").is_err());
+  }
+}
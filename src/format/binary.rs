@@ -0,0 +1,393 @@
+use core::{
+  SudokuCell, SudokuChoice, SudokuClueMask, SudokuGrid, SudokuPosition,
+  SudokuValue, SUDOKU_DIM_SQRT_U8, SUDOKU_DIM_USIZE,
+};
+use std::error::Error;
+use std::fmt;
+
+// Every encoding starts with a two byte header: what is encoded, and
+// the side of a box, so that bytes written for a 9x9 grid are never
+// read as a 16x16 one.
+const GRID_KIND: u8 = b'G';
+const CLUE_MASK_KIND: u8 = b'M';
+const HEADER_LEN: usize = 2;
+
+const NUM_CELLS: usize = SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE;
+
+// DecodeError says why bytes don't hold a valid encoding.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+  // The header announces something else (or there is no header).
+  WrongKind { expected: u8, found: Option<u8> },
+  // The bytes were written for grids with boxes of another size.
+  WrongBoxSize { expected: u8, found: u8 },
+  WrongLength { expected: usize, found: usize },
+  // A cell code that isn't empty or a value.
+  InvalidCell { cell_idx: usize, code: u8 },
+  // The unused bits of the last byte must be zero, so that every grid
+  // has exactly one encoding.
+  NonZeroPadding,
+  // The clue mask marks a cell that is empty in the solution.
+  MissingSolutionValue(SudokuPosition),
+}
+
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      DecodeError::WrongKind {
+        expected,
+        found: None,
+      } => {
+        write!(
+          f,
+          "expected a {:?} header, found none",
+          expected as char
+        )
+      }
+      DecodeError::WrongKind {
+        expected,
+        found: Some(found),
+      } => write!(
+        f,
+        "expected a {:?} header, found {:?}",
+        expected as char, found as char
+      ),
+      DecodeError::WrongBoxSize { expected, found } => write!(
+        f,
+        "expected boxes with side {}, found {}",
+        expected, found
+      ),
+      DecodeError::WrongLength { expected, found } => {
+        write!(f, "expected {} bytes, found {}", expected, found)
+      }
+      DecodeError::InvalidCell { cell_idx, code } => {
+        write!(f, "cell {} has invalid code {}", cell_idx, code)
+      }
+      DecodeError::NonZeroPadding => {
+        write!(f, "padding bits are not zero")
+      }
+      DecodeError::MissingSolutionValue(position) => {
+        let (row_idx, col_idx) = position.as_usize_pair();
+        write!(
+          f,
+          "clue at row {}, column {} is empty in the solution",
+          row_idx + 1,
+          col_idx + 1
+        )
+      }
+    }
+  }
+}
+
+impl Error for DecodeError {}
+
+// A grid takes a header, then one code per cell, left-to-right,
+// top-to-bottom: 0 for an empty cell, the value otherwise. Codes use as
+// few bits as the largest value needs (5 for 16x16 grids) and are
+// packed most significant bit first.
+//
+// Decoding only accepts what `to_bytes` writes, so
+// `SudokuGrid::from_bytes(&grid.to_bytes()) == Ok(grid)` and every
+// grid has exactly one encoding.
+impl SudokuGrid {
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut writer = BitWriter::new(GRID_KIND);
+    for position in SudokuPosition::all() {
+      let code = match self.cell(position) {
+        SudokuCell::Empty => 0,
+        SudokuCell::Filled(value) => value.as_u8_value(),
+      };
+      writer.write(code, bits_per_cell());
+    }
+
+    writer.finish()
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<SudokuGrid, DecodeError> {
+    let mut reader = BitReader::new(
+      bytes,
+      GRID_KIND,
+      NUM_CELLS * bits_per_cell() as usize,
+    )?;
+
+    let mut grid = SudokuGrid::default();
+    for (cell_idx, position) in
+      SudokuPosition::all().into_iter().enumerate()
+    {
+      let code = reader.read(bits_per_cell());
+      if code == 0 {
+        continue;
+      }
+      if code as usize > SUDOKU_DIM_USIZE {
+        return Err(DecodeError::InvalidCell { cell_idx, code });
+      }
+      grid.place(SudokuChoice::new(position, SudokuValue::new(code)));
+    }
+
+    Ok(grid)
+  }
+
+  // `to_clue_bytes` encodes a puzzle relative to its solution: only the
+  // positions of the clues are stored, one bit per cell, since the
+  // values can be read off the solution. Returns None if a clue
+  // disagrees with the solution.
+  pub fn to_clue_bytes(
+    &self,
+    solution: &SudokuGrid,
+  ) -> Option<Vec<u8>> {
+    let mut mask = SudokuClueMask::default();
+    for choice in self.filled_choices() {
+      if solution.cell(choice.position)
+        != SudokuCell::Filled(choice.value)
+      {
+        return None;
+      }
+      mask.set(choice.position, true);
+    }
+
+    Some(mask.to_bytes())
+  }
+
+  // `from_clue_bytes` rebuilds a puzzle from `to_clue_bytes` and the
+  // same solution.
+  pub fn from_clue_bytes(
+    bytes: &[u8],
+    solution: &SudokuGrid,
+  ) -> Result<SudokuGrid, DecodeError> {
+    let mask = SudokuClueMask::from_bytes(bytes)?;
+
+    let mut puzzle = SudokuGrid::default();
+    for position in mask.positions() {
+      match solution.cell(position) {
+        SudokuCell::Empty => {
+          return Err(DecodeError::MissingSolutionValue(position))
+        }
+        SudokuCell::Filled(value) => {
+          puzzle.place(SudokuChoice::new(position, value))
+        }
+      }
+    }
+
+    Ok(puzzle)
+  }
+}
+
+// A clue mask takes a header, then one bit per cell, left-to-right,
+// top-to-bottom: 34 bytes for a 16x16 grid.
+impl SudokuClueMask {
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut writer = BitWriter::new(CLUE_MASK_KIND);
+    for position in SudokuPosition::all() {
+      writer.write(self.contains(position) as u8, 1);
+    }
+
+    writer.finish()
+  }
+
+  pub fn from_bytes(
+    bytes: &[u8],
+  ) -> Result<SudokuClueMask, DecodeError> {
+    let mut reader = BitReader::new(bytes, CLUE_MASK_KIND, NUM_CELLS)?;
+
+    let mut mask = SudokuClueMask::default();
+    for position in SudokuPosition::all() {
+      mask.set(position, reader.read(1) == 1);
+    }
+
+    Ok(mask)
+  }
+}
+
+// `bits_per_cell` is the number of bits needed to write every value,
+// with 0 left for empty cells.
+fn bits_per_cell() -> u32 {
+  usize::BITS - SUDOKU_DIM_USIZE.leading_zeros()
+}
+
+struct BitWriter {
+  bytes: Vec<u8>,
+  num_bits: usize,
+}
+
+impl BitWriter {
+  fn new(kind: u8) -> BitWriter {
+    BitWriter {
+      bytes: vec![kind, SUDOKU_DIM_SQRT_U8],
+      num_bits: 0,
+    }
+  }
+
+  // `write` appends the low `num_bits` bits of `code`.
+  fn write(&mut self, code: u8, num_bits: u32) {
+    for bit_idx in (0..num_bits).rev() {
+      if self.num_bits.is_multiple_of(8) {
+        self.bytes.push(0);
+      }
+      let bit = (code >> bit_idx) & 1;
+      *self.bytes.last_mut().unwrap() |= bit << (7 - self.num_bits % 8);
+      self.num_bits += 1;
+    }
+  }
+
+  fn finish(self) -> Vec<u8> {
+    self.bytes
+  }
+}
+
+struct BitReader<'a> {
+  body: &'a [u8],
+  num_bits: usize,
+}
+
+impl<'a> BitReader<'a> {
+  // `new` checks the header, and that the body holds exactly
+  // `num_body_bits` bits followed by zero padding.
+  fn new(
+    bytes: &'a [u8],
+    kind: u8,
+    num_body_bits: usize,
+  ) -> Result<BitReader<'a>, DecodeError> {
+    if bytes.first() != Some(&kind) {
+      return Err(DecodeError::WrongKind {
+        expected: kind,
+        found: bytes.first().cloned(),
+      });
+    }
+    match bytes.get(1) {
+      Some(&box_size) if box_size != SUDOKU_DIM_SQRT_U8 => {
+        return Err(DecodeError::WrongBoxSize {
+          expected: SUDOKU_DIM_SQRT_U8,
+          found: box_size,
+        })
+      }
+      _ => {}
+    }
+    let expected_len = HEADER_LEN + num_body_bits.div_ceil(8);
+    if bytes.len() != expected_len {
+      return Err(DecodeError::WrongLength {
+        expected: expected_len,
+        found: bytes.len(),
+      });
+    }
+
+    let num_padding_bits = (8 - num_body_bits % 8) % 8;
+    let padding_mask = ((1u16 << num_padding_bits) - 1) as u8;
+    if bytes[expected_len - 1] & padding_mask != 0 {
+      return Err(DecodeError::NonZeroPadding);
+    }
+
+    Ok(BitReader {
+      body: &bytes[HEADER_LEN..],
+      num_bits: 0,
+    })
+  }
+
+  fn read(&mut self, num_bits: u32) -> u8 {
+    let mut code = 0;
+    for _ in 0..num_bits {
+      let byte = self.body[self.num_bits / 8];
+      let bit = (byte >> (7 - self.num_bits % 8)) & 1;
+      code = (code << 1) | bit;
+      self.num_bits += 1;
+    }
+
+    code
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use test_util::{puzzle_of, seeded_solution};
+
+  #[test]
+  fn grids_decode_to_themselves() {
+    let solution = seeded_solution(1);
+    for grid in [SudokuGrid::default(), puzzle_of(&solution), solution]
+    {
+      let bytes = grid.to_bytes();
+      assert_eq!(bytes.len(), HEADER_LEN + NUM_CELLS * 5 / 8);
+      assert_eq!(&bytes[..HEADER_LEN], &[b'G', 4]);
+      assert_eq!(SudokuGrid::from_bytes(&bytes), Ok(grid));
+    }
+  }
+
+  #[test]
+  fn rejects_bytes_of_another_kind_or_size() {
+    let bytes = seeded_solution(1).to_bytes();
+    assert_eq!(
+      SudokuGrid::from_bytes(&[]),
+      Err(DecodeError::WrongKind {
+        expected: b'G',
+        found: None,
+      })
+    );
+
+    let mut wrong_box_size = bytes.clone();
+    wrong_box_size[1] = 3;
+    assert_eq!(
+      SudokuGrid::from_bytes(&wrong_box_size),
+      Err(DecodeError::WrongBoxSize {
+        expected: 4,
+        found: 3,
+      })
+    );
+
+    assert_eq!(
+      SudokuGrid::from_bytes(&bytes[..bytes.len() - 1]),
+      Err(DecodeError::WrongLength {
+        expected: bytes.len(),
+        found: bytes.len() - 1,
+      })
+    );
+
+    // The first cell gets code 31, past the largest value.
+    let mut invalid_cell = bytes.clone();
+    invalid_cell[HEADER_LEN] |= 0b1111_1000;
+    assert_eq!(
+      SudokuGrid::from_bytes(&invalid_cell),
+      Err(DecodeError::InvalidCell {
+        cell_idx: 0,
+        code: 31,
+      })
+    );
+  }
+
+  #[test]
+  fn clue_bytes_decode_against_the_solution() {
+    let solution = seeded_solution(1);
+    let puzzle = puzzle_of(&solution);
+    let bytes = puzzle.to_clue_bytes(&solution).unwrap();
+    assert_eq!(bytes.len(), HEADER_LEN + NUM_CELLS / 8);
+    assert_eq!(bytes[0], b'M');
+    assert_eq!(
+      SudokuGrid::from_clue_bytes(&bytes, &solution),
+      Ok(puzzle)
+    );
+
+    // The clue mask alone doesn't decode as a grid.
+    assert_eq!(
+      SudokuGrid::from_bytes(&bytes),
+      Err(DecodeError::WrongKind {
+        expected: b'G',
+        found: Some(b'M'),
+      })
+    );
+  }
+
+  #[test]
+  fn clues_must_agree_with_the_solution() {
+    let solution = seeded_solution(1);
+    let puzzle = puzzle_of(&solution);
+    assert!(puzzle.to_clue_bytes(&seeded_solution(2)).is_none());
+
+    // A solution missing one of the clues can't rebuild the puzzle.
+    let bytes = puzzle.to_clue_bytes(&solution).unwrap();
+    let mut partial_solution = solution.clone();
+    partial_solution.clear(SudokuPosition::new(1, 0));
+    assert_eq!(
+      SudokuGrid::from_clue_bytes(&bytes, &partial_solution),
+      Err(DecodeError::MissingSolutionValue(SudokuPosition::new(1, 0)))
+    );
+  }
+}
@@ -0,0 +1,313 @@
+use super::SymbolSet;
+use core::{
+  SudokuCell, SudokuChoice, SudokuClueMask, SudokuGrid, SudokuPosition,
+  SUDOKU_DIM_SQRT_USIZE, SUDOKU_DIM_U8,
+};
+use std::fmt;
+
+// GridLayout is the overall shape of the formatted grid.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GridLayout {
+  // All the cells on one line, left-to-right, top-to-bottom, with
+  // nothing in between. Best paired with a SymbolSet.
+  SingleLine,
+  // One line per row, cells separated by a space.
+  Rows,
+  // Like `Rows`, with borders around and between the boxes.
+  Boxed,
+}
+
+// GivenStyle is how the givens stand out from the other filled cells.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GivenStyle {
+  // Givens are written `[5]`, other cells ` 5 `.
+  Bracketed,
+  // Givens are written in bold, using ANSI terminal escapes.
+  Bold,
+}
+
+// GridFormatter turns a SudokuGrid into text. Every cell is padded to
+// the same width, so the columns always line up.
+//
+//   let text = GridFormatter::new()
+//     .with_layout(GridLayout::Rows)
+//     .with_givens(&given_choices, GivenStyle::Bracketed)
+//     .format(&grid);
+#[derive(Clone)]
+pub struct GridFormatter {
+  layout: GridLayout,
+  // When there are no symbols we write values as decimal numbers.
+  symbols: Option<SymbolSet>,
+  // Set by `with_empty_symbol`, in either order with `with_symbols`.
+  empty_symbol: Option<char>,
+  givens: Option<(SudokuClueMask, GivenStyle)>,
+}
+
+impl Default for GridFormatter {
+  fn default() -> GridFormatter {
+    GridFormatter {
+      layout: GridLayout::Boxed,
+      symbols: None,
+      empty_symbol: None,
+      givens: None,
+    }
+  }
+}
+
+impl GridFormatter {
+  // `new` makes the formatter used by `Display`: a boxed grid of
+  // decimal values.
+  pub fn new() -> GridFormatter {
+    GridFormatter::default()
+  }
+
+  pub fn with_layout(mut self, layout: GridLayout) -> GridFormatter {
+    self.layout = layout;
+    self
+  }
+
+  // `with_symbols` writes each value as a single character from
+  // `symbols`, and empty cells with its empty symbol, unless
+  // `with_empty_symbol` picks another.
+  pub fn with_symbols(mut self, symbols: SymbolSet) -> GridFormatter {
+    self.symbols = Some(symbols);
+    self
+  }
+
+  pub fn with_empty_symbol(
+    mut self,
+    empty_symbol: char,
+  ) -> GridFormatter {
+    self.empty_symbol = Some(empty_symbol);
+    self
+  }
+
+  // `with_givens` makes the positions of `given_choices` stand out
+  // from the cells filled in later.
+  pub fn with_givens(
+    mut self,
+    given_choices: &[SudokuChoice],
+    given_style: GivenStyle,
+  ) -> GridFormatter {
+    let given_positions: Vec<SudokuPosition> =
+      given_choices.iter().map(|choice| choice.position).collect();
+    let mask = SudokuClueMask::from_positions(&given_positions);
+
+    self.givens = Some((mask, given_style));
+    self
+  }
+
+  pub fn format(&self, grid: &SudokuGrid) -> String {
+    match self.layout {
+      GridLayout::SingleLine => {
+        let cells: String = SudokuPosition::all()
+          .into_iter()
+          .map(|position| self.cell_text(grid, position))
+          .collect();
+        format!("{}\n", cells)
+      }
+      GridLayout::Rows => (0..SUDOKU_DIM_U8)
+        .map(|row_idx| {
+          format!("{}\n", self.row_text(grid, row_idx, " "))
+        })
+        .collect(),
+      GridLayout::Boxed => self.boxed_text(grid),
+    }
+  }
+
+  fn boxed_text(&self, grid: &SudokuGrid) -> String {
+    // A stack's cells are separated (and surrounded) by single spaces.
+    let cell_width = self.cell_width();
+    let stack_width = SUDOKU_DIM_SQRT_USIZE * (cell_width + 1) + 1;
+    let stack_border = "-".repeat(stack_width);
+    let border = format!(
+      "+{}+\n",
+      vec![stack_border; SUDOKU_DIM_SQRT_USIZE].join("+")
+    );
+
+    let mut text = String::new();
+    for row_idx in 0..SUDOKU_DIM_U8 {
+      if (row_idx as usize).is_multiple_of(SUDOKU_DIM_SQRT_USIZE) {
+        text.push_str(&border);
+      }
+      text.push_str(&format!(
+        "| {} |\n",
+        self.row_text(grid, row_idx, " | ")
+      ));
+    }
+    text.push_str(&border);
+
+    text
+  }
+
+  // `row_text` joins the cells of a row with spaces, putting
+  // `box_separator` between the boxes instead.
+  fn row_text(
+    &self,
+    grid: &SudokuGrid,
+    row_idx: u8,
+    box_separator: &str,
+  ) -> String {
+    let mut text = String::new();
+    for col_idx in 0..SUDOKU_DIM_U8 {
+      if col_idx > 0 {
+        if (col_idx as usize).is_multiple_of(SUDOKU_DIM_SQRT_USIZE) {
+          text.push_str(box_separator);
+        } else {
+          text.push(' ');
+        }
+      }
+      let position = SudokuPosition::new(row_idx, col_idx);
+      text.push_str(&self.cell_text(grid, position));
+    }
+
+    text
+  }
+
+  fn cell_text(
+    &self,
+    grid: &SudokuGrid,
+    position: SudokuPosition,
+  ) -> String {
+    let symbol = match grid.cell(position) {
+      SudokuCell::Empty => self.empty_symbol().to_string(),
+      SudokuCell::Filled(value) => match self.symbols {
+        None => value.as_u8_value().to_string(),
+        Some(ref symbols) => symbols.symbol(value).to_string(),
+      },
+    };
+    let symbol =
+      format!("{:>width$}", symbol, width = self.symbol_width());
+
+    match self.givens {
+      None => symbol,
+      Some((ref mask, GivenStyle::Bracketed)) => {
+        if mask.contains(position) {
+          format!("[{}]", symbol)
+        } else {
+          format!(" {} ", symbol)
+        }
+      }
+      Some((ref mask, GivenStyle::Bold)) => {
+        if mask.contains(position) {
+          format!("\x1b[1m{}\x1b[0m", symbol)
+        } else {
+          symbol
+        }
+      }
+    }
+  }
+
+  // `empty_symbol` is the one set with `with_empty_symbol`, or else
+  // the symbol set's, or else '.'.
+  fn empty_symbol(&self) -> char {
+    match (self.empty_symbol, self.symbols.as_ref()) {
+      (Some(empty_symbol), _) => empty_symbol,
+      (None, Some(symbols)) => symbols.empty_symbol(),
+      (None, None) => '.',
+    }
+  }
+
+  // `symbol_width` is the width of the widest value (or the empty
+  // symbol).
+  fn symbol_width(&self) -> usize {
+    match self.symbols {
+      Some(_) => 1,
+      None => SUDOKU_DIM_U8.to_string().len(),
+    }
+  }
+
+  // `cell_width` is how many columns a cell takes on screen.
+  fn cell_width(&self) -> usize {
+    match self.givens {
+      Some((_, GivenStyle::Bracketed)) => self.symbol_width() + 2,
+      Some((_, GivenStyle::Bold)) | None => self.symbol_width(),
+    }
+  }
+}
+
+// `Display` draws the grid with box borders. Use a GridFormatter for
+// other layouts. It lives here rather than in `core`, which knows
+// nothing of formats.
+impl fmt::Display for SudokuGrid {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", GridFormatter::new().format(self))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::SudokuValue;
+
+  // `grid` has a 1 in the top left corner, and a 16 just right of it.
+  fn grid() -> SudokuGrid {
+    SudokuGrid::from_choices(&[given(), filled()])
+  }
+
+  fn given() -> SudokuChoice {
+    SudokuChoice::new(SudokuPosition::new(0, 0), SudokuValue::new(1))
+  }
+
+  fn filled() -> SudokuChoice {
+    SudokuChoice::new(SudokuPosition::new(0, 1), SudokuValue::new(16))
+  }
+
+  #[test]
+  fn single_lines_hold_one_symbol_per_cell() {
+    let text = GridFormatter::new()
+      .with_layout(GridLayout::SingleLine)
+      .with_symbols(SymbolSet::one_based())
+      .format(&grid());
+    assert_eq!(text.len(), 16 * 16 + 1);
+    assert!(text.starts_with("1G..."));
+    assert!(text.ends_with(".\n"));
+  }
+
+  #[test]
+  fn empty_symbols_outlast_symbol_sets() {
+    let text = GridFormatter::new()
+      .with_layout(GridLayout::SingleLine)
+      .with_empty_symbol('_')
+      .with_symbols(SymbolSet::one_based())
+      .format(&grid());
+    assert!(text.starts_with("1G__"));
+  }
+
+  #[test]
+  fn rows_pad_every_cell_to_the_same_width() {
+    let text = GridFormatter::new()
+      .with_layout(GridLayout::Rows)
+      .with_empty_symbol('_')
+      .format(&grid());
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 16);
+    assert!(lines[0].starts_with(" 1 16  _  _  _"));
+    assert!(lines.iter().all(|line| line.len() == lines[0].len()));
+  }
+
+  #[test]
+  fn boxes_have_borders_that_line_up() {
+    let text = grid().to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 16 + 5);
+    assert!(lines[0].starts_with("+-------------+"));
+    assert!(lines[1].starts_with("|  1 16  .  . |  . "));
+    assert!(lines.iter().all(|line| line.len() == lines[0].len()));
+  }
+
+  #[test]
+  fn givens_stand_out() {
+    let text = GridFormatter::new()
+      .with_layout(GridLayout::Rows)
+      .with_givens(&[given()], GivenStyle::Bracketed)
+      .format(&grid());
+    assert!(text.starts_with("[ 1]  16    . "));
+
+    let text = GridFormatter::new()
+      .with_layout(GridLayout::Rows)
+      .with_givens(&[given()], GivenStyle::Bold)
+      .format(&grid());
+    assert!(text.starts_with("\x1b[1m 1\x1b[0m 16  ."));
+  }
+}
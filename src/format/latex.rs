@@ -0,0 +1,361 @@
+use super::SymbolSet;
+use core::{
+  SudokuCell, SudokuGrid, SudokuPosition, SUDOKU_DIM_SQRT_USIZE,
+  SUDOKU_DIM_USIZE,
+};
+use solver::{SolverResult, SudokuSolver};
+
+// The usable area of an A4 page with the margins we set, in cm.
+const TEXT_WIDTH_CM: f64 = 17.0;
+const TEXT_HEIGHT_CM: f64 = 24.0;
+// Room left for the caption above each grid, in cm.
+const CAPTION_HEIGHT_CM: f64 = 1.5;
+// The solutions appendix packs this many grids on a page.
+const SOLUTIONS_PER_PAGE: usize = 6;
+
+// BookPuzzle is one entry of a PuzzleBook.
+#[derive(Clone, Debug)]
+pub struct BookPuzzle {
+  pub puzzle: SudokuGrid,
+  // The full grid for the answer key. If missing, we solve the puzzle
+  // when writing the book, and print no answer unless the solution is
+  // unique.
+  pub solution: Option<SudokuGrid>,
+  // A label like "Hard", printed next to the puzzle number.
+  pub difficulty: Option<String>,
+}
+
+impl BookPuzzle {
+  pub fn new(puzzle: SudokuGrid) -> BookPuzzle {
+    BookPuzzle {
+      puzzle,
+      solution: None,
+      difficulty: None,
+    }
+  }
+
+  pub fn with_solution(mut self, solution: SudokuGrid) -> BookPuzzle {
+    self.solution = Some(solution);
+    self
+  }
+
+  pub fn with_difficulty(mut self, difficulty: &str) -> BookPuzzle {
+    self.difficulty = Some(difficulty.to_string());
+    self
+  }
+
+  // `solved_grid` is the solution, solving the puzzle if need be. The
+  // solver makes at most `max_nodes` choices, and must show that the
+  // solution is unique; otherwise there is no answer to print.
+  fn solved_grid(&self, max_nodes: u64) -> Option<SudokuGrid> {
+    if let Some(ref solution) = self.solution {
+      return Some(solution.clone());
+    }
+
+    let given_choices = self.puzzle.filled_choices();
+    let mut solver =
+      SudokuSolver::new(&given_choices)?.with_node_limit(max_nodes);
+    let solution = solver.next_solution().into_solution()?;
+    match solver.next_solution() {
+      SolverResult::NoMoreSolutions => {}
+      SolverResult::Solved(_) | SolverResult::NodeLimitReached => {
+        return None
+      }
+    }
+    let mut grid = self.puzzle.clone();
+    for choice in solution {
+      grid.place(choice);
+    }
+
+    Some(grid)
+  }
+}
+
+// PuzzleBook writes a LaTeX document with the puzzles laid out several
+// to a page, numbered, followed by an appendix with the solutions. The
+// grids are drawn with TikZ, so any LaTeX distribution can turn the
+// output into a PDF:
+//
+//   let mut book = PuzzleBook::new().with_puzzles_per_page(4);
+//   book.push(BookPuzzle::new(grid).with_difficulty("Hard"));
+//   fs::write("book.tex", book.to_latex())?;
+#[derive(Clone, Debug, Default)]
+pub struct PuzzleBook {
+  title: Option<String>,
+  puzzles_per_page: usize,
+  // When there are no symbols we write values as decimal numbers.
+  symbols: Option<SymbolSet>,
+  // `max_nodes` bounds the solver, for puzzles that come without a
+  // solution.
+  max_nodes: u64,
+  puzzles: Vec<BookPuzzle>,
+}
+
+impl PuzzleBook {
+  pub fn new() -> PuzzleBook {
+    PuzzleBook {
+      puzzles_per_page: 2,
+      max_nodes: 1_000_000,
+      ..PuzzleBook::default()
+    }
+  }
+
+  pub fn with_title(mut self, title: &str) -> PuzzleBook {
+    self.title = Some(title.to_string());
+    self
+  }
+
+  // `with_puzzles_per_page` lays out one puzzle per page, or several in
+  // two columns.
+  pub fn with_puzzles_per_page(
+    mut self,
+    puzzles_per_page: usize,
+  ) -> PuzzleBook {
+    self.puzzles_per_page = puzzles_per_page.max(1);
+    self
+  }
+
+  pub fn with_symbols(mut self, symbols: SymbolSet) -> PuzzleBook {
+    self.symbols = Some(symbols);
+    self
+  }
+
+  // `with_node_limit` sets how many choices the solver may make for
+  // each puzzle that has no solution attached.
+  pub fn with_node_limit(mut self, max_nodes: u64) -> PuzzleBook {
+    self.max_nodes = max_nodes;
+    self
+  }
+
+  pub fn push(&mut self, puzzle: BookPuzzle) {
+    self.puzzles.push(puzzle);
+  }
+
+  pub fn to_latex(&self) -> String {
+    let mut latex = String::new();
+    latex.push_str(
+      "\\documentclass[a4paper]{article}\n\
+       \\usepackage[margin=2cm]{geometry}\n\
+       \\usepackage{tikz}\n\
+       \\pagestyle{empty}\n\
+       \\begin{document}\n",
+    );
+
+    if let Some(ref title) = self.title {
+      latex.push_str(&format!(
+        "\\begin{{center}}\\LARGE\\bfseries {}\\end{{center}}\n",
+        escape(title)
+      ));
+    }
+
+    let puzzle_pages =
+      self.puzzles.iter().enumerate().map(|(idx, entry)| {
+        let caption = match entry.difficulty {
+          None => format!("Puzzle {}", idx + 1),
+          Some(ref difficulty) => {
+            format!("Puzzle {} \\quad {}", idx + 1, escape(difficulty))
+          }
+        };
+        (caption, Some((entry.puzzle.clone(), None)))
+      });
+    self.push_pages(&mut latex, puzzle_pages, self.puzzles_per_page);
+
+    if !self.puzzles.is_empty() {
+      latex.push_str("\\newpage\n\\section*{Solutions}\n");
+      let solution_pages =
+        self.puzzles.iter().enumerate().map(|(idx, entry)| {
+          let caption = format!("Solution {}", idx + 1);
+          let grids = entry
+            .solved_grid(self.max_nodes)
+            .map(|solution| (solution, Some(entry.puzzle.clone())));
+          (caption, grids)
+        });
+      self.push_pages(&mut latex, solution_pages, SOLUTIONS_PER_PAGE);
+    }
+
+    latex.push_str("\\end{document}\n");
+    latex
+  }
+
+  // `push_pages` lays out captioned grids `per_page` to a page. Each
+  // grid comes with the puzzle it solves, if it is a solution, so that
+  // the givens can be told apart; a missing grid means the puzzle had
+  // no unique solution that the solver could find.
+  fn push_pages<I>(
+    &self,
+    latex: &mut String,
+    entries: I,
+    per_page: usize,
+  ) where
+    I: Iterator<
+      Item = (String, Option<(SudokuGrid, Option<SudokuGrid>)>),
+    >,
+  {
+    let num_cols = if per_page == 1 { 1 } else { 2 };
+    let num_rows = per_page.div_ceil(num_cols);
+    let grid_size_cm = (TEXT_WIDTH_CM / num_cols as f64 - 1.0)
+      .min(TEXT_HEIGHT_CM / num_rows as f64 - CAPTION_HEIGHT_CM);
+    let minipage_width = 1.0 / num_cols as f64 - 0.02;
+
+    for (idx, (caption, grids)) in entries.enumerate() {
+      if idx > 0 {
+        if idx % per_page == 0 {
+          latex.push_str("\\newpage\n");
+        } else if idx % num_cols == 0 {
+          latex.push_str("\\par\\vfill\n");
+        } else {
+          latex.push_str("\\hfill\n");
+        }
+      }
+
+      latex.push_str(&format!(
+        "\\begin{{minipage}}{{{:.2}\\linewidth}}\n\\centering\n\
+         \\textbf{{{}}}\\par\\smallskip\n",
+        minipage_width, caption
+      ));
+      match grids {
+        None => latex.push_str("\\emph{No unique solution.}\n"),
+        Some((grid, puzzle)) => {
+          self.push_grid(latex, &grid, puzzle.as_ref(), grid_size_cm)
+        }
+      }
+      latex.push_str("\\end{minipage}\n");
+    }
+  }
+
+  // `push_grid` draws the grid as a TikZ picture `size_cm` wide. When
+  // drawing a solution, the givens of `puzzle` are set in bold.
+  fn push_grid(
+    &self,
+    latex: &mut String,
+    grid: &SudokuGrid,
+    puzzle: Option<&SudokuGrid>,
+    size_cm: f64,
+  ) {
+    let dim = SUDOKU_DIM_USIZE;
+    let cell_cm = size_cm / dim as f64;
+    latex.push_str(&format!(
+      "\\begin{{tikzpicture}}[x={cell:.3}cm,y={cell:.3}cm]\n\
+       \\draw[step=1,thin] (0,0) grid ({dim},{dim});\n\
+       \\draw[step={box_dim},very thick] (0,0) grid ({dim},{dim});\n",
+      cell = cell_cm,
+      dim = dim,
+      box_dim = SUDOKU_DIM_SQRT_USIZE
+    ));
+
+    // Size the digits to the cells; LaTeX wants points.
+    let font_pt = cell_cm * 28.45 * 0.55;
+    latex.push_str(&format!(
+      "\\begin{{scope}}[every node/.style={{font=\\fontsize{{{:.1}}}\
+       {{{:.1}}}\\selectfont}}]\n",
+      font_pt,
+      font_pt * 1.2
+    ));
+    for position in SudokuPosition::all() {
+      let value = match grid.cell(position) {
+        SudokuCell::Empty => continue,
+        SudokuCell::Filled(value) => value,
+      };
+      let symbol = match self.symbols {
+        None => value.as_u8_value().to_string(),
+        Some(ref symbols) => escape(&symbols.symbol(value).to_string()),
+      };
+      let is_given = puzzle.is_none_or(|puzzle| {
+        puzzle.cell(position) != SudokuCell::Empty
+      });
+
+      let (row_idx, col_idx) = position.as_usize_pair();
+      latex.push_str(&format!(
+        "\\node at ({}.5,{}.5) {{{}}};\n",
+        col_idx,
+        dim - 1 - row_idx,
+        if is_given {
+          format!("\\textbf{{{}}}", symbol)
+        } else {
+          symbol
+        }
+      ));
+    }
+    latex.push_str("\\end{scope}\n\\end{tikzpicture}\n");
+  }
+}
+
+// `escape` protects the characters LaTeX treats specially.
+fn escape(text: &str) -> String {
+  let mut escaped = String::new();
+  for symbol in text.chars() {
+    match symbol {
+      '\\' => escaped.push_str("\\textbackslash{}"),
+      '~' => escaped.push_str("\\textasciitilde{}"),
+      '^' => escaped.push_str("\\textasciicircum{}"),
+      '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+        escaped.push('\\');
+        escaped.push(symbol);
+      }
+      _ => escaped.push(symbol),
+    }
+  }
+
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use test_util::seeded_solution;
+
+  #[test]
+  fn solves_puzzles_with_a_unique_solution() {
+    // Each of the missing cells is the only one left in its row.
+    let choices: Vec<_> = seeded_solution(1)
+      .filled_choices()
+      .into_iter()
+      .filter(|choice| {
+        let (row_idx, col_idx) = choice.position.as_usize_pair();
+        row_idx != col_idx
+      })
+      .collect();
+    let puzzle = BookPuzzle::new(SudokuGrid::from_choices(&choices));
+    assert!(puzzle.solved_grid(1_000).is_some());
+  }
+
+  #[test]
+  fn refuses_puzzles_with_several_solutions() {
+    // The rows of an empty band can come in any order.
+    let choices: Vec<_> = seeded_solution(1)
+      .filled_choices()
+      .into_iter()
+      .filter(|choice| {
+        let (row_idx, _) = choice.position.as_usize_pair();
+        row_idx >= SUDOKU_DIM_SQRT_USIZE
+      })
+      .collect();
+    let puzzle = BookPuzzle::new(SudokuGrid::from_choices(&choices));
+    assert!(puzzle.solved_grid(1_000_000).is_none());
+
+    let mut book = PuzzleBook::new();
+    book.push(puzzle);
+    assert!(book.to_latex().contains("No unique solution."));
+  }
+
+  #[test]
+  fn gives_up_at_the_node_limit() {
+    let mut book = PuzzleBook::new().with_node_limit(10);
+    book.push(BookPuzzle::new(SudokuGrid::default()));
+    assert!(book.to_latex().contains("No unique solution."));
+  }
+
+  #[test]
+  fn escapes_captions() {
+    let mut book = PuzzleBook::new().with_title("50% off & more");
+    book.push(
+      BookPuzzle::new(SudokuGrid::default())
+        .with_solution(SudokuGrid::default())
+        .with_difficulty("#1_hard"),
+    );
+    let latex = book.to_latex();
+    assert!(latex.contains("50\\% off \\& more"));
+    assert!(latex.contains("Puzzle 1 \\quad \\#1\\_hard"));
+    assert!(!latex.contains("No unique solution."));
+  }
+}
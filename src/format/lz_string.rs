@@ -0,0 +1,305 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+// This is the base64 flavor of the lz-string JavaScript library
+// (`compressToBase64` and `decompressFromBase64`), which SudokuPad and
+// f-puzzles use to fit a puzzle in a link. Like the JavaScript, it
+// works on UTF-16 code units.
+
+const BASE64_SYMBOLS: &[u8] =
+  b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// The codes of the compressed stream below the dictionary entries.
+const CHAR_8_BIT_CODE: u32 = 0;
+const CHAR_16_BIT_CODE: u32 = 1;
+const END_OF_STREAM_CODE: u32 = 2;
+
+pub(super) fn compress_to_base64(text: &str) -> String {
+  let units: Vec<u16> = text.encode_utf16().collect();
+  let mut writer = BitWriter::default();
+
+  // Entries are sequences of code units. Single units only get a code
+  // after being written out literally once.
+  let mut dictionary: HashMap<Vec<u16>, u32> = HashMap::new();
+  let mut units_to_create: HashSet<u16> = HashSet::new();
+  let mut dict_size = 3;
+  let mut num_bits = 2;
+  let mut enlarge_in = 2;
+  let mut w: Vec<u16> = vec![];
+
+  for &unit in &units {
+    if let Entry::Vacant(entry) = dictionary.entry(vec![unit]) {
+      entry.insert(dict_size);
+      dict_size += 1;
+      units_to_create.insert(unit);
+    }
+
+    let mut wc = w.clone();
+    wc.push(unit);
+    if dictionary.contains_key(&wc) {
+      w = wc;
+      continue;
+    }
+
+    write_phrase(
+      &mut writer,
+      &w,
+      &dictionary,
+      &mut units_to_create,
+      &mut num_bits,
+      &mut enlarge_in,
+    );
+    dictionary.insert(wc, dict_size);
+    dict_size += 1;
+    w = vec![unit];
+  }
+
+  if !w.is_empty() {
+    write_phrase(
+      &mut writer,
+      &w,
+      &dictionary,
+      &mut units_to_create,
+      &mut num_bits,
+      &mut enlarge_in,
+    );
+  }
+  writer.write(END_OF_STREAM_CODE, num_bits);
+
+  let mut base64: String = writer
+    .finish()
+    .into_iter()
+    .map(|sextet| BASE64_SYMBOLS[sextet as usize] as char)
+    .collect();
+  while !base64.len().is_multiple_of(4) {
+    base64.push('=');
+  }
+
+  base64
+}
+
+// `decompress_from_base64` returns None if the input is not something
+// `compress_to_base64` could have written. Besides the base64 alphabet,
+// it accepts the `-` of lz-string's URI-safe flavor, and a space for a
+// `+` that was URL-decoded.
+pub(super) fn decompress_from_base64(base64: &str) -> Option<String> {
+  let sextets: Vec<u8> = base64
+    .chars()
+    .filter(|&symbol| symbol != '=' && symbol != '$')
+    .map(|symbol| match symbol {
+      ' ' => Some(62),
+      '-' => Some(63),
+      _ => BASE64_SYMBOLS
+        .iter()
+        .position(|&s| s as char == symbol)
+        .map(|idx| idx as u8),
+    })
+    .collect::<Option<_>>()?;
+  if sextets.is_empty() {
+    return None;
+  }
+
+  let mut reader = BitReader {
+    sextets: &sextets,
+    num_bits: 0,
+  };
+  let mut dictionary: Vec<Vec<u16>> = vec![vec![]; 3];
+  let mut num_bits = 3;
+  let mut enlarge_in = 4;
+
+  let first_unit = match reader.read(2)? {
+    CHAR_8_BIT_CODE => reader.read(8)? as u16,
+    CHAR_16_BIT_CODE => reader.read(16)? as u16,
+    _ => return None,
+  };
+  let mut w = vec![first_unit];
+  dictionary.push(w.clone());
+  let mut units = w.clone();
+
+  loop {
+    let mut code = reader.read(num_bits)?;
+    match code {
+      CHAR_8_BIT_CODE | CHAR_16_BIT_CODE => {
+        let unit_bits = if code == CHAR_8_BIT_CODE { 8 } else { 16 };
+        dictionary.push(vec![reader.read(unit_bits)? as u16]);
+        code = dictionary.len() as u32 - 1;
+        enlarge_in -= 1;
+      }
+      END_OF_STREAM_CODE => break,
+      _ => {}
+    }
+    if enlarge_in == 0 {
+      enlarge_in = 1 << num_bits;
+      num_bits += 1;
+    }
+
+    let entry = match dictionary.get(code as usize) {
+      Some(entry) => entry.clone(),
+      // The one code that can be used before it is defined is the next
+      // one, which stands for `w` plus its own first unit.
+      None if code as usize == dictionary.len() => {
+        let mut entry = w.clone();
+        entry.push(w[0]);
+        entry
+      }
+      None => return None,
+    };
+    units.extend_from_slice(&entry);
+
+    let mut new_entry = w;
+    new_entry.push(entry[0]);
+    dictionary.push(new_entry);
+    enlarge_in -= 1;
+    if enlarge_in == 0 {
+      enlarge_in = 1 << num_bits;
+      num_bits += 1;
+    }
+    w = entry;
+  }
+
+  String::from_utf16(&units).ok()
+}
+
+// `write_phrase` writes out `w`: literally the first time a single unit
+// is seen, and by its dictionary code afterwards.
+fn write_phrase(
+  writer: &mut BitWriter,
+  w: &[u16],
+  dictionary: &HashMap<Vec<u16>, u32>,
+  units_to_create: &mut HashSet<u16>,
+  num_bits: &mut u32,
+  enlarge_in: &mut u32,
+) {
+  let is_new_unit = w.len() == 1 && units_to_create.remove(&w[0]);
+  if is_new_unit {
+    if w[0] < 256 {
+      writer.write(CHAR_8_BIT_CODE, *num_bits);
+      writer.write(u32::from(w[0]), 8);
+    } else {
+      writer.write(CHAR_16_BIT_CODE, *num_bits);
+      writer.write(u32::from(w[0]), 16);
+    }
+    count_down(num_bits, enlarge_in);
+  } else {
+    writer.write(dictionary[w], *num_bits);
+  }
+  count_down(num_bits, enlarge_in);
+}
+
+// `count_down` widens the codes once the dictionary outgrows them.
+fn count_down(num_bits: &mut u32, enlarge_in: &mut u32) {
+  *enlarge_in -= 1;
+  if *enlarge_in == 0 {
+    *enlarge_in = 1 << *num_bits;
+    *num_bits += 1;
+  }
+}
+
+// lz-string packs codes least significant bit first into 6-bit
+// sextets, filling each sextet from its most significant bit.
+#[derive(Default)]
+struct BitWriter {
+  sextets: Vec<u8>,
+  value: u8,
+  position: u32,
+}
+
+impl BitWriter {
+  fn write(&mut self, mut code: u32, num_bits: u32) {
+    for _ in 0..num_bits {
+      self.push_bit((code & 1) as u8);
+      code >>= 1;
+    }
+  }
+
+  fn push_bit(&mut self, bit: u8) {
+    self.value = (self.value << 1) | bit;
+    if self.position == 5 {
+      self.sextets.push(self.value);
+      self.value = 0;
+      self.position = 0;
+    } else {
+      self.position += 1;
+    }
+  }
+
+  // `finish` pads with zero bits up to the end of a sextet. Like the
+  // JavaScript, it adds a whole sextet of padding if the bits already
+  // fill the last one.
+  fn finish(mut self) -> Vec<u8> {
+    let num_sextets = self.sextets.len();
+    while self.sextets.len() == num_sextets {
+      self.push_bit(0);
+    }
+
+    self.sextets
+  }
+}
+
+struct BitReader<'a> {
+  sextets: &'a [u8],
+  num_bits: usize,
+}
+
+impl<'a> BitReader<'a> {
+  // `read` returns None when the input runs out.
+  fn read(&mut self, num_bits: u32) -> Option<u32> {
+    let mut code = 0;
+    for bit_idx in 0..num_bits {
+      let sextet = *self.sextets.get(self.num_bits / 6)?;
+      let bit = (sextet >> (5 - self.num_bits % 6)) & 1;
+      code |= u32::from(bit) << bit_idx;
+      self.num_bits += 1;
+    }
+
+    Some(code)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn decompresses_what_it_compresses() {
+    let long_text = "{\"size\":16,\"grid\":[{},{},{}]}".repeat(50);
+    let texts = [
+      "a",
+      "abababababab",
+      "Hello, world!",
+      // Code units past 8 bits, and a surrogate pair.
+      "16\u{d7}16 \u{2192} \u{1f9e9}",
+      &long_text,
+    ];
+    for &text in &texts {
+      let base64 = compress_to_base64(text);
+      assert!(
+        base64.len().is_multiple_of(4),
+        "{:?} is not padded",
+        base64
+      );
+      assert_eq!(
+        decompress_from_base64(&base64).as_deref(),
+        Some(text)
+      );
+    }
+  }
+
+  #[test]
+  fn repeats_compress_well() {
+    let text = "R1C1".repeat(1000);
+    assert!(compress_to_base64(&text).len() < text.len() / 10);
+  }
+
+  #[test]
+  fn rejects_corrupt_input() {
+    assert_eq!(decompress_from_base64(""), None);
+    assert_eq!(decompress_from_base64("not base64!"), None);
+    // A stream cut short never reaches its end code.
+    let base64 = compress_to_base64(&"abc".repeat(100));
+    assert_eq!(
+      decompress_from_base64(&base64[..base64.len() / 2]),
+      None
+    );
+  }
+}
@@ -0,0 +1,27 @@
+mod binary;
+mod grid_formatter;
+#[cfg(feature = "puzzle_links")]
+mod lz_string;
+mod latex;
+mod parse_error;
+mod pencil_marks;
+#[cfg(feature = "puzzle_links")]
+mod puzzle_link;
+mod puzzle_lines;
+mod sdk;
+mod simple_sudoku;
+mod svg;
+mod symbol_set;
+
+pub use self::binary::DecodeError;
+pub use self::grid_formatter::{GivenStyle, GridFormatter, GridLayout};
+pub use self::latex::{BookPuzzle, PuzzleBook};
+pub use self::parse_error::{ParseError, ParseErrorKind};
+pub use self::pencil_marks::PencilMarkGrid;
+#[cfg(feature = "puzzle_links")]
+pub use self::puzzle_link::{PuzzleLink, PuzzleLinkError, VariantConstraint};
+pub use self::puzzle_lines::{PuzzleLine, PuzzleLineReader};
+pub use self::sdk::{SdkMetadata, SdkPuzzle};
+pub use self::simple_sudoku::SimpleSudokuFormat;
+pub use self::svg::SvgRenderer;
+pub use self::symbol_set::SymbolSet;
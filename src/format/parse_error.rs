@@ -0,0 +1,83 @@
+use std::error::Error;
+use std::fmt;
+
+// ParseError says where (and why) a puzzle file is malformed. Lines and
+// columns count from 1, like in a text editor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+  pub line: usize,
+  pub column: usize,
+  pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseErrorKind {
+  // A character that doesn't stand for any cell value.
+  UnexpectedSymbol(char),
+  // A grid row with the wrong number of cells.
+  WrongRowLength { expected: usize, found: usize },
+  // A grid with the wrong number of rows.
+  WrongRowCount { expected: usize, found: usize },
+  // A puzzle written on one line with the wrong number of cells.
+  WrongCellCount { expected: usize, found: usize },
+  // A `#` metadata line without a tag letter.
+  MissingMetadataTag,
+  UnknownSection(String),
+  DuplicateSection(String),
+  // The file never gives the puzzle itself.
+  MissingPuzzle,
+  // The underlying reader failed, with this message.
+  Read(String),
+}
+
+impl ParseError {
+  pub(crate) fn new(
+    line: usize,
+    column: usize,
+    kind: ParseErrorKind,
+  ) -> ParseError {
+    ParseError { line, column, kind }
+  }
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}, column {}: ", self.line, self.column)?;
+
+    match self.kind {
+      ParseErrorKind::UnexpectedSymbol(symbol) => {
+        write!(f, "unexpected symbol {:?}", symbol)
+      }
+      ParseErrorKind::WrongRowLength { expected, found } => write!(
+        f,
+        "expected {} cells in the row, found {}",
+        expected, found
+      ),
+      ParseErrorKind::WrongRowCount { expected, found } => write!(
+        f,
+        "expected {} rows in the grid, found {}",
+        expected, found
+      ),
+      ParseErrorKind::WrongCellCount { expected, found } => write!(
+        f,
+        "expected {} cells in the puzzle, found {}",
+        expected, found
+      ),
+      ParseErrorKind::MissingMetadataTag => {
+        write!(f, "metadata line is missing its tag")
+      }
+      ParseErrorKind::UnknownSection(ref name) => {
+        write!(f, "unknown section [{}]", name)
+      }
+      ParseErrorKind::DuplicateSection(ref name) => {
+        write!(f, "section [{}] appears more than once", name)
+      }
+      ParseErrorKind::MissingPuzzle => write!(f, "no puzzle was given"),
+      ParseErrorKind::Read(ref message) => {
+        write!(f, "could not read: {}", message)
+      }
+    }
+  }
+}
+
+impl Error for ParseError {}
@@ -0,0 +1,443 @@
+use super::simple_sudoku::{is_border, tokenize};
+use super::{ParseError, ParseErrorKind, SymbolSet};
+use checker::SudokuGridConflictChecker;
+use core::{
+  SudokuBox, SudokuCell, SudokuChoice, SudokuGrid, SudokuPosition,
+  SudokuValue, SUDOKU_DIM_SQRT_USIZE, SUDOKU_DIM_USIZE,
+};
+use std::fmt;
+use std::str::FromStr;
+
+// PencilMarkGrid is a position in the middle of solving, as players
+// share it on forums (Simple Sudoku and HoDoKu both write these). Every
+// cell lists its remaining candidates; a cell with a single candidate
+// is solved:
+//
+//   *-----------------------------------------------*
+//   | 1     2     3     | 4     5     6     | ...
+//   | 456   78    9     | 123   37    8     | ...
+//   ...
+//   |-------------------+-------------------+-- ...
+//
+// Parsing is forgiving about the borders and the spacing, but every row
+// must list one token per cell.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PencilMarkGrid {
+  // `candidates[row_idx * SUDOKU_DIM_USIZE + col_idx]` are the values
+  // that may still go in that cell, in increasing order.
+  candidates: Vec<Vec<SudokuValue>>,
+}
+
+impl PencilMarkGrid {
+  // `from_grid` pencils in every value that doesn't clash with a filled
+  // cell in the same row/column/box.
+  pub fn from_grid(grid: &SudokuGrid) -> PencilMarkGrid {
+    let candidates = SudokuPosition::all()
+      .into_iter()
+      .map(|position| match grid.cell(position) {
+        SudokuCell::Filled(value) => vec![value],
+        SudokuCell::Empty => {
+          let peer_values: Vec<SudokuValue> = peers(position)
+            .into_iter()
+            .filter_map(|peer| match grid.cell(peer) {
+              SudokuCell::Empty => None,
+              SudokuCell::Filled(value) => Some(value),
+            })
+            .collect();
+          SudokuValue::all()
+            .into_iter()
+            .filter(|value| !peer_values.contains(value))
+            .collect()
+        }
+      })
+      .collect();
+
+    PencilMarkGrid { candidates }
+  }
+
+  // `from_checker` takes the candidates of the empty cells from the
+  // checker, so that eliminations recorded there are kept.
+  pub fn from_checker(
+    grid: &SudokuGrid,
+    grid_checker: &SudokuGridConflictChecker,
+  ) -> PencilMarkGrid {
+    let candidates = SudokuPosition::all()
+      .into_iter()
+      .map(|position| match grid.cell(position) {
+        SudokuCell::Filled(value) => vec![value],
+        SudokuCell::Empty => grid_checker.candidates(position),
+      })
+      .collect();
+
+    PencilMarkGrid { candidates }
+  }
+
+  pub fn candidates(&self, position: SudokuPosition) -> &[SudokuValue] {
+    &self.candidates[cell_idx(position)]
+  }
+
+  // `grid` has the solved cells (those with a single candidate) filled
+  // in.
+  pub fn grid(&self) -> SudokuGrid {
+    let mut grid = SudokuGrid::default();
+    for position in SudokuPosition::all() {
+      if let [value] = *self.candidates(position) {
+        grid.place(SudokuChoice::new(position, value));
+      }
+    }
+
+    grid
+  }
+
+  // `to_checker` seeds a SudokuGridConflictChecker with this position:
+  // solved cells become choices, and every missing pencil mark becomes
+  // an eliminated candidate. Returns None if the position contradicts
+  // itself (say, two solved cells in a row hold the same value).
+  //
+  // Since a solved cell is a choice, its value is no longer a
+  // candidate anywhere else in its row/column/box, even if this grid
+  // still listed it there.
+  pub fn to_checker(&self) -> Option<SudokuGridConflictChecker> {
+    let mut grid_checker = SudokuGridConflictChecker::new();
+
+    let solved_choices = self.grid().filled_choices();
+    for &choice in &solved_choices {
+      if !grid_checker.add_choice(choice).did_add() {
+        return None;
+      }
+    }
+
+    for position in SudokuPosition::all() {
+      let candidates = self.candidates(position);
+      if candidates.len() == 1 {
+        continue;
+      }
+
+      for value in SudokuValue::all() {
+        if candidates.contains(&value) {
+          continue;
+        }
+        if !grid_checker
+          .eliminate_candidate(position, value)
+          .did_eliminate()
+        {
+          return None;
+        }
+      }
+    }
+
+    Some(grid_checker)
+  }
+
+  // `parse_with_symbols` reads a grid whose candidates are written with
+  // the given symbols. `str::parse` uses the default SymbolSet.
+  pub fn parse_with_symbols(
+    text: &str,
+    symbols: &SymbolSet,
+  ) -> Result<PencilMarkGrid, ParseError> {
+    let mut candidates = vec![];
+    let mut num_rows = 0;
+    let mut num_lines = 0;
+
+    for (line_idx, line) in text.lines().enumerate() {
+      let line_number = line_idx + 1;
+      num_lines = line_number;
+
+      let tokens = tokenize(line);
+      if tokens.is_empty() || tokens.iter().all(|t| is_border(&t.1)) {
+        continue;
+      }
+
+      if num_rows == SUDOKU_DIM_USIZE {
+        return Err(ParseError::new(
+          line_number,
+          tokens[0].0,
+          ParseErrorKind::WrongRowCount {
+            expected: SUDOKU_DIM_USIZE,
+            found: SUDOKU_DIM_USIZE + 1,
+          },
+        ));
+      }
+      if tokens.len() != SUDOKU_DIM_USIZE {
+        let column = match tokens.get(SUDOKU_DIM_USIZE) {
+          Some(&(column, _)) => column,
+          None => line.chars().count() + 1,
+        };
+        return Err(ParseError::new(
+          line_number,
+          column,
+          ParseErrorKind::WrongRowLength {
+            expected: SUDOKU_DIM_USIZE,
+            found: tokens.len(),
+          },
+        ));
+      }
+
+      for (column, token) in tokens {
+        candidates.push(parse_candidates(
+          &token,
+          line_number,
+          column,
+          symbols,
+        )?);
+      }
+      num_rows += 1;
+    }
+
+    if num_rows < SUDOKU_DIM_USIZE {
+      return Err(ParseError::new(
+        num_lines + 1,
+        1,
+        ParseErrorKind::WrongRowCount {
+          expected: SUDOKU_DIM_USIZE,
+          found: num_rows,
+        },
+      ));
+    }
+
+    Ok(PencilMarkGrid { candidates })
+  }
+
+  // `format_with_symbols` writes the grid with the given symbols.
+  // `Display` uses the default SymbolSet.
+  pub fn format_with_symbols(&self, symbols: &SymbolSet) -> String {
+    let tokens: Vec<String> = self
+      .candidates
+      .iter()
+      .map(|candidates| {
+        candidates
+          .iter()
+          .map(|&value| symbols.symbol(value))
+          .collect()
+      })
+      .collect();
+
+    // Every column is as wide as its widest cell, so the columns line
+    // up.
+    let col_widths: Vec<usize> = (0..SUDOKU_DIM_USIZE)
+      .map(|col_idx| {
+        (0..SUDOKU_DIM_USIZE)
+          .map(|row_idx| {
+            tokens[row_idx * SUDOKU_DIM_USIZE + col_idx].chars().count()
+          })
+          .max()
+          .unwrap_or(1)
+      })
+      .collect();
+    let stack_borders: Vec<String> = col_widths
+      .chunks(SUDOKU_DIM_SQRT_USIZE)
+      .map(|widths| {
+        // Each cell is followed by a space, and the stack starts with
+        // one.
+        let stack_width: usize = widths.iter().map(|w| w + 1).sum();
+        "-".repeat(stack_width + 1)
+      })
+      .collect();
+    let outer_border = format!("*{}*", stack_borders.join("-"));
+    let inner_border = format!("|{}|", stack_borders.join("+"));
+
+    let mut text = format!("{}\n", outer_border);
+    for row_idx in 0..SUDOKU_DIM_USIZE {
+      if row_idx > 0 && row_idx % SUDOKU_DIM_SQRT_USIZE == 0 {
+        text.push_str(&format!("{}\n", inner_border));
+      }
+
+      text.push('|');
+      for col_idx in 0..SUDOKU_DIM_USIZE {
+        if col_idx % SUDOKU_DIM_SQRT_USIZE == 0 {
+          text.push(' ');
+        }
+        let token = &tokens[row_idx * SUDOKU_DIM_USIZE + col_idx];
+        text.push_str(&format!(
+          "{:width$} ",
+          token,
+          width = col_widths[col_idx]
+        ));
+        if (col_idx + 1) % SUDOKU_DIM_SQRT_USIZE == 0 {
+          text.push('|');
+        }
+      }
+      text.push('\n');
+    }
+    text.push_str(&format!("{}\n", outer_border));
+
+    text
+  }
+}
+
+fn cell_idx(position: SudokuPosition) -> usize {
+  let (row_idx, col_idx) = position.as_usize_pair();
+  row_idx * SUDOKU_DIM_USIZE + col_idx
+}
+
+// `peers` are the other positions in the same row/column/box.
+fn peers(position: SudokuPosition) -> Vec<SudokuPosition> {
+  let (row_idx, col_idx) = position.as_usize_pair();
+  SudokuPosition::all()
+    .into_iter()
+    .filter(|&other| {
+      let (other_row_idx, other_col_idx) = other.as_usize_pair();
+      other != position
+        && (other_row_idx == row_idx
+          || other_col_idx == col_idx
+          || box_of(other) == box_of(position))
+    })
+    .collect()
+}
+
+fn box_of(position: SudokuPosition) -> SudokuPosition {
+  SudokuBox::for_position(position).top_left_position()
+}
+
+impl FromStr for PencilMarkGrid {
+  type Err = ParseError;
+
+  fn from_str(text: &str) -> Result<PencilMarkGrid, ParseError> {
+    PencilMarkGrid::parse_with_symbols(text, &SymbolSet::default())
+  }
+}
+
+fn parse_candidates(
+  token: &str,
+  line_number: usize,
+  column: usize,
+  symbols: &SymbolSet,
+) -> Result<Vec<SudokuValue>, ParseError> {
+  let mut candidates = vec![];
+  for (offset, symbol) in token.chars().enumerate() {
+    match symbols.cell(symbol) {
+      Some(SudokuCell::Filled(value)) => {
+        if !candidates.contains(&value) {
+          candidates.push(value);
+        }
+      }
+      // An empty cell makes no sense here: every cell lists its
+      // candidates.
+      Some(SudokuCell::Empty) | None => {
+        return Err(ParseError::new(
+          line_number,
+          column + offset,
+          ParseErrorKind::UnexpectedSymbol(symbol),
+        ))
+      }
+    }
+  }
+  candidates.sort_by_key(|value| value.as_u8_value());
+
+  Ok(candidates)
+}
+
+impl fmt::Display for PencilMarkGrid {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.format_with_symbols(&SymbolSet::default()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn value(val: u8) -> SudokuValue {
+    SudokuValue::new(val)
+  }
+
+  // `grid_with_a_one` is an otherwise empty grid with a 1 in the top
+  // left corner.
+  fn grid_with_a_one() -> SudokuGrid {
+    SudokuGrid::from_choices(&[SudokuChoice::new(
+      SudokuPosition::new(0, 0),
+      value(1),
+    )])
+  }
+
+  #[test]
+  fn pencils_in_what_the_peers_allow() {
+    let pencil_marks = PencilMarkGrid::from_grid(&grid_with_a_one());
+    assert_eq!(
+      pencil_marks.candidates(SudokuPosition::new(0, 0)),
+      &[value(1)]
+    );
+    assert_eq!(
+      pencil_marks.candidates(SudokuPosition::new(3, 3)).len(),
+      SUDOKU_DIM_USIZE - 1
+    );
+    assert_eq!(
+      pencil_marks.candidates(SudokuPosition::new(4, 4)).len(),
+      SUDOKU_DIM_USIZE
+    );
+    assert_eq!(pencil_marks.grid(), grid_with_a_one());
+  }
+
+  #[test]
+  fn reads_back_what_it_writes() {
+    let mut pencil_marks =
+      PencilMarkGrid::from_grid(&grid_with_a_one());
+    pencil_marks.candidates[1] = vec![value(2), value(16)];
+    let text = pencil_marks.to_string();
+    assert_eq!(text.parse::<PencilMarkGrid>().unwrap(), pencil_marks);
+
+    let symbols = SymbolSet::zero_based();
+    let text = pencil_marks.format_with_symbols(&symbols);
+    assert_eq!(
+      PencilMarkGrid::parse_with_symbols(&text, &symbols).unwrap(),
+      pencil_marks
+    );
+  }
+
+  #[test]
+  fn reports_malformed_rows() {
+    let text =
+      PencilMarkGrid::from_grid(&grid_with_a_one()).to_string();
+
+    // The solved 1 on the first row becomes a blank.
+    let error =
+      text.replacen("| 1 ", "| . ", 1).parse::<PencilMarkGrid>();
+    assert_eq!(
+      error.unwrap_err().kind,
+      ParseErrorKind::UnexpectedSymbol('.')
+    );
+
+    let error = text
+      .replacen("| 1 ", "| ", 1)
+      .parse::<PencilMarkGrid>()
+      .unwrap_err();
+    assert_eq!(
+      error.kind,
+      ParseErrorKind::WrongRowLength {
+        expected: SUDOKU_DIM_USIZE,
+        found: SUDOKU_DIM_USIZE - 1,
+      }
+    );
+    assert_eq!(error.line, 2);
+  }
+
+  #[test]
+  fn to_checker_keeps_solved_cells_and_eliminations() {
+    let mut pencil_marks =
+      PencilMarkGrid::from_grid(&grid_with_a_one());
+    pencil_marks.candidates[cell_idx(SudokuPosition::new(5, 5))] =
+      vec![value(2), value(3)];
+
+    let grid_checker = pencil_marks.to_checker().unwrap();
+    assert!(!grid_checker
+      .candidates(SudokuPosition::new(0, 1))
+      .contains(&value(1)));
+    assert_eq!(
+      grid_checker.candidates(SudokuPosition::new(5, 5)),
+      vec![value(2), value(3)]
+    );
+    assert_eq!(
+      PencilMarkGrid::from_checker(&pencil_marks.grid(), &grid_checker),
+      pencil_marks
+    );
+  }
+
+  #[test]
+  fn to_checker_refuses_contradictions() {
+    // Two solved cells in the top row both hold a 1.
+    let mut pencil_marks =
+      PencilMarkGrid::from_grid(&grid_with_a_one());
+    pencil_marks.candidates[1] = vec![value(1)];
+    assert!(pencil_marks.to_checker().is_none());
+  }
+}
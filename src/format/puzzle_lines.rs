@@ -0,0 +1,266 @@
+use super::{ParseError, ParseErrorKind, SymbolSet};
+use core::{
+  SudokuCell, SudokuChoice, SudokuGrid, SudokuPosition,
+  SUDOKU_DIM_USIZE,
+};
+use std::io::BufRead;
+
+// Lines starting with one of these are comments.
+const COMMENT_PREFIXES: &[&str] = &["#", "//"];
+
+// PuzzleLine is one puzzle read by a PuzzleLineReader.
+#[derive(Clone, Debug)]
+pub struct PuzzleLine {
+  // The (1-based) line number the puzzle was on.
+  pub line: usize,
+  pub grid: SudokuGrid,
+  // Whatever came after the puzzle on its line, like a rating or a
+  // name, with the separating whitespace (or `,` or `;`) trimmed.
+  pub metadata: String,
+}
+
+// PuzzleLineReader reads puzzle collections with one puzzle per line,
+// the cells left-to-right, top-to-bottom:
+//
+//   # 17-clue puzzles
+//   ...1.2..........3.... (256 cells)  rating 4.5
+//
+// Blank lines and comments are skipped, and anything after the puzzle
+// is kept as metadata. The input is read a line at a time, so files of
+// any size can be streamed:
+//
+//   for puzzle in PuzzleLineReader::new(BufReader::new(file)) {
+//     let puzzle = puzzle?;
+//     ...
+//   }
+//
+// Malformed lines are reported as errors, and reading goes on with the
+// next line. In lenient mode they are skipped (and counted) instead.
+// A read error from the underlying reader ends the iteration.
+pub struct PuzzleLineReader<R> {
+  reader: R,
+  symbols: SymbolSet,
+  skips_malformed: bool,
+  line_buffer: Vec<u8>,
+  line_number: usize,
+  num_skipped: usize,
+  is_done: bool,
+}
+
+impl<R: BufRead> PuzzleLineReader<R> {
+  pub fn new(reader: R) -> PuzzleLineReader<R> {
+    PuzzleLineReader {
+      reader,
+      symbols: SymbolSet::default(),
+      skips_malformed: false,
+      line_buffer: vec![],
+      line_number: 0,
+      num_skipped: 0,
+      is_done: false,
+    }
+  }
+
+  pub fn with_symbols(
+    mut self,
+    symbols: SymbolSet,
+  ) -> PuzzleLineReader<R> {
+    self.symbols = symbols;
+    self
+  }
+
+  // `with_skip_malformed` makes the reader lenient: malformed lines are
+  // skipped rather than reported. Read errors are still reported.
+  pub fn with_skip_malformed(mut self) -> PuzzleLineReader<R> {
+    self.skips_malformed = true;
+    self
+  }
+
+  // `num_skipped` is how many malformed lines lenient mode has skipped
+  // so far.
+  pub fn num_skipped(&self) -> usize {
+    self.num_skipped
+  }
+
+  // `parse_line` returns None for blank and comment lines.
+  fn parse_line(
+    &self,
+    line: &str,
+  ) -> Option<Result<PuzzleLine, ParseError>> {
+    let trimmed_line = line.trim_start();
+    if trimmed_line.is_empty()
+      || COMMENT_PREFIXES
+        .iter()
+        .any(|prefix| trimmed_line.starts_with(prefix))
+    {
+      return None;
+    }
+
+    // The puzzle is the first run of symbols on the line.
+    let indent = line.chars().count() - trimmed_line.chars().count();
+    let puzzle_text: String = trimmed_line
+      .chars()
+      .take_while(|&symbol| !is_separator(symbol))
+      .collect();
+    let metadata = trimmed_line[puzzle_text.len()..]
+      .trim_matches(is_separator)
+      .to_string();
+
+    let num_cells = SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE;
+    let num_symbols = puzzle_text.chars().count();
+    if num_symbols != num_cells {
+      return Some(Err(ParseError::new(
+        self.line_number,
+        indent + num_symbols.min(num_cells) + 1,
+        ParseErrorKind::WrongCellCount {
+          expected: num_cells,
+          found: num_symbols,
+        },
+      )));
+    }
+
+    let mut grid = SudokuGrid::default();
+    for (cell_idx, symbol) in puzzle_text.chars().enumerate() {
+      match self.symbols.cell(symbol) {
+        None => {
+          return Some(Err(ParseError::new(
+            self.line_number,
+            indent + cell_idx + 1,
+            ParseErrorKind::UnexpectedSymbol(symbol),
+          )))
+        }
+        Some(SudokuCell::Empty) => {}
+        Some(SudokuCell::Filled(value)) => {
+          let position = SudokuPosition::new(
+            (cell_idx / SUDOKU_DIM_USIZE) as u8,
+            (cell_idx % SUDOKU_DIM_USIZE) as u8,
+          );
+          grid.place(SudokuChoice::new(position, value));
+        }
+      }
+    }
+
+    Some(Ok(PuzzleLine {
+      line: self.line_number,
+      grid,
+      metadata,
+    }))
+  }
+}
+
+impl<R: BufRead> Iterator for PuzzleLineReader<R> {
+  type Item = Result<PuzzleLine, ParseError>;
+
+  fn next(&mut self) -> Option<Result<PuzzleLine, ParseError>> {
+    while !self.is_done {
+      self.line_buffer.clear();
+      match self.reader.read_until(b'\n', &mut self.line_buffer) {
+        Ok(0) => self.is_done = true,
+        Ok(_) => {
+          self.line_number += 1;
+          // Bad UTF-8 shows up as an unexpected symbol, not a read
+          // error, so that lenient mode can skip it.
+          let line = String::from_utf8_lossy(&self.line_buffer);
+          match self.parse_line(&line) {
+            None => {}
+            Some(Err(_)) if self.skips_malformed => {
+              self.num_skipped += 1
+            }
+            Some(result) => return Some(result),
+          }
+        }
+        Err(error) => {
+          self.is_done = true;
+          return Some(Err(ParseError::new(
+            self.line_number + 1,
+            1,
+            ParseErrorKind::Read(error.to_string()),
+          )));
+        }
+      }
+    }
+
+    None
+  }
+}
+
+fn is_separator(symbol: char) -> bool {
+  symbol.is_whitespace() || symbol == ',' || symbol == ';'
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::SudokuValue;
+
+  // `puzzle_text` is an otherwise empty puzzle with a 1 in the top left
+  // corner.
+  fn puzzle_text() -> String {
+    format!("1{}", ".".repeat(SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE - 1))
+  }
+
+  fn read_all(text: &str) -> Vec<Result<PuzzleLine, ParseError>> {
+    PuzzleLineReader::new(text.as_bytes()).collect()
+  }
+
+  #[test]
+  fn reads_puzzles_and_their_metadata() {
+    let text = format!(
+      "# a comment\n\n{}\n  // another\n{}, rating 4.5\n",
+      puzzle_text(),
+      puzzle_text()
+    );
+    let puzzle_lines: Vec<_> =
+      read_all(&text).into_iter().map(Result::unwrap).collect();
+    assert_eq!(puzzle_lines.len(), 2);
+    assert_eq!(puzzle_lines[0].line, 3);
+    assert_eq!(puzzle_lines[0].metadata, "");
+    assert_eq!(puzzle_lines[1].line, 5);
+    assert_eq!(puzzle_lines[1].metadata, "rating 4.5");
+    assert_eq!(
+      puzzle_lines[1].grid.cell(SudokuPosition::new(0, 0)),
+      SudokuCell::Filled(SudokuValue::first())
+    );
+    assert_eq!(puzzle_lines[1].grid.filled_choices().len(), 1);
+  }
+
+  #[test]
+  fn reports_the_wrong_number_of_cells() {
+    let text = format!("  {} too short\n", &puzzle_text()[1..]);
+    let error = read_all(&text).pop().unwrap().unwrap_err();
+    let num_cells = SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE;
+    assert_eq!(
+      error.kind,
+      ParseErrorKind::WrongCellCount {
+        expected: num_cells,
+        found: num_cells - 1,
+      }
+    );
+    assert_eq!((error.line, error.column), (1, num_cells + 2));
+    assert_eq!(
+      error.to_string(),
+      "line 1, column 258: expected 256 cells in the puzzle, found 255"
+    );
+  }
+
+  #[test]
+  fn reports_unexpected_symbols() {
+    let text = format!("{}\n{}\n", puzzle_text(), puzzle_text())
+      .replacen("1", "?", 1);
+    let results = read_all(&text);
+    let error = results[0].clone().unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedSymbol('?'));
+    assert_eq!((error.line, error.column), (1, 1));
+    // Reading goes on with the next line.
+    assert!(results[1].is_ok());
+  }
+
+  #[test]
+  fn lenient_mode_counts_skipped_lines() {
+    let text = format!("bad\n{}\n", puzzle_text());
+    let mut reader =
+      PuzzleLineReader::new(text.as_bytes()).with_skip_malformed();
+    assert_eq!(reader.next().unwrap().unwrap().line, 2);
+    assert!(reader.next().is_none());
+    assert_eq!(reader.num_skipped(), 1);
+  }
+}
@@ -0,0 +1,578 @@
+use super::lz_string::{compress_to_base64, decompress_from_base64};
+use core::{
+  SudokuCell, SudokuChoice, SudokuGrid, SudokuPosition, SudokuValue,
+  SUDOKU_DIM_USIZE,
+};
+use serde_json::{self, Map, Value};
+use std::error::Error;
+use std::fmt;
+
+const FPUZZLES_URL_PREFIX: &str = "https://www.f-puzzles.com/?load=";
+const SUDOKUPAD_URL_PREFIX: &str = "https://sudokupad.app/fpuzzles";
+
+// The f-puzzles keys PuzzleLink reads into fields of its own. Every
+// other key is kept as a VariantConstraint::Other.
+const GRID_KEY: &str = "grid";
+const SIZE_KEY: &str = "size";
+const TITLE_KEY: &str = "title";
+const AUTHOR_KEY: &str = "author";
+const RULES_KEY: &str = "ruleset";
+const POSITIVE_DIAGONAL_KEY: &str = "diagonal+";
+const NEGATIVE_DIAGONAL_KEY: &str = "diagonal-";
+const KILLER_CAGE_KEY: &str = "killercage";
+const THERMOMETER_KEY: &str = "thermometer";
+const ODD_KEY: &str = "odd";
+const EVEN_KEY: &str = "even";
+
+// VariantConstraint is a rule on top of the classic ones. The solver
+// only knows the classic rules; these are carried along so that a
+// puzzle survives the trip through the crate.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VariantConstraint {
+  // Values don't repeat on the diagonal from bottom left to top right.
+  PositiveDiagonal,
+  // Values don't repeat on the diagonal from top left to bottom right.
+  NegativeDiagonal,
+  // Values don't repeat in the cage, and add up to `sum` if there is
+  // one.
+  KillerCage {
+    cells: Vec<SudokuPosition>,
+    sum: Option<u32>,
+  },
+  // Values increase from the bulb, the first cell, along the line.
+  Thermometer(Vec<SudokuPosition>),
+  Odd(SudokuPosition),
+  Even(SudokuPosition),
+  // Any other f-puzzles entry (arrows, sandwich sums, ...), with its
+  // JSON value as is.
+  Other {
+    key: String,
+    json: String,
+  },
+}
+
+// PuzzleLinkError says why a link couldn't be decoded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PuzzleLinkError {
+  // A link that holds no f-puzzles data, like the ones SudokuPad makes
+  // in its own formats.
+  UnsupportedLink,
+  // The payload isn't valid lz-string base64.
+  CorruptPayload,
+  InvalidJson(String),
+  WrongGridSize { expected: usize, found: usize },
+  // An entry that doesn't have the shape f-puzzles gives it.
+  InvalidEntry(String),
+}
+
+impl fmt::Display for PuzzleLinkError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      PuzzleLinkError::UnsupportedLink => {
+        write!(f, "link does not hold f-puzzles data")
+      }
+      PuzzleLinkError::CorruptPayload => {
+        write!(f, "link payload is not valid compressed data")
+      }
+      PuzzleLinkError::InvalidJson(ref message) => {
+        write!(f, "invalid puzzle JSON: {}", message)
+      }
+      PuzzleLinkError::WrongGridSize { expected, found } => write!(
+        f,
+        "expected a {}x{} grid, found {}x{}",
+        expected, expected, found, found
+      ),
+      PuzzleLinkError::InvalidEntry(ref key) => {
+        write!(f, "malformed {:?} entry", key)
+      }
+    }
+  }
+}
+
+impl Error for PuzzleLinkError {}
+
+// PuzzleLink is a puzzle as the SudokuPad and f-puzzles web tools share
+// it: lz-string compressed f-puzzles JSON, in the link itself.
+//
+//   let link = PuzzleLink::decode(url)?;
+//   let solution = SudokuSolver::solve(&link.grid.filled_choices());
+//   ...
+//   let url = PuzzleLink::new(puzzle).to_sudokupad_url();
+//
+// Only the givens are read from the grid; values and pencil marks a
+// player has entered are dropped, as are cell colors and irregular
+// regions.
+#[derive(Clone, Debug)]
+pub struct PuzzleLink {
+  pub grid: SudokuGrid,
+  pub title: Option<String>,
+  pub author: Option<String>,
+  pub rules: Option<String>,
+  pub constraints: Vec<VariantConstraint>,
+}
+
+impl PuzzleLink {
+  pub fn new(grid: SudokuGrid) -> PuzzleLink {
+    PuzzleLink {
+      grid,
+      title: None,
+      author: None,
+      rules: None,
+      constraints: vec![],
+    }
+  }
+
+  // `decode` takes an f-puzzles link (`...f-puzzles.com/?load=...`), a
+  // SudokuPad link to f-puzzles data (`sudokupad.app/fpuzzles...`), or
+  // just the compressed payload.
+  pub fn decode(link: &str) -> Result<PuzzleLink, PuzzleLinkError> {
+    let link = link.trim();
+    let load_idx = link.find("?load=").or_else(|| link.find("&load="));
+    let payload = if let Some(idx) = load_idx {
+      let payload = &link[idx + "?load=".len()..];
+      payload.split('&').next().unwrap()
+    } else if let Some(idx) = link.find("fpuzzles") {
+      &link[idx + "fpuzzles".len()..]
+    } else if link.contains("://") {
+      return Err(PuzzleLinkError::UnsupportedLink);
+    } else {
+      link
+    };
+
+    let json = decompress_from_base64(&percent_decode(payload))
+      .ok_or(PuzzleLinkError::CorruptPayload)?;
+    PuzzleLink::from_fpuzzles_json(&json)
+  }
+
+  pub fn from_fpuzzles_json(
+    json: &str,
+  ) -> Result<PuzzleLink, PuzzleLinkError> {
+    let value: Value = serde_json::from_str(json).map_err(|error| {
+      PuzzleLinkError::InvalidJson(error.to_string())
+    })?;
+    let object = match value {
+      Value::Object(object) => object,
+      _ => return Err(invalid_entry("")),
+    };
+
+    let size = object
+      .get(SIZE_KEY)
+      .and_then(Value::as_u64)
+      .ok_or_else(|| invalid_entry(SIZE_KEY))?;
+    if size as usize != SUDOKU_DIM_USIZE {
+      return Err(PuzzleLinkError::WrongGridSize {
+        expected: SUDOKU_DIM_USIZE,
+        found: size as usize,
+      });
+    }
+
+    let mut link = PuzzleLink::new(SudokuGrid::default());
+    for (key, value) in object {
+      match key.as_str() {
+        SIZE_KEY => {}
+        GRID_KEY => link.grid = parse_grid(&value)?,
+        TITLE_KEY => link.title = value.as_str().map(String::from),
+        AUTHOR_KEY => link.author = value.as_str().map(String::from),
+        RULES_KEY => link.rules = value.as_str().map(String::from),
+        POSITIVE_DIAGONAL_KEY | NEGATIVE_DIAGONAL_KEY => {
+          if value.as_bool() == Some(true) {
+            link.constraints.push(if key == POSITIVE_DIAGONAL_KEY {
+              VariantConstraint::PositiveDiagonal
+            } else {
+              VariantConstraint::NegativeDiagonal
+            });
+          }
+        }
+        KILLER_CAGE_KEY | THERMOMETER_KEY | ODD_KEY | EVEN_KEY => {
+          link.constraints.extend(parse_constraints(&key, &value)?)
+        }
+        _ => link.constraints.push(VariantConstraint::Other {
+          json: value.to_string(),
+          key,
+        }),
+      }
+    }
+
+    Ok(link)
+  }
+
+  pub fn to_fpuzzles_json(&self) -> String {
+    let mut object = Map::new();
+    object.insert(SIZE_KEY.to_string(), Value::from(SUDOKU_DIM_USIZE));
+    object.insert(GRID_KEY.to_string(), grid_json(&self.grid));
+    let metadata = [
+      (TITLE_KEY, &self.title),
+      (AUTHOR_KEY, &self.author),
+      (RULES_KEY, &self.rules),
+    ];
+    for &(key, text) in &metadata {
+      if let Some(ref text) = *text {
+        object.insert(key.to_string(), Value::from(text.as_str()));
+      }
+    }
+
+    for constraint in &self.constraints {
+      let (key, entry) = match *constraint {
+        VariantConstraint::PositiveDiagonal => {
+          object.insert(POSITIVE_DIAGONAL_KEY.to_string(), true.into());
+          continue;
+        }
+        VariantConstraint::NegativeDiagonal => {
+          object.insert(NEGATIVE_DIAGONAL_KEY.to_string(), true.into());
+          continue;
+        }
+        VariantConstraint::Other { ref key, ref json } => {
+          let value = serde_json::from_str(json)
+            .unwrap_or_else(|_| Value::from(json.as_str()));
+          object.insert(key.clone(), value);
+          continue;
+        }
+        VariantConstraint::KillerCage { ref cells, sum } => {
+          let mut entry = Map::new();
+          entry.insert("cells".to_string(), cells_json(cells));
+          if let Some(sum) = sum {
+            entry.insert("value".to_string(), sum.to_string().into());
+          }
+          (KILLER_CAGE_KEY, entry)
+        }
+        VariantConstraint::Thermometer(ref cells) => {
+          let mut entry = Map::new();
+          entry.insert(
+            "lines".to_string(),
+            Value::Array(vec![cells_json(cells)]),
+          );
+          (THERMOMETER_KEY, entry)
+        }
+        VariantConstraint::Odd(position) => {
+          (ODD_KEY, single_cell_entry(position))
+        }
+        VariantConstraint::Even(position) => {
+          (EVEN_KEY, single_cell_entry(position))
+        }
+      };
+
+      let entries = object
+        .entry(key.to_string())
+        .or_insert_with(|| Value::Array(vec![]));
+      if let Value::Array(ref mut entries) = *entries {
+        entries.push(Value::Object(entry));
+      }
+    }
+
+    Value::Object(object).to_string()
+  }
+
+  // `to_payload` is the compressed JSON both web tools put in links.
+  pub fn to_payload(&self) -> String {
+    compress_to_base64(&self.to_fpuzzles_json())
+  }
+
+  pub fn to_fpuzzles_url(&self) -> String {
+    // A `+` in a query string would be read back as a space.
+    format!(
+      "{}{}",
+      FPUZZLES_URL_PREFIX,
+      self.to_payload().replace('+', "%2B")
+    )
+  }
+
+  pub fn to_sudokupad_url(&self) -> String {
+    format!("{}{}", SUDOKUPAD_URL_PREFIX, self.to_payload())
+  }
+}
+
+// The grid is an array of rows, each an array of cell objects like
+// `{"value": 5, "given": true}`; empty cells are `{}`.
+fn parse_grid(value: &Value) -> Result<SudokuGrid, PuzzleLinkError> {
+  let rows = value.as_array().ok_or_else(|| invalid_entry(GRID_KEY))?;
+  if rows.len() != SUDOKU_DIM_USIZE {
+    return Err(PuzzleLinkError::WrongGridSize {
+      expected: SUDOKU_DIM_USIZE,
+      found: rows.len(),
+    });
+  }
+
+  let mut grid = SudokuGrid::default();
+  for (row_idx, row) in rows.iter().enumerate() {
+    let cells = match row.as_array() {
+      Some(cells) if cells.len() == SUDOKU_DIM_USIZE => cells,
+      _ => return Err(invalid_entry(GRID_KEY)),
+    };
+    for (col_idx, cell) in cells.iter().enumerate() {
+      let is_given = cell.get("given").and_then(Value::as_bool);
+      if is_given != Some(true) {
+        continue;
+      }
+      let value = cell
+        .get("value")
+        .and_then(Value::as_u64)
+        .filter(|value| (1..=SUDOKU_DIM_USIZE as u64).contains(value))
+        .ok_or_else(|| invalid_entry(GRID_KEY))?;
+      let position = SudokuPosition::new(row_idx as u8, col_idx as u8);
+      grid.place(SudokuChoice::new(
+        position,
+        SudokuValue::new(value as u8),
+      ));
+    }
+  }
+
+  Ok(grid)
+}
+
+fn parse_constraints(
+  key: &str,
+  value: &Value,
+) -> Result<Vec<VariantConstraint>, PuzzleLinkError> {
+  let entries = value.as_array().ok_or_else(|| invalid_entry(key))?;
+
+  let mut constraints = vec![];
+  for entry in entries {
+    match key {
+      KILLER_CAGE_KEY => {
+        let cells = parse_cells(key, entry.get("cells"))?;
+        // f-puzzles writes sums as strings, but numbers do no harm.
+        let sum = match entry.get("value") {
+          None | Some(Value::Null) => None,
+          Some(Value::String(sum)) if sum.is_empty() => None,
+          Some(Value::String(sum)) => {
+            Some(sum.parse().map_err(|_| invalid_entry(key))?)
+          }
+          Some(sum) => {
+            Some(sum.as_u64().ok_or_else(|| invalid_entry(key))? as u32)
+          }
+        };
+        constraints.push(VariantConstraint::KillerCage { cells, sum });
+      }
+      THERMOMETER_KEY => {
+        let lines = entry
+          .get("lines")
+          .and_then(Value::as_array)
+          .ok_or_else(|| invalid_entry(key))?;
+        for line in lines {
+          constraints.push(VariantConstraint::Thermometer(
+            parse_cells(key, Some(line))?,
+          ));
+        }
+      }
+      _ => {
+        let position = entry
+          .get("cell")
+          .and_then(Value::as_str)
+          .and_then(parse_cell)
+          .ok_or_else(|| invalid_entry(key))?;
+        constraints.push(if key == ODD_KEY {
+          VariantConstraint::Odd(position)
+        } else {
+          VariantConstraint::Even(position)
+        });
+      }
+    }
+  }
+
+  Ok(constraints)
+}
+
+fn parse_cells(
+  key: &str,
+  value: Option<&Value>,
+) -> Result<Vec<SudokuPosition>, PuzzleLinkError> {
+  value
+    .and_then(Value::as_array)
+    .and_then(|cells| {
+      cells
+        .iter()
+        .map(|cell| cell.as_str().and_then(parse_cell))
+        .collect()
+    })
+    .ok_or_else(|| invalid_entry(key))
+}
+
+// `parse_cell` reads f-puzzles cell names, like `R1C1` for the top left
+// cell.
+fn parse_cell(name: &str) -> Option<SudokuPosition> {
+  let name = name.to_ascii_uppercase();
+  let col_start = name.find('C')?;
+  if !name.starts_with('R') {
+    return None;
+  }
+  let row: usize = name[1..col_start].parse().ok()?;
+  let col: usize = name[col_start + 1..].parse().ok()?;
+  let dims = 1..=SUDOKU_DIM_USIZE;
+  if !dims.contains(&row) || !dims.contains(&col) {
+    return None;
+  }
+
+  Some(SudokuPosition::new(row as u8 - 1, col as u8 - 1))
+}
+
+fn grid_json(grid: &SudokuGrid) -> Value {
+  let rows = (0..SUDOKU_DIM_USIZE)
+    .map(|row_idx| {
+      let cells = (0..SUDOKU_DIM_USIZE)
+        .map(|col_idx| {
+          let position =
+            SudokuPosition::new(row_idx as u8, col_idx as u8);
+          let mut cell = Map::new();
+          if let SudokuCell::Filled(value) = grid.cell(position) {
+            cell
+              .insert("value".to_string(), value.as_u8_value().into());
+            cell.insert("given".to_string(), true.into());
+          }
+          Value::Object(cell)
+        })
+        .collect();
+      Value::Array(cells)
+    })
+    .collect();
+
+  Value::Array(rows)
+}
+
+fn single_cell_entry(position: SudokuPosition) -> Map<String, Value> {
+  let mut entry = Map::new();
+  entry.insert("cell".to_string(), cell_json(position));
+  entry
+}
+
+fn cells_json(positions: &[SudokuPosition]) -> Value {
+  Value::Array(positions.iter().map(|&p| cell_json(p)).collect())
+}
+
+fn cell_json(position: SudokuPosition) -> Value {
+  let (row_idx, col_idx) = position.as_usize_pair();
+  Value::from(format!("R{}C{}", row_idx + 1, col_idx + 1))
+}
+
+fn invalid_entry(key: &str) -> PuzzleLinkError {
+  PuzzleLinkError::InvalidEntry(key.to_string())
+}
+
+// `percent_decode` undoes the escapes a browser may have added to the
+// payload. Only `+`, `/` and `=` can need them.
+fn percent_decode(payload: &str) -> String {
+  payload
+    .replace("%2B", "+")
+    .replace("%2b", "+")
+    .replace("%2F", "/")
+    .replace("%2f", "/")
+    .replace("%3D", "=")
+    .replace("%3d", "=")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn position(row_idx: u8, col_idx: u8) -> SudokuPosition {
+    SudokuPosition::new(row_idx, col_idx)
+  }
+
+  fn puzzle_link() -> PuzzleLink {
+    let mut link = PuzzleLink::new(SudokuGrid::from_choices(&[
+      SudokuChoice::new(position(0, 0), SudokuValue::new(1)),
+      SudokuChoice::new(position(15, 15), SudokuValue::new(16)),
+    ]));
+    link.title = Some("Test & \"quotes\"".to_string());
+    link.constraints = vec![
+      VariantConstraint::PositiveDiagonal,
+      VariantConstraint::KillerCage {
+        cells: vec![position(1, 1), position(1, 2)],
+        sum: Some(17),
+      },
+      VariantConstraint::Thermometer(vec![
+        position(2, 0),
+        position(3, 0),
+        position(4, 0),
+      ]),
+      VariantConstraint::Odd(position(5, 5)),
+      VariantConstraint::Other {
+        key: "arrow".to_string(),
+        json: "[{\"lines\":[[\"R7C7\",\"R7C8\"]]}]".to_string(),
+      },
+    ];
+    link
+  }
+
+  fn assert_same_link(decoded: &PuzzleLink, link: &PuzzleLink) {
+    assert_eq!(decoded.grid, link.grid);
+    assert_eq!(decoded.title, link.title);
+    assert_eq!(decoded.author, link.author);
+    for constraint in &link.constraints {
+      assert!(
+        decoded.constraints.contains(constraint),
+        "lost {:?}",
+        constraint
+      );
+    }
+    assert_eq!(decoded.constraints.len(), link.constraints.len());
+  }
+
+  #[test]
+  fn links_decode_to_the_same_puzzle() {
+    let link = puzzle_link();
+    for url in &[
+      link.to_fpuzzles_url(),
+      link.to_sudokupad_url(),
+      link.to_payload(),
+    ] {
+      assert_same_link(&PuzzleLink::decode(url).unwrap(), &link);
+    }
+
+    let url = format!("{}&lang=en", link.to_fpuzzles_url());
+    assert_same_link(&PuzzleLink::decode(&url).unwrap(), &link);
+  }
+
+  #[test]
+  fn reads_only_the_givens() {
+    let given_grid = SudokuGrid::from_choices(&[SudokuChoice::new(
+      position(0, 0),
+      SudokuValue::new(3),
+    )]);
+    // A player's entry has a value, but isn't given.
+    let mut grid = grid_json(&given_grid);
+    grid[0][1]["value"] = Value::from(4);
+    let json = format!("{{\"size\":16,\"grid\":{}}}", grid);
+
+    let link = PuzzleLink::from_fpuzzles_json(&json).unwrap();
+    assert_eq!(link.grid, given_grid);
+  }
+
+  #[test]
+  fn reports_what_it_cannot_read() {
+    assert_eq!(
+      PuzzleLink::decode("https://sudokupad.app/scf/abc").unwrap_err(),
+      PuzzleLinkError::UnsupportedLink
+    );
+    assert_eq!(
+      PuzzleLink::decode("not a payload!").unwrap_err(),
+      PuzzleLinkError::CorruptPayload
+    );
+    assert_eq!(
+      PuzzleLink::from_fpuzzles_json("{\"size\":9}").unwrap_err(),
+      PuzzleLinkError::WrongGridSize {
+        expected: 16,
+        found: 9,
+      }
+    );
+    assert_eq!(
+      PuzzleLink::from_fpuzzles_json(
+        "{\"size\":16,\"odd\":[{\"cell\":\"R17C1\"}]}"
+      )
+      .unwrap_err(),
+      PuzzleLinkError::InvalidEntry("odd".to_string())
+    );
+    assert!(matches!(
+      PuzzleLink::from_fpuzzles_json("{"),
+      Err(PuzzleLinkError::InvalidJson(_))
+    ));
+  }
+
+  #[test]
+  fn reads_cell_names() {
+    assert_eq!(parse_cell("R1C1"), Some(position(0, 0)));
+    assert_eq!(parse_cell("r16c10"), Some(position(15, 9)));
+    assert_eq!(parse_cell("R0C1"), None);
+    assert_eq!(parse_cell("C1R1"), None);
+    assert_eq!(parse_cell("R1"), None);
+  }
+}
@@ -0,0 +1,505 @@
+use super::{ParseError, ParseErrorKind, SymbolSet};
+use core::{
+  SudokuCell, SudokuChoice, SudokuGrid, SudokuPosition,
+  SUDOKU_DIM_USIZE,
+};
+use std::fmt;
+use std::str::FromStr;
+
+// SdkPuzzle is a puzzle in the SadMan Software `.sdk` format. A file
+// looks like:
+//
+//   #AAuthor
+//   #DDescription
+//   #CA comment; there may be several.
+//   #LDifficulty
+//   [Puzzle]
+//   ..2.7..5.
+//   ...
+//   [State]
+//   ..2.7.35.
+//   ...
+//
+// The `[State]` section holds the player's progress, givens included.
+// Older files leave out the section headers and just give the puzzle
+// rows after the metadata, which we also accept.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SdkPuzzle {
+  pub metadata: SdkMetadata,
+  pub puzzle: SudokuGrid,
+  pub state: Option<SudokuGrid>,
+}
+
+// SdkMetadata holds the `#X...` lines of a `.sdk` file, keyed by their
+// tag letter.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SdkMetadata {
+  // #A
+  pub author: Option<String>,
+  // #D
+  pub description: Option<String>,
+  // #C; each line is one entry.
+  pub comments: Vec<String>,
+  // #B
+  pub date: Option<String>,
+  // #S
+  pub source: Option<String>,
+  // #L
+  pub difficulty: Option<String>,
+  // #U
+  pub url: Option<String>,
+  // Any other tags, kept so we can write them back out.
+  pub other: Vec<(char, String)>,
+}
+
+impl SdkPuzzle {
+  pub fn new(puzzle: SudokuGrid) -> SdkPuzzle {
+    SdkPuzzle {
+      metadata: SdkMetadata::default(),
+      puzzle,
+      state: None,
+    }
+  }
+
+  // `given_choices` are the clues of the puzzle, ready for the solver.
+  pub fn given_choices(&self) -> Vec<SudokuChoice> {
+    self.puzzle.filled_choices()
+  }
+
+  // `parse_with_symbols` reads a file whose grids are written with the
+  // given symbols. `str::parse` uses the default SymbolSet.
+  pub fn parse_with_symbols(
+    text: &str,
+    symbols: &SymbolSet,
+  ) -> Result<SdkPuzzle, ParseError> {
+    let mut metadata = SdkMetadata::default();
+    let mut puzzle = None;
+    let mut state = None;
+    let mut current_rows: Option<GridRows> = None;
+    let mut num_lines = 0;
+
+    for (line_idx, line) in text.lines().enumerate() {
+      let line_number = line_idx + 1;
+      num_lines = line_number;
+      let line = line.trim_end();
+
+      if line.is_empty() {
+        continue;
+      }
+
+      if line.starts_with('#') {
+        let mut chars = line.chars().skip(1);
+        let tag = chars.next().ok_or_else(|| {
+          ParseError::new(
+            line_number,
+            2,
+            ParseErrorKind::MissingMetadataTag,
+          )
+        })?;
+        metadata.set(tag, chars.collect::<String>().trim().to_string());
+        continue;
+      }
+
+      if line.starts_with('[') {
+        let section = parse_section_header(line, line_number)?;
+        let is_duplicate = match section {
+          SdkSection::Puzzle => puzzle.is_some(),
+          SdkSection::State => state.is_some(),
+        } || current_rows
+          .as_ref()
+          .is_some_and(|rows| rows.section == section);
+        if is_duplicate {
+          return Err(ParseError::new(
+            line_number,
+            2,
+            ParseErrorKind::DuplicateSection(
+              section.name().to_string(),
+            ),
+          ));
+        }
+
+        if let Some(rows) = current_rows.take() {
+          store_grid(rows, line_number, &mut puzzle, &mut state)?;
+        }
+        current_rows = Some(GridRows::new(section));
+        continue;
+      }
+
+      // A grid row before any section header belongs to the puzzle.
+      if current_rows.is_none() {
+        if puzzle.is_some() {
+          return Err(ParseError::new(
+            line_number,
+            1,
+            ParseErrorKind::DuplicateSection(
+              SdkSection::Puzzle.name().to_string(),
+            ),
+          ));
+        }
+        current_rows = Some(GridRows::new(SdkSection::Puzzle));
+      }
+      if let Some(ref mut rows) = current_rows {
+        rows.push_row(line, line_number, symbols)?;
+      }
+    }
+
+    let end_line_number = num_lines + 1;
+    if let Some(rows) = current_rows.take() {
+      store_grid(rows, end_line_number, &mut puzzle, &mut state)?;
+    }
+
+    let puzzle = puzzle.ok_or_else(|| {
+      ParseError::new(end_line_number, 1, ParseErrorKind::MissingPuzzle)
+    })?;
+
+    Ok(SdkPuzzle {
+      metadata,
+      puzzle,
+      state,
+    })
+  }
+
+  // `format_with_symbols` writes the file with the given symbols.
+  // `Display` uses the default SymbolSet.
+  pub fn format_with_symbols(&self, symbols: &SymbolSet) -> String {
+    let mut text = String::new();
+    for (tag, value) in self.metadata.tagged_lines() {
+      text.push_str(&format!("#{}{}\n", tag, value));
+    }
+
+    text.push_str(&format!("[{}]\n", SdkSection::Puzzle.name()));
+    push_grid(&mut text, &self.puzzle, symbols);
+
+    if let Some(ref state) = self.state {
+      text.push_str(&format!("[{}]\n", SdkSection::State.name()));
+      push_grid(&mut text, state, symbols);
+    }
+
+    text
+  }
+}
+
+impl SdkMetadata {
+  fn set(&mut self, tag: char, value: String) {
+    match tag {
+      'A' => self.author = Some(value),
+      'D' => self.description = Some(value),
+      'C' => self.comments.push(value),
+      'B' => self.date = Some(value),
+      'S' => self.source = Some(value),
+      'L' => self.difficulty = Some(value),
+      'U' => self.url = Some(value),
+      _ => self.other.push((tag, value)),
+    }
+  }
+
+  // `tagged_lines` lists the metadata in the order we write it out.
+  fn tagged_lines(&self) -> Vec<(char, &str)> {
+    let mut lines = vec![];
+    let single_values =
+      vec![('A', &self.author), ('D', &self.description)];
+    for (tag, value) in single_values {
+      if let Some(ref value) = *value {
+        lines.push((tag, value.as_str()));
+      }
+    }
+    for comment in &self.comments {
+      lines.push(('C', comment.as_str()));
+    }
+    let single_values = vec![
+      ('B', &self.date),
+      ('S', &self.source),
+      ('L', &self.difficulty),
+      ('U', &self.url),
+    ];
+    for (tag, value) in single_values {
+      if let Some(ref value) = *value {
+        lines.push((tag, value.as_str()));
+      }
+    }
+    for &(tag, ref value) in &self.other {
+      lines.push((tag, value.as_str()));
+    }
+
+    lines
+  }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum SdkSection {
+  Puzzle,
+  State,
+}
+
+impl SdkSection {
+  fn name(self) -> &'static str {
+    match self {
+      SdkSection::Puzzle => "Puzzle",
+      SdkSection::State => "State",
+    }
+  }
+}
+
+// GridRows collects the rows of one section as we read them.
+struct GridRows {
+  section: SdkSection,
+  rows: Vec<[SudokuCell; SUDOKU_DIM_USIZE]>,
+}
+
+impl GridRows {
+  fn new(section: SdkSection) -> GridRows {
+    GridRows {
+      section,
+      rows: vec![],
+    }
+  }
+
+  fn push_row(
+    &mut self,
+    line: &str,
+    line_number: usize,
+    symbols: &SymbolSet,
+  ) -> Result<(), ParseError> {
+    if self.rows.len() == SUDOKU_DIM_USIZE {
+      return Err(ParseError::new(
+        line_number,
+        1,
+        ParseErrorKind::WrongRowCount {
+          expected: SUDOKU_DIM_USIZE,
+          found: SUDOKU_DIM_USIZE + 1,
+        },
+      ));
+    }
+
+    let num_symbols = line.chars().count();
+    let mut row = [SudokuCell::Empty; SUDOKU_DIM_USIZE];
+    for (col_idx, symbol) in line.chars().enumerate() {
+      if col_idx == SUDOKU_DIM_USIZE {
+        return Err(ParseError::new(
+          line_number,
+          col_idx + 1,
+          ParseErrorKind::WrongRowLength {
+            expected: SUDOKU_DIM_USIZE,
+            found: num_symbols,
+          },
+        ));
+      }
+
+      row[col_idx] = symbols.cell(symbol).ok_or_else(|| {
+        ParseError::new(
+          line_number,
+          col_idx + 1,
+          ParseErrorKind::UnexpectedSymbol(symbol),
+        )
+      })?;
+    }
+    if num_symbols < SUDOKU_DIM_USIZE {
+      return Err(ParseError::new(
+        line_number,
+        num_symbols + 1,
+        ParseErrorKind::WrongRowLength {
+          expected: SUDOKU_DIM_USIZE,
+          found: num_symbols,
+        },
+      ));
+    }
+
+    self.rows.push(row);
+    Ok(())
+  }
+
+  // `finish` builds the grid once the section is over. `line_number` is
+  // the line that ended the section, where we report missing rows.
+  fn finish(
+    self,
+    line_number: usize,
+  ) -> Result<SudokuGrid, ParseError> {
+    if self.rows.len() < SUDOKU_DIM_USIZE {
+      return Err(ParseError::new(
+        line_number,
+        1,
+        ParseErrorKind::WrongRowCount {
+          expected: SUDOKU_DIM_USIZE,
+          found: self.rows.len(),
+        },
+      ));
+    }
+
+    let mut grid = SudokuGrid::default();
+    for position in SudokuPosition::all() {
+      let (row_idx, col_idx) = position.as_usize_pair();
+      if let SudokuCell::Filled(value) = self.rows[row_idx][col_idx] {
+        grid.place(SudokuChoice::new(position, value));
+      }
+    }
+
+    Ok(grid)
+  }
+}
+
+impl FromStr for SdkPuzzle {
+  type Err = ParseError;
+
+  fn from_str(text: &str) -> Result<SdkPuzzle, ParseError> {
+    SdkPuzzle::parse_with_symbols(text, &SymbolSet::default())
+  }
+}
+
+fn parse_section_header(
+  line: &str,
+  line_number: usize,
+) -> Result<SdkSection, ParseError> {
+  if !line.ends_with(']') {
+    let num_symbols = line.chars().count();
+    let last_symbol = line.chars().last().unwrap_or('[');
+    return Err(ParseError::new(
+      line_number,
+      num_symbols,
+      ParseErrorKind::UnexpectedSymbol(last_symbol),
+    ));
+  }
+
+  let name = line[1..line.len() - 1].trim();
+  if name.eq_ignore_ascii_case(SdkSection::Puzzle.name()) {
+    Ok(SdkSection::Puzzle)
+  } else if name.eq_ignore_ascii_case(SdkSection::State.name()) {
+    Ok(SdkSection::State)
+  } else {
+    Err(ParseError::new(
+      line_number,
+      2,
+      ParseErrorKind::UnknownSection(name.to_string()),
+    ))
+  }
+}
+
+fn store_grid(
+  rows: GridRows,
+  line_number: usize,
+  puzzle: &mut Option<SudokuGrid>,
+  state: &mut Option<SudokuGrid>,
+) -> Result<(), ParseError> {
+  let section = rows.section;
+  let grid = rows.finish(line_number)?;
+  match section {
+    SdkSection::Puzzle => *puzzle = Some(grid),
+    SdkSection::State => *state = Some(grid),
+  }
+
+  Ok(())
+}
+
+impl fmt::Display for SdkPuzzle {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.format_with_symbols(&SymbolSet::default()))
+  }
+}
+
+fn push_grid(
+  text: &mut String,
+  grid: &SudokuGrid,
+  symbols: &SymbolSet,
+) {
+  for row_idx in 0..SUDOKU_DIM_USIZE {
+    for col_idx in 0..SUDOKU_DIM_USIZE {
+      let position = SudokuPosition::new(row_idx as u8, col_idx as u8);
+      text.push(symbols.cell_symbol(grid.cell(position)));
+    }
+    text.push('\n');
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::SudokuValue;
+
+  // `grid_text` writes an otherwise empty grid with a 1 in the top left
+  // corner, one row per line.
+  fn grid_text() -> String {
+    let empty_row = ".".repeat(SUDOKU_DIM_USIZE);
+    let mut text = format!("1{}\n", &empty_row[1..]);
+    for _ in 1..SUDOKU_DIM_USIZE {
+      text.push_str(&empty_row);
+      text.push('\n');
+    }
+    text
+  }
+
+  #[test]
+  fn reads_metadata_and_both_sections() {
+    let text = format!(
+      "#AAuthor\n#CFirst\n#CSecond\n#Xextra\n[Puzzle]\n{}[State]\n{}",
+      grid_text(),
+      grid_text()
+    );
+    let sdk_puzzle: SdkPuzzle = text.parse().unwrap();
+    assert_eq!(sdk_puzzle.metadata.author, Some("Author".to_string()));
+    assert_eq!(sdk_puzzle.metadata.comments, vec!["First", "Second"]);
+    assert_eq!(sdk_puzzle.metadata.other, vec![('X', "extra".into())]);
+    assert_eq!(
+      sdk_puzzle.puzzle.cell(SudokuPosition::new(0, 0)),
+      SudokuCell::Filled(SudokuValue::first())
+    );
+    assert_eq!(sdk_puzzle.given_choices().len(), 1);
+    assert_eq!(sdk_puzzle.state, Some(sdk_puzzle.puzzle.clone()));
+
+    // Writing it back out gives the same text.
+    assert_eq!(sdk_puzzle.to_string(), text);
+  }
+
+  #[test]
+  fn reads_files_without_section_headers() {
+    let text = format!("#DOld style\n{}", grid_text());
+    let sdk_puzzle: SdkPuzzle = text.parse().unwrap();
+    assert_eq!(sdk_puzzle.puzzle.filled_choices().len(), 1);
+    assert_eq!(sdk_puzzle.state, None);
+  }
+
+  #[test]
+  fn reports_malformed_grids() {
+    let short_row = grid_text().replacen("1.", "1", 1);
+    let error = short_row.parse::<SdkPuzzle>().unwrap_err();
+    assert_eq!(
+      error.kind,
+      ParseErrorKind::WrongRowLength {
+        expected: SUDOKU_DIM_USIZE,
+        found: SUDOKU_DIM_USIZE - 1,
+      }
+    );
+    assert_eq!((error.line, error.column), (1, SUDOKU_DIM_USIZE));
+
+    let missing_row = format!("[Puzzle]\n{}", &grid_text()[17..]);
+    let error = missing_row.parse::<SdkPuzzle>().unwrap_err();
+    assert_eq!(
+      error.kind,
+      ParseErrorKind::WrongRowCount {
+        expected: SUDOKU_DIM_USIZE,
+        found: SUDOKU_DIM_USIZE - 1,
+      }
+    );
+  }
+
+  #[test]
+  fn reports_malformed_sections() {
+    let duplicate =
+      format!("[Puzzle]\n{}[Puzzle]\n{}", grid_text(), grid_text());
+    let error = duplicate.parse::<SdkPuzzle>().unwrap_err();
+    assert_eq!(
+      error.kind,
+      ParseErrorKind::DuplicateSection("Puzzle".to_string())
+    );
+    assert_eq!(error.line, SUDOKU_DIM_USIZE + 2);
+
+    let unknown = format!("[Notes]\n{}", grid_text());
+    let error = unknown.parse::<SdkPuzzle>().unwrap_err();
+    assert_eq!(
+      error.kind,
+      ParseErrorKind::UnknownSection("Notes".to_string())
+    );
+
+    let error = "#AAuthor\n".parse::<SdkPuzzle>().unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::MissingPuzzle);
+    assert_eq!(error.line, 2);
+  }
+}
@@ -0,0 +1,273 @@
+use super::{ParseError, ParseErrorKind, SymbolSet};
+use core::{
+  SudokuCell, SudokuChoice, SudokuGrid, SudokuPosition,
+  SUDOKU_DIM_SQRT_USIZE, SUDOKU_DIM_USIZE,
+};
+
+// SimpleSudokuFormat reads and writes the boxed grids of the Simple
+// Sudoku `.ss` format:
+//
+//   *-----------*
+//   |..2|.7.|.5.|
+//   |...|...|...|
+//   |...|...|...|
+//   |---+---+---|
+//   ...
+//   *-----------*
+//
+// Border lines are optional when reading, as is the `|` between boxes.
+#[derive(Clone, Debug, Default)]
+pub struct SimpleSudokuFormat {
+  symbols: SymbolSet,
+}
+
+impl SimpleSudokuFormat {
+  pub fn new() -> SimpleSudokuFormat {
+    SimpleSudokuFormat::default()
+  }
+
+  pub fn with_symbols(
+    mut self,
+    symbols: SymbolSet,
+  ) -> SimpleSudokuFormat {
+    self.symbols = symbols;
+    self
+  }
+
+  pub fn parse(&self, text: &str) -> Result<SudokuGrid, ParseError> {
+    let mut grid = SudokuGrid::default();
+    let mut num_rows = 0;
+    let mut num_lines = 0;
+
+    for (line_idx, line) in text.lines().enumerate() {
+      let line_number = line_idx + 1;
+      num_lines = line_number;
+
+      let tokens = tokenize(line);
+      if tokens.is_empty() || tokens.iter().all(|t| is_border(&t.1)) {
+        continue;
+      }
+
+      if num_rows == SUDOKU_DIM_USIZE {
+        return Err(ParseError::new(
+          line_number,
+          tokens[0].0,
+          ParseErrorKind::WrongRowCount {
+            expected: SUDOKU_DIM_USIZE,
+            found: SUDOKU_DIM_USIZE + 1,
+          },
+        ));
+      }
+
+      // The cells of the row are the symbols of all the tokens, read
+      // left to right.
+      let symbols: Vec<(usize, char)> = tokens
+        .iter()
+        .flat_map(|&(column, ref token)| {
+          token
+            .chars()
+            .enumerate()
+            .map(move |(offset, symbol)| (column + offset, symbol))
+        })
+        .collect();
+      if symbols.len() != SUDOKU_DIM_USIZE {
+        let column = match symbols.get(SUDOKU_DIM_USIZE) {
+          Some(&(column, _)) => column,
+          None => line.chars().count() + 1,
+        };
+        return Err(ParseError::new(
+          line_number,
+          column,
+          ParseErrorKind::WrongRowLength {
+            expected: SUDOKU_DIM_USIZE,
+            found: symbols.len(),
+          },
+        ));
+      }
+
+      for (col_idx, &(column, symbol)) in symbols.iter().enumerate() {
+        match self.symbols.cell(symbol) {
+          None => {
+            return Err(ParseError::new(
+              line_number,
+              column,
+              ParseErrorKind::UnexpectedSymbol(symbol),
+            ))
+          }
+          Some(SudokuCell::Empty) => {}
+          Some(SudokuCell::Filled(value)) => {
+            let position =
+              SudokuPosition::new(num_rows as u8, col_idx as u8);
+            grid.place(SudokuChoice::new(position, value));
+          }
+        }
+      }
+      num_rows += 1;
+    }
+
+    if num_rows < SUDOKU_DIM_USIZE {
+      return Err(ParseError::new(
+        num_lines + 1,
+        1,
+        ParseErrorKind::WrongRowCount {
+          expected: SUDOKU_DIM_USIZE,
+          found: num_rows,
+        },
+      ));
+    }
+
+    Ok(grid)
+  }
+
+  pub fn format(&self, grid: &SudokuGrid) -> String {
+    let box_border = "-".repeat(SUDOKU_DIM_SQRT_USIZE);
+    let box_borders = vec![box_border; SUDOKU_DIM_SQRT_USIZE];
+    let outer_border = format!("*{}*\n", box_borders.join("-"));
+    let inner_border = format!("|{}|\n", box_borders.join("+"));
+
+    let mut text = outer_border.clone();
+    for row_idx in 0..SUDOKU_DIM_USIZE {
+      if row_idx > 0 && row_idx % SUDOKU_DIM_SQRT_USIZE == 0 {
+        text.push_str(&inner_border);
+      }
+
+      text.push('|');
+      for col_idx in 0..SUDOKU_DIM_USIZE {
+        let position =
+          SudokuPosition::new(row_idx as u8, col_idx as u8);
+        text.push(self.symbols.cell_symbol(grid.cell(position)));
+        if (col_idx + 1) % SUDOKU_DIM_SQRT_USIZE == 0 {
+          text.push('|');
+        }
+      }
+      text.push('\n');
+    }
+    text.push_str(&outer_border);
+
+    text
+  }
+}
+
+// `tokenize` splits a line into its runs of symbols, treating spaces and
+// the `|` box separators as gaps. Each token comes with the (1-based)
+// column where it starts.
+pub(super) fn tokenize(line: &str) -> Vec<(usize, String)> {
+  let mut tokens: Vec<(usize, String)> = vec![];
+  let mut in_token = false;
+
+  for (char_idx, symbol) in line.chars().enumerate() {
+    if symbol.is_whitespace() || symbol == '|' {
+      in_token = false;
+    } else if in_token {
+      tokens.last_mut().unwrap().1.push(symbol);
+    } else {
+      tokens.push((char_idx + 1, symbol.to_string()));
+      in_token = true;
+    }
+  }
+
+  tokens
+}
+
+// `is_border` says whether a token is part of a border line, like
+// `*-----*` or `|---+---|`. HoDoKu draws its corners with `.`, `:` and
+// `'`, so those count too as long as there is a dash somewhere.
+pub(super) fn is_border(token: &str) -> bool {
+  token.contains(['-', '='])
+    && token.chars().all(|symbol| "*-=+.:'".contains(symbol))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use test_util::seeded_solution;
+
+  // `random_puzzle` is a random solution with every other cell blanked.
+  fn random_puzzle() -> SudokuGrid {
+    let choices: Vec<_> = seeded_solution(1)
+      .filled_choices()
+      .into_iter()
+      .enumerate()
+      .filter(|&(choice_idx, _)| choice_idx % 2 == 0)
+      .map(|(_, choice)| choice)
+      .collect();
+    SudokuGrid::from_choices(&choices)
+  }
+
+  #[test]
+  fn reads_back_what_it_writes() {
+    let ss_format = SimpleSudokuFormat::new();
+    let grid = random_puzzle();
+    let text = ss_format.format(&grid);
+    assert!(text.starts_with("*-------------------*\n|"));
+    assert_eq!(ss_format.parse(&text).unwrap(), grid);
+
+    let ss_format = SimpleSudokuFormat::new().with_symbols(
+      SymbolSet::zero_based().with_empty_symbol('_').unwrap(),
+    );
+    assert_eq!(
+      ss_format.parse(&ss_format.format(&grid)).unwrap(),
+      grid
+    );
+  }
+
+  #[test]
+  fn reads_grids_without_borders() {
+    let ss_format = SimpleSudokuFormat::new();
+    let grid = random_puzzle();
+    let text: String = ss_format
+      .format(&grid)
+      .lines()
+      .filter(|line| !line.contains('-'))
+      .map(|line| format!("{}\n", line.replace('|', " ")))
+      .collect();
+    assert_eq!(ss_format.parse(&text).unwrap(), grid);
+  }
+
+  #[test]
+  fn reports_where_rows_go_wrong() {
+    let ss_format = SimpleSudokuFormat::new();
+    let text = ss_format.format(&random_puzzle());
+
+    // The first row, on the second line, gets a cell too many.
+    let long_row = text.replacen("|\n", ".|\n", 1);
+    let error = ss_format.parse(&long_row).unwrap_err();
+    assert_eq!(
+      error.kind,
+      ParseErrorKind::WrongRowLength {
+        expected: SUDOKU_DIM_USIZE,
+        found: SUDOKU_DIM_USIZE + 1,
+      }
+    );
+    assert_eq!((error.line, error.column), (2, 21));
+
+    // Every other cell is blank, so the first row ends with one.
+    let bad_symbol = text.replacen(".|\n", "?|\n", 1);
+    let error = ss_format.parse(&bad_symbol).unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedSymbol('?'));
+    assert_eq!((error.line, error.column), (2, 20));
+
+    let missing_row: String = text
+      .lines()
+      .skip(2)
+      .map(|line| format!("{}\n", line))
+      .collect();
+    let error = ss_format.parse(&missing_row).unwrap_err();
+    assert_eq!(
+      error.kind,
+      ParseErrorKind::WrongRowCount {
+        expected: SUDOKU_DIM_USIZE,
+        found: SUDOKU_DIM_USIZE - 1,
+      }
+    );
+  }
+
+  #[test]
+  fn recognizes_border_tokens() {
+    assert!(is_border("*-----*"));
+    assert!(is_border("+---+"));
+    assert!(is_border(".---:"));
+    assert!(!is_border("..."));
+    assert!(!is_border("1-2"));
+  }
+}
@@ -0,0 +1,345 @@
+use super::{PencilMarkGrid, SymbolSet};
+use core::{
+  SudokuCell, SudokuChoice, SudokuClueMask, SudokuGrid, SudokuPosition,
+  SudokuValue, SUDOKU_DIM_SQRT_USIZE, SUDOKU_DIM_USIZE,
+};
+
+const THIN_LINE_WIDTH: u32 = 1;
+const THICK_LINE_WIDTH: u32 = 3;
+const GIVEN_COLOR: &str = "#000000";
+const FILLED_COLOR: &str = "#1f4fbf";
+const CANDIDATE_COLOR: &str = "#555555";
+
+// SvgRenderer draws a SudokuGrid as a standalone SVG document: thin
+// lines between cells, thick lines between boxes, givens in bold black
+// and other filled cells in blue. Empty cells can show small candidate
+// values, and any cell can be highlighted with a background color.
+//
+//   let svg = SvgRenderer::new()
+//     .with_givens(&given_choices)
+//     .with_highlight(position, "#ffe680")
+//     .render(&grid);
+#[derive(Clone)]
+pub struct SvgRenderer {
+  cell_size: u32,
+  // When there are no symbols we write values as decimal numbers.
+  symbols: Option<SymbolSet>,
+  // With no givens, every filled cell is drawn as a given.
+  givens: Option<SudokuClueMask>,
+  candidates: Option<PencilMarkGrid>,
+  highlights: Vec<(SudokuPosition, String)>,
+}
+
+impl Default for SvgRenderer {
+  fn default() -> SvgRenderer {
+    SvgRenderer {
+      cell_size: 40,
+      symbols: None,
+      givens: None,
+      candidates: None,
+      highlights: vec![],
+    }
+  }
+}
+
+impl SvgRenderer {
+  pub fn new() -> SvgRenderer {
+    SvgRenderer::default()
+  }
+
+  // `with_cell_size` sets the side of a cell, in SVG user units.
+  pub fn with_cell_size(mut self, cell_size: u32) -> SvgRenderer {
+    self.cell_size = cell_size.max(1);
+    self
+  }
+
+  pub fn with_symbols(mut self, symbols: SymbolSet) -> SvgRenderer {
+    self.symbols = Some(symbols);
+    self
+  }
+
+  // `with_givens` marks which filled cells are givens. The other
+  // filled cells are drawn as the solver's (or player's) work.
+  pub fn with_givens(
+    mut self,
+    given_choices: &[SudokuChoice],
+  ) -> SvgRenderer {
+    let given_positions: Vec<SudokuPosition> =
+      given_choices.iter().map(|choice| choice.position).collect();
+    self.givens =
+      Some(SudokuClueMask::from_positions(&given_positions));
+    self
+  }
+
+  // `with_candidates` draws the candidates of every empty cell in small
+  // type.
+  pub fn with_candidates(
+    mut self,
+    candidates: PencilMarkGrid,
+  ) -> SvgRenderer {
+    self.candidates = Some(candidates);
+    self
+  }
+
+  // `with_highlight` fills the cell's background with `color`, which is
+  // any SVG color ("yellow", "#ffe680", ...).
+  pub fn with_highlight(
+    mut self,
+    position: SudokuPosition,
+    color: &str,
+  ) -> SvgRenderer {
+    self.highlights.push((position, color.to_string()));
+    self
+  }
+
+  pub fn render(&self, grid: &SudokuGrid) -> String {
+    // Leave room for the outer thick border, which is centered on the
+    // edge of the grid.
+    let margin = THICK_LINE_WIDTH;
+    let grid_size = self.cell_size * SUDOKU_DIM_USIZE as u32;
+    let size = grid_size + 2 * margin;
+
+    let mut svg = String::new();
+    svg.push_str(&format!(
+      "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" \
+       height=\"{size}\" viewBox=\"0 0 {size} {size}\">\n",
+      size = size
+    ));
+    svg.push_str(&format!(
+      "<rect width=\"{size}\" height=\"{size}\" fill=\"#ffffff\"/>\n",
+      size = size
+    ));
+    svg.push_str(&format!(
+      "<g transform=\"translate({margin} {margin})\" \
+       font-family=\"sans-serif\" text-anchor=\"middle\" \
+       dominant-baseline=\"central\">\n",
+      margin = margin
+    ));
+
+    for &(position, ref color) in &self.highlights {
+      let (x, y) = self.cell_origin(position);
+      svg.push_str(&format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{size}\" height=\"{size}\" \
+         fill=\"{}\"/>\n",
+        x,
+        y,
+        escape(color),
+        size = self.cell_size
+      ));
+    }
+
+    for position in SudokuPosition::all() {
+      match grid.cell(position) {
+        SudokuCell::Filled(value) => {
+          self.push_value(&mut svg, position, value)
+        }
+        SudokuCell::Empty => self.push_candidates(&mut svg, position),
+      }
+    }
+
+    self.push_lines(&mut svg, grid_size);
+    svg.push_str("</g>\n</svg>\n");
+
+    svg
+  }
+
+  fn push_value(
+    &self,
+    svg: &mut String,
+    position: SudokuPosition,
+    value: SudokuValue,
+  ) {
+    let is_given = self
+      .givens
+      .as_ref()
+      .is_none_or(|givens| givens.contains(position));
+    let (color, weight) = if is_given {
+      (GIVEN_COLOR, "bold")
+    } else {
+      (FILLED_COLOR, "normal")
+    };
+
+    let (x, y) = self.cell_origin(position);
+    let half_cell = self.cell_size / 2;
+    svg.push_str(&format!(
+      "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-weight=\"{}\" \
+       fill=\"{}\">{}</text>\n",
+      x + half_cell,
+      y + half_cell,
+      self.cell_size * 3 / 5,
+      weight,
+      color,
+      escape(&self.symbol(value))
+    ));
+  }
+
+  // `push_candidates` lays the candidates out like the cells of a box:
+  // value 1 in the top left corner, the last value in the bottom right.
+  fn push_candidates(
+    &self,
+    svg: &mut String,
+    position: SudokuPosition,
+  ) {
+    let candidates = match self.candidates {
+      None => return,
+      Some(ref candidates) => candidates.candidates(position),
+    };
+
+    // Candidates are laid out in the cell like the cells of a box.
+    let (x, y) = self.cell_origin(position);
+    let slot_size = self.cell_size / SUDOKU_DIM_SQRT_USIZE as u32;
+    let font_size = slot_size * 3 / 4;
+    for &value in candidates {
+      let slot_idx = value.as_usize_idx();
+      let slot_row = (slot_idx / SUDOKU_DIM_SQRT_USIZE) as u32;
+      let slot_col = (slot_idx % SUDOKU_DIM_SQRT_USIZE) as u32;
+      svg.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" fill=\"{}\">{}</text>\n",
+        x + slot_col * slot_size + slot_size / 2,
+        y + slot_row * slot_size + slot_size / 2,
+        font_size.max(1),
+        CANDIDATE_COLOR,
+        escape(&self.symbol(value))
+      ));
+    }
+  }
+
+  // `push_lines` draws the thin cell lines first, so that the thick box
+  // lines cover them where they cross.
+  fn push_lines(&self, svg: &mut String, grid_size: u32) {
+    for &is_box_line in &[false, true] {
+      let stroke_width = if is_box_line {
+        THICK_LINE_WIDTH
+      } else {
+        THIN_LINE_WIDTH
+      };
+
+      for idx in 0..=SUDOKU_DIM_USIZE {
+        if idx.is_multiple_of(SUDOKU_DIM_SQRT_USIZE) != is_box_line {
+          continue;
+        }
+        // A horizontal line, and then a vertical one.
+        let offset = idx as u32 * self.cell_size;
+        let lines = [
+          (0, offset, grid_size, offset),
+          (offset, 0, offset, grid_size),
+        ];
+        for &(x1, y1, x2, y2) in &lines {
+          svg.push_str(&format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" \
+             stroke=\"#000000\" stroke-width=\"{}\" \
+             stroke-linecap=\"square\"/>\n",
+            x1, y1, x2, y2, stroke_width
+          ));
+        }
+      }
+    }
+  }
+
+  fn cell_origin(&self, position: SudokuPosition) -> (u32, u32) {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    (
+      col_idx as u32 * self.cell_size,
+      row_idx as u32 * self.cell_size,
+    )
+  }
+
+  fn symbol(&self, value: SudokuValue) -> String {
+    match self.symbols {
+      None => value.as_u8_value().to_string(),
+      Some(ref symbols) => symbols.symbol(value).to_string(),
+    }
+  }
+}
+
+// `escape` makes text safe to put in an SVG attribute or element.
+fn escape(text: &str) -> String {
+  let mut escaped = String::new();
+  for symbol in text.chars() {
+    match symbol {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      _ => escaped.push(symbol),
+    }
+  }
+
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn given() -> SudokuChoice {
+    SudokuChoice::new(SudokuPosition::new(0, 0), SudokuValue::new(1))
+  }
+
+  fn filled() -> SudokuChoice {
+    SudokuChoice::new(SudokuPosition::new(0, 1), SudokuValue::new(16))
+  }
+
+  #[test]
+  fn draws_every_line_with_thick_box_borders() {
+    let svg = SvgRenderer::new()
+      .with_cell_size(10)
+      .render(&SudokuGrid::default());
+    assert!(svg.starts_with(
+      "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"166\""
+    ));
+    assert!(svg.ends_with("</svg>\n"));
+
+    // Both directions have a line on each side of every cell.
+    let num_lines = 2 * (SUDOKU_DIM_USIZE + 1);
+    let num_box_lines = 2 * (SUDOKU_DIM_SQRT_USIZE + 1);
+    assert_eq!(svg.matches("<line ").count(), num_lines);
+    assert_eq!(
+      svg.matches("stroke-width=\"3\"").count(),
+      num_box_lines
+    );
+    assert!(!svg.contains("<text "));
+  }
+
+  #[test]
+  fn givens_are_drawn_apart_from_other_values() {
+    let grid = SudokuGrid::from_choices(&[given(), filled()]);
+    let svg = SvgRenderer::new().render(&grid);
+    assert_eq!(svg.matches("font-weight=\"bold\"").count(), 2);
+
+    let svg = SvgRenderer::new()
+      .with_givens(&[given()])
+      .with_symbols(SymbolSet::one_based())
+      .render(&grid);
+    assert!(svg.contains("font-weight=\"bold\" fill=\"#000000\">1<"));
+    assert!(svg.contains("font-weight=\"normal\" fill=\"#1f4fbf\">G<"));
+  }
+
+  #[test]
+  fn candidates_fill_only_empty_cells() {
+    let grid = SudokuGrid::from_choices(&[given()]);
+    let svg = SvgRenderer::new()
+      .with_candidates(PencilMarkGrid::from_grid(&grid))
+      .render(&grid);
+
+    // Every empty cell shows every value, but the given's 39 peers
+    // have lost the 1.
+    let num_cells = SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE;
+    let num_candidates = (num_cells - 1) * SUDOKU_DIM_USIZE - 39;
+    let candidate_fill = format!("fill=\"{}\"", CANDIDATE_COLOR);
+    assert_eq!(svg.matches(&candidate_fill).count(), num_candidates);
+  }
+
+  #[test]
+  fn escapes_highlight_colors() {
+    let svg = SvgRenderer::new()
+      .with_highlight(SudokuPosition::new(1, 2), "\"/><script>")
+      .render(&SudokuGrid::default());
+    assert!(svg.contains(
+      "<rect x=\"80\" y=\"40\" width=\"40\" height=\"40\" \
+       fill=\"&quot;/&gt;&lt;script&gt;\"/>"
+    ));
+    assert!(!svg.contains("<script>"));
+  }
+}
@@ -0,0 +1,190 @@
+use core::{SudokuCell, SudokuValue, SUDOKU_DIM_USIZE};
+
+const DIGITS_AND_LETTERS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// SymbolSet is the alphabet text formats use to write values, one
+// character per value. Values stay numbers everywhere else; only
+// parsing and formatting go through a SymbolSet.
+//
+// When reading, lowercase letters are accepted for uppercase ones, and
+// '0' is accepted as an empty cell unless it stands for a value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SymbolSet {
+  // `symbols[i]` stands for the value `i + 1`.
+  symbols: Vec<char>,
+  empty_symbol: char,
+}
+
+impl Default for SymbolSet {
+  fn default() -> SymbolSet {
+    SymbolSet::one_based()
+  }
+}
+
+impl SymbolSet {
+  // `one_based` writes 1 through 9 as digits, and goes on with letters:
+  // `1-9A-G` for a 16x16 grid. This is the default.
+  pub fn one_based() -> SymbolSet {
+    SymbolSet::from_pool(&DIGITS_AND_LETTERS[1..])
+  }
+
+  // `zero_based` starts from zero: `0-9A-F` for a 16x16 grid.
+  pub fn zero_based() -> SymbolSet {
+    SymbolSet::from_pool(DIGITS_AND_LETTERS)
+  }
+
+  // `letters` uses only letters, `A-Y` for a 25x25 grid.
+  pub fn letters() -> SymbolSet {
+    SymbolSet::from_pool(LETTERS)
+  }
+
+  // `custom` uses the characters of `symbols` in order, as for a
+  // Wordoku. Returns None unless there is exactly one distinct symbol
+  // per value, none of them whitespace or the empty symbol.
+  pub fn custom(symbols: &str) -> Option<SymbolSet> {
+    SymbolSet {
+      symbols: symbols.chars().collect(),
+      empty_symbol: '.',
+    }
+    .validated()
+  }
+
+  // `with_empty_symbol` changes the symbol for an empty cell, which is
+  // '.' by default. Returns None if it clashes with a value's symbol.
+  pub fn with_empty_symbol(
+    mut self,
+    empty_symbol: char,
+  ) -> Option<SymbolSet> {
+    self.empty_symbol = empty_symbol;
+    self.validated()
+  }
+
+  pub fn symbol(&self, value: SudokuValue) -> char {
+    self.symbols[value.as_usize_idx()]
+  }
+
+  pub fn empty_symbol(&self) -> char {
+    self.empty_symbol
+  }
+
+  pub fn cell_symbol(&self, cell: SudokuCell) -> char {
+    match cell {
+      SudokuCell::Empty => self.empty_symbol,
+      SudokuCell::Filled(value) => self.symbol(value),
+    }
+  }
+
+  // `value` returns None if the character doesn't stand for a value.
+  pub fn value(&self, symbol: char) -> Option<SudokuValue> {
+    let symbol_idx =
+      self.symbols.iter().position(|&s| s == symbol).or_else(|| {
+        let upper_symbol = symbol.to_ascii_uppercase();
+        self.symbols.iter().position(|&s| s == upper_symbol)
+      })?;
+
+    Some(SudokuValue::new(symbol_idx as u8 + 1))
+  }
+
+  // `cell` returns None if the character doesn't stand for a value or
+  // an empty cell.
+  pub fn cell(&self, symbol: char) -> Option<SudokuCell> {
+    if let Some(value) = self.value(symbol) {
+      Some(SudokuCell::Filled(value))
+    } else if symbol == self.empty_symbol
+      || symbol == '.'
+      || symbol == '0'
+    {
+      Some(SudokuCell::Empty)
+    } else {
+      None
+    }
+  }
+
+  // `from_pool` takes as many symbols from the front of `pool` as there
+  // are values.
+  fn from_pool(pool: &str) -> SymbolSet {
+    assert!(
+      pool.len() >= SUDOKU_DIM_USIZE,
+      "not enough symbols for a grid this big"
+    );
+
+    SymbolSet {
+      symbols: pool.chars().take(SUDOKU_DIM_USIZE).collect(),
+      empty_symbol: '.',
+    }
+  }
+
+  fn validated(self) -> Option<SymbolSet> {
+    if self.symbols.len() != SUDOKU_DIM_USIZE {
+      return None;
+    }
+    for (idx, &symbol) in self.symbols.iter().enumerate() {
+      if symbol.is_whitespace()
+        || symbol == self.empty_symbol
+        || self.symbols[..idx].contains(&symbol)
+      {
+        return None;
+      }
+    }
+    if self.empty_symbol.is_whitespace() {
+      return None;
+    }
+
+    Some(self)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn built_in_sets_write_values() {
+    let first = SudokuValue::first();
+    let last = SudokuValue::new(SUDOKU_DIM_USIZE as u8);
+    assert_eq!(SymbolSet::one_based().symbol(first), '1');
+    assert_eq!(SymbolSet::one_based().symbol(last), 'G');
+    assert_eq!(SymbolSet::zero_based().symbol(first), '0');
+    assert_eq!(SymbolSet::zero_based().symbol(last), 'F');
+    assert_eq!(SymbolSet::letters().symbol(last), 'P');
+  }
+
+  #[test]
+  fn reads_lowercase_letters_and_empty_cells() {
+    let symbols = SymbolSet::one_based();
+    assert_eq!(
+      symbols.cell('g'),
+      Some(SudokuCell::Filled(SudokuValue::new(16)))
+    );
+    assert_eq!(symbols.cell('.'), Some(SudokuCell::Empty));
+    assert_eq!(symbols.cell('0'), Some(SudokuCell::Empty));
+    assert_eq!(symbols.cell('H'), None);
+
+    // In the zero-based set '0' is a value, not an empty cell.
+    assert_eq!(
+      SymbolSet::zero_based().cell('0'),
+      Some(SudokuCell::Filled(SudokuValue::first()))
+    );
+  }
+
+  #[test]
+  fn custom_sets_need_one_distinct_symbol_per_value() {
+    let wordoku = "ABCDEFGHIJKLMNOP";
+    assert!(SymbolSet::custom(wordoku).is_some());
+    assert!(SymbolSet::custom(&wordoku[1..]).is_none());
+    assert!(SymbolSet::custom("ABCDEFGHIJKLMNOA").is_none());
+    assert!(SymbolSet::custom("ABCDEFGHIJKLMNO.").is_none());
+    assert!(SymbolSet::custom("ABCDEFGHIJKLMNO ").is_none());
+  }
+
+  #[test]
+  fn empty_symbols_cannot_clash_with_values() {
+    let symbols =
+      SymbolSet::one_based().with_empty_symbol('-').unwrap();
+    assert_eq!(symbols.cell_symbol(SudokuCell::Empty), '-');
+    assert_eq!(symbols.cell('-'), Some(SudokuCell::Empty));
+    assert!(SymbolSet::one_based().with_empty_symbol('A').is_none());
+    assert!(SymbolSet::one_based().with_empty_symbol(' ').is_none());
+  }
+}
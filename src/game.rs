@@ -0,0 +1,569 @@
+use super::checker::SudokuGridConflictChecker;
+use super::core::{
+  SudokuBox, SudokuCell, SudokuChoice, SudokuGrid, SudokuPosition,
+  SudokuValue, SUDOKU_DIM_SQRT_USIZE, SUDOKU_DIM_USIZE,
+};
+use super::solver::{
+  SearchControl, SolverEvent, SolverResult, SudokuSolver,
+};
+
+// SudokuGame is a puzzle being solved by hand: the player fills in
+// values and pencil marks, and can undo and redo their moves. It is
+// what a front end, like the terminal player, keeps as its state.
+//
+// Entries are checked with a SudokuGridConflictChecker as they are
+// made. The checker only holds choices that fit, so an entry it refuses
+// (because it clashes with a peer, or leaves a peer with no possible
+// value) stays in the grid but is remembered as a conflict, until the
+// moves that caused it are taken back.
+pub struct SudokuGame {
+  givens: SudokuGrid,
+  grid: SudokuGrid,
+  // `pencil_marks[row_idx * SUDOKU_DIM_USIZE + col_idx]` are the marks
+  // in that cell, in increasing order.
+  pencil_marks: Vec<Vec<SudokuValue>>,
+  grid_checker: SudokuGridConflictChecker,
+  // `givens_checker` is the checker before any moves, for solving the
+  // givens alone.
+  givens_checker: SudokuGridConflictChecker,
+  // Filled positions whose entry the checker refused.
+  conflicts: Vec<SudokuPosition>,
+  undo_moves: Vec<GameMove>,
+  redo_moves: Vec<GameMove>,
+}
+
+// GameMove records what a cell held before and after a move, so that
+// it can be undone and redone.
+#[derive(Clone, Debug)]
+struct GameMove {
+  position: SudokuPosition,
+  before: CellState,
+  after: CellState,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct CellState {
+  cell: SudokuCell,
+  pencil_marks: Vec<SudokuValue>,
+}
+
+impl SudokuGame {
+  // `new` starts a game on the puzzle. Returns None if the givens are
+  // manifestly incompatible with each other.
+  pub fn new(puzzle: &SudokuGrid) -> Option<SudokuGame> {
+    let mut grid_checker = SudokuGridConflictChecker::new();
+    for choice in puzzle.filled_choices() {
+      if !grid_checker.add_choice(choice).did_add() {
+        return None;
+      }
+    }
+
+    Some(SudokuGame::from_checker(puzzle, grid_checker))
+  }
+
+  // `from_checker` starts a game from a `grid_checker` which already
+  // holds the givens of the puzzle, and perhaps candidates a player
+  // eliminated (see `PencilMarkGrid::to_checker`). Candidates, steps
+  // and hints then keep to those eliminations.
+  pub fn from_checker(
+    puzzle: &SudokuGrid,
+    grid_checker: SudokuGridConflictChecker,
+  ) -> SudokuGame {
+    SudokuGame {
+      givens: puzzle.clone(),
+      grid: puzzle.clone(),
+      pencil_marks: vec![vec![]; SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE],
+      givens_checker: grid_checker.clone(),
+      grid_checker,
+      conflicts: vec![],
+      undo_moves: vec![],
+      redo_moves: vec![],
+    }
+  }
+
+  pub fn givens(&self) -> &SudokuGrid {
+    &self.givens
+  }
+
+  // `grid` has the givens and the player's entries.
+  pub fn grid(&self) -> &SudokuGrid {
+    &self.grid
+  }
+
+  pub fn is_given(&self, position: SudokuPosition) -> bool {
+    self.givens.cell(position) != SudokuCell::Empty
+  }
+
+  pub fn is_conflict(&self, position: SudokuPosition) -> bool {
+    self.conflicts.contains(&position)
+  }
+
+  pub fn pencil_marks(
+    &self,
+    position: SudokuPosition,
+  ) -> &[SudokuValue] {
+    &self.pencil_marks[cell_idx(position)]
+  }
+
+  // `grid_checker` holds the entries that fit, and any eliminated
+  // candidates.
+  pub fn grid_checker(&self) -> &SudokuGridConflictChecker {
+    &self.grid_checker
+  }
+
+  // `candidates` are the values that don't clash with the entries the
+  // checker holds. For a filled position, they include its value.
+  pub fn candidates(
+    &self,
+    position: SudokuPosition,
+  ) -> Vec<SudokuValue> {
+    self.grid_checker.candidates(position)
+  }
+
+  // `is_solved` is true once every cell is filled without conflicts.
+  pub fn is_solved(&self) -> bool {
+    self.conflicts.is_empty()
+      && SudokuPosition::all()
+        .into_iter()
+        .all(|position| self.grid.cell(position) != SudokuCell::Empty)
+  }
+
+  // `place` fills in a value. Givens can't be changed.
+  pub fn place(
+    &mut self,
+    position: SudokuPosition,
+    value: SudokuValue,
+  ) -> GameMoveResult {
+    let pencil_marks = self.pencil_marks(position).to_vec();
+    self.make_move(
+      position,
+      CellState {
+        cell: SudokuCell::Filled(value),
+        pencil_marks,
+      },
+    )
+  }
+
+  // `clear` empties a filled cell, or else rubs out its pencil marks.
+  pub fn clear(&mut self, position: SudokuPosition) -> GameMoveResult {
+    let state = if self.grid.cell(position) == SudokuCell::Empty {
+      CellState {
+        cell: SudokuCell::Empty,
+        pencil_marks: vec![],
+      }
+    } else {
+      CellState {
+        cell: SudokuCell::Empty,
+        pencil_marks: self.pencil_marks(position).to_vec(),
+      }
+    };
+    self.make_move(position, state)
+  }
+
+  // `toggle_pencil_mark` adds the mark, or removes it if it was there.
+  pub fn toggle_pencil_mark(
+    &mut self,
+    position: SudokuPosition,
+    value: SudokuValue,
+  ) -> GameMoveResult {
+    let mut pencil_marks = self.pencil_marks(position).to_vec();
+    match pencil_marks
+      .binary_search_by_key(&value.as_u8_value(), |mark| {
+        mark.as_u8_value()
+      }) {
+      Ok(mark_idx) => {
+        pencil_marks.remove(mark_idx);
+      }
+      Err(mark_idx) => pencil_marks.insert(mark_idx, value),
+    }
+    let cell = self.grid.cell(position);
+    self.make_move(position, CellState { cell, pencil_marks })
+  }
+
+  // `undo` takes back the last move, and returns where it was made.
+  pub fn undo(&mut self) -> Option<SudokuPosition> {
+    let game_move = self.undo_moves.pop()?;
+    self.set_cell_state(game_move.position, game_move.before.clone());
+    let position = game_move.position;
+    self.redo_moves.push(game_move);
+
+    Some(position)
+  }
+
+  // `redo` makes the last undone move again, and returns where it was
+  // made. Any new move forgets the undone ones.
+  pub fn redo(&mut self) -> Option<SudokuPosition> {
+    let game_move = self.redo_moves.pop()?;
+    self.set_cell_state(game_move.position, game_move.after.clone());
+    let position = game_move.position;
+    self.undo_moves.push(game_move);
+
+    Some(position)
+  }
+
+  // `hint` asks the solver for help, making no more than `max_nodes`
+  // choices. When an entry is wrong, the hint points it out; otherwise
+  // it gives the value of the empty cell with the fewest candidates.
+  // Returns None if the puzzle has no solution, is already solved, or
+  // the solver gave up.
+  pub fn hint(&self, max_nodes: u64) -> Option<GameHint> {
+    self.hint_observed(max_nodes, |_| SearchControl::Continue)
+  }
+
+  // `hint_observed` is `hint`, reporting the solver's events to
+  // `observer` like `SudokuSolver::next_solution_observed` does. When
+  // the observer stops the search, there is no hint.
+  pub fn hint_observed<O>(
+    &self,
+    max_nodes: u64,
+    mut observer: O,
+  ) -> Option<GameHint>
+  where
+    O: FnMut(SolverEvent) -> SearchControl,
+  {
+    if let Some(&position) = self.conflicts.first() {
+      return Some(GameHint::WrongEntry(position));
+    }
+
+    let solution = match solve(
+      &self.grid,
+      &self.grid_checker,
+      max_nodes,
+      &mut observer,
+    ) {
+      Ok(solution) => solution?,
+      Err(NoSolution) => {
+        // Some entry is wrong: compare with a solution of the givens.
+        let solution = solve(
+          &self.givens,
+          &self.givens_checker,
+          max_nodes,
+          &mut observer,
+        )
+        .ok()??;
+        let position =
+          SudokuPosition::all().into_iter().find(|&position| {
+            self.grid.cell(position) != SudokuCell::Empty
+              && self.grid.cell(position) != solution.cell(position)
+          })?;
+        return Some(GameHint::WrongEntry(position));
+      }
+    };
+
+    let position = SudokuPosition::all()
+      .into_iter()
+      .filter(|&position| self.grid.cell(position) == SudokuCell::Empty)
+      .min_by_key(|&position| self.candidates(position).len())?;
+    match solution.cell(position) {
+      SudokuCell::Filled(value) => {
+        Some(GameHint::Value(SudokuChoice::new(position, value)))
+      }
+      SudokuCell::Empty => None,
+    }
+  }
+
+  // `next_step` finds a value that follows from the candidates alone:
+  // a naked single (an empty cell with one candidate left) or, failing
+  // that, a hidden single (a value with one cell left in a row, column
+  // or box). Returns None if there is neither, or if there are
+  // conflicts, which make the candidates unreliable.
+  pub fn next_step(&self) -> Option<LogicalStep> {
+    if !self.conflicts.is_empty() {
+      return None;
+    }
+    let candidates: Vec<Vec<SudokuValue>> = SudokuPosition::all()
+      .into_iter()
+      .map(|position| match self.grid.cell(position) {
+        SudokuCell::Empty => self.candidates(position),
+        SudokuCell::Filled(_) => vec![],
+      })
+      .collect();
+
+    for position in SudokuPosition::all() {
+      if let [value] = candidates[cell_idx(position)][..] {
+        return Some(LogicalStep {
+          choice: SudokuChoice::new(position, value),
+          reason: StepReason::NakedSingle,
+        });
+      }
+    }
+
+    for (reason, positions) in units() {
+      for value in SudokuValue::all() {
+        let mut places = positions.iter().filter(|&&position| {
+          candidates[cell_idx(position)].contains(&value)
+        });
+        if let (Some(&position), None) = (places.next(), places.next())
+        {
+          return Some(LogicalStep {
+            choice: SudokuChoice::new(position, value),
+            reason,
+          });
+        }
+      }
+    }
+
+    None
+  }
+
+  fn make_move(
+    &mut self,
+    position: SudokuPosition,
+    after: CellState,
+  ) -> GameMoveResult {
+    if self.is_given(position) {
+      return GameMoveResult::CouldNotMove;
+    }
+    let before = CellState {
+      cell: self.grid.cell(position),
+      pencil_marks: self.pencil_marks(position).to_vec(),
+    };
+    if before == after {
+      return GameMoveResult::CouldNotMove;
+    }
+
+    self.set_cell_state(position, after.clone());
+    self.undo_moves.push(GameMove {
+      position,
+      before,
+      after,
+    });
+    self.redo_moves.clear();
+
+    GameMoveResult::DidMove
+  }
+
+  // `set_cell_state` changes a cell, and keeps the checker and the
+  // conflicts up to date.
+  fn set_cell_state(
+    &mut self,
+    position: SudokuPosition,
+    state: CellState,
+  ) {
+    if let SudokuCell::Filled(old_value) = self.grid.cell(position) {
+      if let Some(conflict_idx) =
+        self.conflicts.iter().position(|&p| p == position)
+      {
+        self.conflicts.remove(conflict_idx);
+      } else {
+        self
+          .grid_checker
+          .remove_choice(SudokuChoice::new(position, old_value));
+        // With this entry gone, earlier conflicts may fit again.
+        self.retry_conflicts();
+      }
+      self.grid.clear(position);
+    }
+
+    if let SudokuCell::Filled(value) = state.cell {
+      let choice = SudokuChoice::new(position, value);
+      if !self.grid_checker.add_choice(choice).did_add() {
+        self.conflicts.push(position);
+      }
+      self.grid.place(choice);
+    }
+    self.pencil_marks[cell_idx(position)] = state.pencil_marks;
+  }
+
+  fn retry_conflicts(&mut self) {
+    let conflicts = self.conflicts.clone();
+    for position in conflicts {
+      let value = match self.grid.cell(position) {
+        SudokuCell::Filled(value) => value,
+        SudokuCell::Empty => continue,
+      };
+      let choice = SudokuChoice::new(position, value);
+      if self.grid_checker.add_choice(choice).did_add() {
+        self.conflicts.retain(|&p| p != position);
+      }
+    }
+  }
+}
+
+// GameMoveResult tells the caller whether a move was made. Moves on a
+// given, or that change nothing, are refused.
+#[must_use]
+#[derive(Clone, Copy)]
+pub enum GameMoveResult {
+  CouldNotMove,
+  DidMove,
+}
+
+impl GameMoveResult {
+  pub fn did_move(self) -> bool {
+    match self {
+      GameMoveResult::CouldNotMove => false,
+      GameMoveResult::DidMove => true,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum GameHint {
+  // This entry is wrong, or clashes with others.
+  WrongEntry(SudokuPosition),
+  // This value goes here.
+  Value(SudokuChoice),
+}
+
+// LogicalStep is a value that can be filled in without guessing, and
+// why.
+#[derive(Clone, Copy, Debug)]
+pub struct LogicalStep {
+  pub choice: SudokuChoice,
+  pub reason: StepReason,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StepReason {
+  // The cell has no other candidate.
+  NakedSingle,
+  // No other cell of the row, column or box can take the value.
+  HiddenSingleInRow,
+  HiddenSingleInColumn,
+  HiddenSingleInBox,
+}
+
+struct NoSolution;
+
+// `solve` finds a solution of the grid, whose entries `grid_checker`
+// holds. It is Ok(None) when the solver gave up, or was stopped.
+fn solve<O>(
+  grid: &SudokuGrid,
+  grid_checker: &SudokuGridConflictChecker,
+  max_nodes: u64,
+  observer: &mut O,
+) -> Result<Option<SudokuGrid>, NoSolution>
+where
+  O: FnMut(SolverEvent) -> SearchControl,
+{
+  let given_choices = grid.filled_choices();
+  let mut solver =
+    SudokuSolver::from_checker(&given_choices, grid_checker.clone())
+      .with_node_limit(max_nodes);
+  match solver.next_solution_observed(&mut *observer) {
+    SolverResult::Solved(solution_choices) => {
+      let mut solution = grid.clone();
+      for choice in solution_choices {
+        solution.place(choice);
+      }
+      Ok(Some(solution))
+    }
+    SolverResult::NoMoreSolutions => Err(NoSolution),
+    SolverResult::NodeLimitReached => Ok(None),
+  }
+}
+
+// `units` lists the positions of every row, column and box, with the
+// kind of hidden single found there.
+fn units() -> Vec<(StepReason, Vec<SudokuPosition>)> {
+  let mut units = vec![];
+  for row_idx in 0..SUDOKU_DIM_USIZE {
+    let positions = (0..SUDOKU_DIM_USIZE)
+      .map(|col_idx| position_at(row_idx, col_idx))
+      .collect();
+    units.push((StepReason::HiddenSingleInRow, positions));
+  }
+  for col_idx in 0..SUDOKU_DIM_USIZE {
+    let positions = (0..SUDOKU_DIM_USIZE)
+      .map(|row_idx| position_at(row_idx, col_idx))
+      .collect();
+    units.push((StepReason::HiddenSingleInColumn, positions));
+  }
+  for box_idx in 0..SUDOKU_DIM_USIZE {
+    let top_left = position_at(
+      box_idx / SUDOKU_DIM_SQRT_USIZE * SUDOKU_DIM_SQRT_USIZE,
+      box_idx % SUDOKU_DIM_SQRT_USIZE * SUDOKU_DIM_SQRT_USIZE,
+    );
+    let positions = SudokuBox::for_position(top_left).positions();
+    units.push((StepReason::HiddenSingleInBox, positions.collect()));
+  }
+
+  units
+}
+
+fn position_at(row_idx: usize, col_idx: usize) -> SudokuPosition {
+  SudokuPosition::new(row_idx as u8, col_idx as u8)
+}
+
+fn cell_idx(position: SudokuPosition) -> usize {
+  let (row_idx, col_idx) = position.as_usize_pair();
+  row_idx * SUDOKU_DIM_USIZE + col_idx
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use test_util::seeded_solution;
+
+  #[test]
+  fn entries_that_clash_are_conflicts_until_undone() {
+    let mut game = SudokuGame::new(&SudokuGrid::default()).unwrap();
+    let value = SudokuValue::first();
+    let position = SudokuPosition::new(0, 0);
+    let peer_position = SudokuPosition::new(0, 1);
+    assert!(game.place(position, value).did_move());
+    assert!(game.place(peer_position, value).did_move());
+    assert!(!game.is_conflict(position));
+    assert!(game.is_conflict(peer_position));
+    assert!(!game.is_solved());
+
+    assert_eq!(game.undo(), Some(peer_position));
+    assert!(!game.is_conflict(peer_position));
+    assert_eq!(game.grid().cell(peer_position), SudokuCell::Empty);
+    assert_eq!(game.redo(), Some(peer_position));
+    assert!(game.is_conflict(peer_position));
+  }
+
+  #[test]
+  fn givens_cannot_be_changed() {
+    let position = SudokuPosition::new(3, 4);
+    let mut puzzle = SudokuGrid::default();
+    puzzle.place(SudokuChoice::new(position, SudokuValue::first()));
+    let mut game = SudokuGame::new(&puzzle).unwrap();
+    assert!(!game.clear(position).did_move());
+    assert!(!game.place(position, SudokuValue::new(2)).did_move());
+  }
+
+  #[test]
+  fn steps_and_hints_keep_to_eliminated_candidates() {
+    // The bottom half of a solution leaves eight candidates in each cell
+    // of the top half. Crossing out all but the right value in the top
+    // left cell makes a naked single there.
+    let solution = seeded_solution(1).filled_choices();
+    let (top_half, bottom_half) =
+      solution.split_at(SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE / 2);
+    let puzzle = SudokuGrid::from_choices(bottom_half);
+    let SudokuChoice { position, value } = top_half[0];
+    assert_eq!(position, SudokuPosition::new(0, 0));
+
+    let mut grid_checker = SudokuGridConflictChecker::new();
+    for &choice in bottom_half {
+      assert!(grid_checker.add_choice(choice).did_add());
+    }
+    for other_value in grid_checker.candidates(position) {
+      if other_value != value {
+        assert!(grid_checker
+          .eliminate_candidate(position, other_value)
+          .did_eliminate());
+      }
+    }
+
+    let game = SudokuGame::from_checker(&puzzle, grid_checker);
+    assert_eq!(game.candidates(position), vec![value]);
+    let step = game.next_step().unwrap();
+    assert_eq!(step.choice.position, position);
+    assert_eq!(step.choice.value, value);
+    assert_eq!(step.reason, StepReason::NakedSingle);
+    match game.hint(100_000) {
+      Some(GameHint::Value(choice)) => {
+        assert_eq!((choice.position, choice.value), (position, value))
+      }
+      hint => panic!("unexpected hint {:?}", hint),
+    }
+
+    // Without the eliminations, the cell is far from solved.
+    let game = SudokuGame::new(&puzzle).unwrap();
+    assert_eq!(game.candidates(position).len(), SUDOKU_DIM_USIZE / 2);
+  }
+}
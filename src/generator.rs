@@ -0,0 +1,181 @@
+use super::core::SudokuChoice;
+use super::solver::{
+  SearchControl, SolverEvent, SolverResult, SudokuSolver,
+};
+use rand::Rng;
+
+// The clue mask lives in `core`, since the formats use it too.
+pub use super::core::SudokuClueMask;
+
+// GeneratorBudget bounds how hard the generator tries before it
+// reports failure.
+#[derive(Clone, Copy, Debug)]
+pub struct GeneratorBudget {
+  // The number of random solution grids we try.
+  pub max_attempts: usize,
+  // The number of solver choices we allow for each search: drawing a
+  // solution grid, and checking a candidate puzzle has a unique
+  // solution. An attempt whose search runs out of nodes is thrown away.
+  pub max_nodes_per_attempt: u64,
+}
+
+impl Default for GeneratorBudget {
+  fn default() -> GeneratorBudget {
+    GeneratorBudget {
+      max_attempts: 100,
+      max_nodes_per_attempt: 1_000_000,
+    }
+  }
+}
+
+// SudokuGenerator builds puzzles, which are returned as the list of
+// given SudokuChoices.
+pub struct SudokuGenerator;
+
+impl SudokuGenerator {
+  // `fill_clue_mask` finds digits for exactly the masked positions so
+  // that the resulting puzzle has a unique solution.
+  //
+  // Each attempt draws a random solution grid and keeps the values at
+  // the masked positions. If those clues don't pin down the solution,
+  // we throw the grid away and draw another one. Returns None if no
+  // attempt succeeds within the budget; some masks (for instance, ones
+  // with too few clues) can never support a unique solution.
+  pub fn fill_clue_mask<R: Rng>(
+    mask: &SudokuClueMask,
+    budget: GeneratorBudget,
+    rng: &mut R,
+  ) -> Option<Vec<SudokuChoice>> {
+    SudokuGenerator::fill_clue_mask_observed(mask, budget, rng, |_| {
+      SearchControl::Continue
+    })
+  }
+
+  // `fill_clue_mask_observed` is `fill_clue_mask`, reporting the
+  // events of each search (for a solution grid, then for a second
+  // solution) to `observer` like `SudokuSolver::next_solution_observed`
+  // does. A search the observer stops counts as a failed attempt.
+  pub fn fill_clue_mask_observed<R, O>(
+    mask: &SudokuClueMask,
+    budget: GeneratorBudget,
+    rng: &mut R,
+    mut observer: O,
+  ) -> Option<Vec<SudokuChoice>>
+  where
+    R: Rng,
+    O: FnMut(SolverEvent) -> SearchControl,
+  {
+    for _ in 0..budget.max_attempts {
+      // A random solution for the empty grid always exists, but the
+      // search for one can still run out of nodes.
+      let solution = match SudokuSolver::new(&[])
+        .expect("the empty grid has no clashes")
+        .with_shuffled_value_orders(rng)
+        .with_node_limit(budget.max_nodes_per_attempt)
+        .next_solution_observed(&mut observer)
+        .into_solution()
+      {
+        None => continue,
+        Some(solution) => solution,
+      };
+
+      let given_choices: Vec<_> = solution
+        .into_iter()
+        .filter(|choice| mask.contains(choice.position))
+        .collect();
+
+      // Stop at the second solution; we only care whether the solution
+      // is unique.
+      let mut solver = SudokuSolver::new(&given_choices)
+        .expect("clues from a solution never clash")
+        .with_node_limit(budget.max_nodes_per_attempt);
+      let is_unique = solver
+        .next_solution_observed(&mut observer)
+        .into_solution()
+        .is_some()
+        && matches!(
+          solver.next_solution_observed(&mut observer),
+          SolverResult::NoMoreSolutions
+        );
+      if is_unique {
+        return Some(given_choices);
+      }
+    }
+
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use core::SudokuPosition;
+  use rand::rngs::SmallRng;
+  use rand::SeedableRng;
+  use solver::SolutionCount;
+
+  #[test]
+  fn dense_masks_make_unique_puzzles() {
+    // Every row but the first is given.
+    let positions: Vec<_> = SudokuPosition::all()
+      .into_iter()
+      .filter(|position| position.as_usize_pair().0 > 0)
+      .collect();
+    let mask = SudokuClueMask::from_positions(&positions);
+
+    let mut rng = SmallRng::seed_from_u64(1);
+    let given_choices = SudokuGenerator::fill_clue_mask(
+      &mask,
+      GeneratorBudget::default(),
+      &mut rng,
+    )
+    .unwrap();
+    let given_positions: Vec<_> =
+      given_choices.iter().map(|choice| choice.position).collect();
+    assert_eq!(given_positions, positions);
+    assert_eq!(
+      SudokuSolver::count_solutions(&given_choices, 2, None),
+      SolutionCount::Exactly(1)
+    );
+  }
+
+  #[test]
+  fn empty_masks_never_make_unique_puzzles() {
+    let budget = GeneratorBudget {
+      max_attempts: 5,
+      max_nodes_per_attempt: 100_000,
+    };
+    let mut rng = SmallRng::seed_from_u64(2);
+    assert!(SudokuGenerator::fill_clue_mask(
+      &SudokuClueMask::default(),
+      budget,
+      &mut rng
+    )
+    .is_none());
+  }
+
+  #[test]
+  fn keeps_to_the_budget() {
+    // Too few nodes to draw a solution grid, so every attempt runs out
+    // of nodes while drawing one.
+    let budget = GeneratorBudget {
+      max_attempts: 3,
+      max_nodes_per_attempt: 10,
+    };
+    let mut num_places = 0;
+    let mut rng = SmallRng::seed_from_u64(3);
+    let given_choices = SudokuGenerator::fill_clue_mask_observed(
+      &SudokuClueMask::default(),
+      budget,
+      &mut rng,
+      |event| {
+        if let SolverEvent::Place(_) = event {
+          num_places += 1;
+        }
+        SearchControl::Continue
+      },
+    );
+    assert!(given_choices.is_none());
+    assert_eq!(num_places, 3 * 10);
+  }
+}
@@ -1,5 +1,19 @@
 extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(any(feature = "puzzle_links", test))]
+extern crate serde_json;
 
+pub mod batch;
+pub mod canonical;
 pub mod checker;
 pub mod core;
+pub mod format;
+pub mod game;
+pub mod generator;
+pub mod mutation;
+pub mod sampler;
 pub mod solver;
+#[cfg(test)]
+mod test_util;
+pub mod transform;
@@ -1,25 +1,12 @@
+extern crate rand;
 extern crate sudoku_solving;
 
-use sudoku_solving::{
-  core::{SudokuChoice, SudokuGrid, SudokuPosition, SudokuValue},
-  solver::SudokuSolver,
-};
+mod cli;
 
-fn main() {
-  // Some pre-filled values for the Sudoku grid.
-  let filled_choices = vec![];
-
-  // Try to solve the grid.
-  let solution = SudokuSolver::solve(&filled_choices).unwrap();
+use std::env;
+use std::process;
 
-  // Write the solution choices into a grid, and print it.
-  let mut grid = SudokuGrid::default();
-  for choice in filled_choices {
-    grid.place(choice);
-  }
-  for choice in solution {
-    grid.place(choice);
-  }
-
-  print!("{}", grid);
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  process::exit(cli::run(&args));
 }
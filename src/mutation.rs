@@ -0,0 +1,362 @@
+use super::core::{
+  SudokuCell, SudokuChoice, SudokuGrid, SudokuPosition, SudokuValue,
+};
+use super::solver::{SolverResult, SudokuSolver};
+use rand::{seq::SliceRandom, Rng};
+use std::cmp::Ordering;
+
+// MutationGoal says what the mutation search is climbing toward.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MutationGoal {
+  // Fewer clues; difficulty only breaks ties.
+  FewerClues,
+  // A higher difficulty rating; the number of clues only breaks ties.
+  HigherDifficulty,
+}
+
+// Mutation is one way of perturbing a puzzle.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mutation {
+  // Drop one clue.
+  RemoveClue,
+  // Reveal one more cell of the puzzle's solution.
+  AddClue,
+  // Drop one clue and reveal a different cell of the solution.
+  MoveClue,
+  // Change the value of one clue. This changes the solution too, if
+  // the mutant still has one.
+  ChangeDigit,
+}
+
+impl Mutation {
+  pub fn all() -> Vec<Mutation> {
+    vec![
+      Mutation::RemoveClue,
+      Mutation::AddClue,
+      Mutation::MoveClue,
+      Mutation::ChangeDigit,
+    ]
+  }
+}
+
+// MutationSearchResult is the best puzzle the search found.
+#[derive(Clone, Debug)]
+pub struct MutationSearchResult {
+  pub puzzle: Vec<SudokuChoice>,
+  // The puzzle's rating from `MutationSearch::rate`.
+  pub difficulty: u64,
+  // How many mutants were accepted along the way.
+  pub num_accepted: usize,
+}
+
+// MutationSearch hill-climbs from a starting puzzle. Each step applies
+// a random mutation; mutants without a unique solution are thrown
+// away, as are mutants that are worse for the goal. Mutants that are
+// exactly as good are kept, so the search can wander across plateaus.
+pub struct MutationSearch {
+  goal: MutationGoal,
+  mutations: Vec<Mutation>,
+  max_steps: usize,
+  // `max_nodes_per_check` bounds the solver when checking (and rating)
+  // each mutant. A mutant whose check runs out of nodes is thrown
+  // away.
+  max_nodes_per_check: u64,
+}
+
+impl MutationSearch {
+  pub fn new(goal: MutationGoal) -> MutationSearch {
+    // Adding a clue can never help when we want fewer clues.
+    let mutations = match goal {
+      MutationGoal::FewerClues => vec![
+        Mutation::RemoveClue,
+        Mutation::MoveClue,
+        Mutation::ChangeDigit,
+      ],
+      MutationGoal::HigherDifficulty => Mutation::all(),
+    };
+
+    MutationSearch {
+      goal,
+      mutations,
+      max_steps: 1_000,
+      max_nodes_per_check: 100_000,
+    }
+  }
+
+  // `with_mutations` restricts the search to the given mutations. An
+  // empty list is ignored.
+  pub fn with_mutations(
+    mut self,
+    mutations: &[Mutation],
+  ) -> MutationSearch {
+    if !mutations.is_empty() {
+      self.mutations = mutations.to_vec();
+    }
+    self
+  }
+
+  pub fn with_max_steps(mut self, max_steps: usize) -> MutationSearch {
+    self.max_steps = max_steps;
+    self
+  }
+
+  pub fn with_node_limit(mut self, max_nodes: u64) -> MutationSearch {
+    self.max_nodes_per_check = max_nodes;
+    self
+  }
+
+  // `rate` is the difficulty rating used by the search: the number of
+  // choices our backtracking solver makes to find the solution and
+  // prove that it is the only one. Returns None if the puzzle doesn't
+  // have a unique solution, or if that can't be decided within
+  // `max_nodes`.
+  pub fn rate(puzzle: &[SudokuChoice], max_nodes: u64) -> Option<u64> {
+    rate_and_solve(puzzle, max_nodes).map(|(difficulty, _)| difficulty)
+  }
+
+  // `search` runs the hill-climb from `puzzle`. Returns None if the
+  // starting puzzle doesn't have a unique solution (within the node
+  // limit).
+  pub fn search<R: Rng>(
+    &self,
+    puzzle: &[SudokuChoice],
+    rng: &mut R,
+  ) -> Option<MutationSearchResult> {
+    let (difficulty, solution) =
+      rate_and_solve(puzzle, self.max_nodes_per_check)?;
+    let mut current = Candidate {
+      puzzle: puzzle.to_vec(),
+      difficulty,
+      solution,
+    };
+    let mut best = current.clone();
+    let mut num_accepted = 0;
+
+    for _ in 0..self.max_steps {
+      let mutation = *self
+        .mutations
+        .choose(rng)
+        .expect("mutation list is never empty");
+      let mutant = match mutate(&current, mutation, rng) {
+        None => continue,
+        Some(mutant) => mutant,
+      };
+      let (difficulty, solution) =
+        match rate_and_solve(&mutant, self.max_nodes_per_check) {
+          None => continue,
+          Some(rating) => rating,
+        };
+      let mutant = Candidate {
+        puzzle: mutant,
+        difficulty,
+        solution,
+      };
+
+      if self.compare(&mutant, &current) == Ordering::Less {
+        continue;
+      }
+      current = mutant;
+      num_accepted += 1;
+
+      if self.compare(&current, &best) == Ordering::Greater {
+        best = current.clone();
+      }
+    }
+
+    Some(MutationSearchResult {
+      puzzle: best.puzzle,
+      difficulty: best.difficulty,
+      num_accepted,
+    })
+  }
+
+  // `compare` orders puzzles so that greater is better for the goal.
+  fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+    let by_clues = b.puzzle.len().cmp(&a.puzzle.len());
+    let by_difficulty = a.difficulty.cmp(&b.difficulty);
+    match self.goal {
+      MutationGoal::FewerClues => by_clues.then(by_difficulty),
+      MutationGoal::HigherDifficulty => by_difficulty.then(by_clues),
+    }
+  }
+}
+
+// Candidate is a puzzle with a unique solution, which we keep around to
+// pick the values of added clues.
+#[derive(Clone)]
+struct Candidate {
+  puzzle: Vec<SudokuChoice>,
+  difficulty: u64,
+  solution: SudokuGrid,
+}
+
+// `rate_and_solve` returns the rating of the puzzle along with its
+// solution grid (including the clues).
+fn rate_and_solve(
+  puzzle: &[SudokuChoice],
+  max_nodes: u64,
+) -> Option<(u64, SudokuGrid)> {
+  let mut solver =
+    SudokuSolver::new(puzzle)?.with_node_limit(max_nodes);
+  let solution = solver.next_solution().into_solution()?;
+  match solver.next_solution() {
+    SolverResult::NoMoreSolutions => {}
+    SolverResult::Solved(_) | SolverResult::NodeLimitReached => {
+      return None
+    }
+  }
+
+  let mut grid = SudokuGrid::from_choices(puzzle);
+  for choice in solution {
+    grid.place(choice);
+  }
+
+  Some((solver.num_nodes(), grid))
+}
+
+// `mutate` applies the mutation to the candidate's puzzle. Returns None
+// if the mutation can't be applied (say, removing a clue from an empty
+// puzzle).
+fn mutate<R: Rng>(
+  candidate: &Candidate,
+  mutation: Mutation,
+  rng: &mut R,
+) -> Option<Vec<SudokuChoice>> {
+  let mut puzzle = candidate.puzzle.clone();
+
+  match mutation {
+    Mutation::RemoveClue => {
+      remove_random_clue(&mut puzzle, rng)?;
+    }
+    Mutation::AddClue => {
+      add_random_clue(&mut puzzle, &candidate.solution, rng)?;
+    }
+    Mutation::MoveClue => {
+      // Choose where the new clue goes before removing the old one, so
+      // we never put the clue back where it came from.
+      add_random_clue(&mut puzzle, &candidate.solution, rng)?;
+      let new_clue = puzzle.pop()?;
+      remove_random_clue(&mut puzzle, rng)?;
+      puzzle.push(new_clue);
+    }
+    Mutation::ChangeDigit => {
+      let clue = puzzle.choose_mut(rng)?;
+      let other_values: Vec<_> = SudokuValue::all()
+        .into_iter()
+        .filter(|&value| value != clue.value)
+        .collect();
+      clue.value = *other_values.choose(rng)?;
+    }
+  }
+
+  Some(puzzle)
+}
+
+fn remove_random_clue<R: Rng>(
+  puzzle: &mut Vec<SudokuChoice>,
+  rng: &mut R,
+) -> Option<SudokuChoice> {
+  if puzzle.is_empty() {
+    return None;
+  }
+
+  let clue_idx = rng.gen_range(0, puzzle.len());
+  Some(puzzle.swap_remove(clue_idx))
+}
+
+fn add_random_clue<R: Rng>(
+  puzzle: &mut Vec<SudokuChoice>,
+  solution: &SudokuGrid,
+  rng: &mut R,
+) -> Option<()> {
+  let empty_positions: Vec<SudokuPosition> = SudokuPosition::all()
+    .into_iter()
+    .filter(|&position| {
+      !puzzle.iter().any(|clue| clue.position == position)
+    })
+    .collect();
+  let &position = empty_positions.choose(rng)?;
+
+  match solution.cell(position) {
+    SudokuCell::Filled(value) => {
+      puzzle.push(SudokuChoice::new(position, value));
+      Some(())
+    }
+    SudokuCell::Empty => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::SmallRng;
+  use rand::SeedableRng;
+  use solver::SolutionCount;
+  use test_util::{puzzle_of, random_solution};
+
+  fn unique_puzzle(rng: &mut SmallRng) -> Vec<SudokuChoice> {
+    puzzle_of(&random_solution(rng)).filled_choices()
+  }
+
+  #[test]
+  fn mutants_keep_a_unique_solution() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let puzzle = unique_puzzle(&mut rng);
+    let result = MutationSearch::new(MutationGoal::FewerClues)
+      .with_max_steps(50)
+      .search(&puzzle, &mut rng)
+      .unwrap();
+    assert!(result.num_accepted > 0);
+    assert!(result.puzzle.len() < puzzle.len());
+    assert_eq!(
+      SudokuSolver::count_solutions(&result.puzzle, 2, None),
+      SolutionCount::Exactly(1)
+    );
+  }
+
+  #[test]
+  fn never_rates_lower_than_the_start() {
+    let mut rng = SmallRng::seed_from_u64(2);
+    let puzzle = unique_puzzle(&mut rng);
+    let start_difficulty =
+      MutationSearch::rate(&puzzle, 100_000).unwrap();
+    let result = MutationSearch::new(MutationGoal::HigherDifficulty)
+      .with_max_steps(50)
+      .search(&puzzle, &mut rng)
+      .unwrap();
+    assert!(result.difficulty >= start_difficulty);
+    assert_eq!(
+      MutationSearch::rate(&result.puzzle, 100_000),
+      Some(result.difficulty)
+    );
+  }
+
+  #[test]
+  fn keeps_to_the_step_limit() {
+    let mut rng = SmallRng::seed_from_u64(3);
+    let puzzle = unique_puzzle(&mut rng);
+
+    let result = MutationSearch::new(MutationGoal::FewerClues)
+      .with_max_steps(0)
+      .search(&puzzle, &mut rng)
+      .unwrap();
+    assert_eq!(result.num_accepted, 0);
+    assert_eq!(result.puzzle.len(), puzzle.len());
+
+    // Each step removes at most one clue.
+    let result = MutationSearch::new(MutationGoal::FewerClues)
+      .with_mutations(&[Mutation::RemoveClue])
+      .with_max_steps(5)
+      .search(&puzzle, &mut rng)
+      .unwrap();
+    assert!(result.num_accepted <= 5);
+    assert!(result.puzzle.len() >= puzzle.len() - 5);
+  }
+
+  #[test]
+  fn refuses_puzzles_without_a_unique_solution() {
+    let mut rng = SmallRng::seed_from_u64(4);
+    let search = MutationSearch::new(MutationGoal::FewerClues);
+    assert!(search.search(&[], &mut rng).is_none());
+  }
+}
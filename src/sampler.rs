@@ -0,0 +1,311 @@
+use super::checker::SudokuGridConflictChecker;
+use super::core::{
+  SudokuCell, SudokuChoice, SudokuGrid, SudokuPosition, SudokuValue,
+};
+use super::solver::{SolutionCount, SudokuSolver};
+use rand::Rng;
+
+// SolutionSampler draws random solutions of a puzzle with many
+// solutions.
+//
+// `SudokuSolver::solve_randomly` is cheap, but its draws are biased:
+// it picks uniformly among the values at each position, so solutions
+// in thinly populated parts of the search tree come up far more often
+// than those in crowded parts. The sampler instead weights each value
+// by the number of solutions it leads to.
+//
+// Counting every solution is hopeless for a nearly empty grid, so
+// counts are capped at `max_count` solutions and `max_nodes` choices.
+// Where a cap is hit we only know a lower bound, and the draw is no
+// longer exactly uniform. Once the remaining solutions can all be
+// counted, the draw among them is exact.
+pub struct SolutionSampler {
+  given_choices: Vec<SudokuChoice>,
+  // `max_count` is the most solutions we count (and enumerate) for any
+  // partial grid.
+  max_count: usize,
+  // `max_nodes` bounds the choices made by each count.
+  max_nodes: Option<u64>,
+}
+
+impl SolutionSampler {
+  // `new` builds a sampler for the grid with the given pre-filled
+  // values. Returns None if the `given_choices` are manifestly
+  // incompatible with each other.
+  pub fn new(
+    given_choices: &[SudokuChoice],
+  ) -> Option<SolutionSampler> {
+    SudokuSolver::new(given_choices)?;
+
+    Some(SolutionSampler {
+      given_choices: given_choices.to_vec(),
+      max_count: 64,
+      max_nodes: Some(10_000),
+    })
+  }
+
+  // `with_count_limit` sets how many solutions we are willing to count
+  // for each partial grid. Higher limits give draws closer to uniform,
+  // but cost more.
+  pub fn with_count_limit(
+    mut self,
+    max_count: usize,
+  ) -> SolutionSampler {
+    self.max_count = max_count.max(1);
+    self
+  }
+
+  // `with_node_limit` sets how many choices each count may make before
+  // it gives up with a lower bound.
+  pub fn with_node_limit(mut self, max_nodes: u64) -> SolutionSampler {
+    self.max_nodes = Some(max_nodes);
+    self
+  }
+
+  // `sample` draws one solution. As with `SudokuSolver::solve`, the
+  // returned choices do not include the given ones. Returns None if
+  // the grid has no solution, or if the sampler walks into a dead end
+  // and the fallback solver then hits the node limit.
+  pub fn sample<R: Rng>(
+    &self,
+    rng: &mut R,
+  ) -> Option<Vec<SudokuChoice>> {
+    let mut choices = self.given_choices.clone();
+    let mut grid = SudokuGrid::from_choices(&choices);
+    let mut grid_checker = SudokuGridConflictChecker::new();
+    for &choice in &choices {
+      // `new` already checked these are compatible.
+      let _ = grid_checker.add_choice(choice);
+    }
+
+    let mut solution_count = self.count(&choices);
+    loop {
+      // Once we know every remaining solution, pick one of them.
+      if let SolutionCount::Exactly(num_solutions) = solution_count {
+        if num_solutions == 0 {
+          break;
+        }
+
+        let mut solver = SudokuSolver::new(&choices)?;
+        let solution_idx = rng.gen_range(0, num_solutions);
+        for _ in 0..solution_idx {
+          solver.next_solution();
+        }
+        let mut solution = solver.next_solution().into_solution()?;
+
+        let mut sampled_choices =
+          choices.split_off(self.given_choices.len());
+        sampled_choices.append(&mut solution);
+        return Some(sampled_choices);
+      }
+
+      // Otherwise, fix the most constrained empty position. Its value
+      // is drawn in proportion to the (lower bound on the) number of
+      // solutions each candidate leads to.
+      let position =
+        match most_constrained_position(&grid, &grid_checker) {
+          None => break,
+          Some(position) => position,
+        };
+      let candidate_counts: Vec<(SudokuValue, SolutionCount)> =
+        grid_checker
+          .candidates(position)
+          .into_iter()
+          .map(|value| {
+            choices.push(SudokuChoice::new(position, value));
+            let count = self.count(&choices);
+            choices.pop();
+            (value, count)
+          })
+          .collect();
+
+      let (value, count) = match weighted_choice(&candidate_counts, rng)
+      {
+        None => break,
+        Some(candidate_count) => candidate_count,
+      };
+
+      let choice = SudokuChoice::new(position, value);
+      if !grid_checker.add_choice(choice).did_add() {
+        break;
+      }
+      choices.push(choice);
+      grid.place(choice);
+      solution_count = count;
+    }
+
+    // We only get here if we walked into a dead end, which can happen
+    // when a count was cut short before finding any solution. Fall back
+    // to the (biased) randomized solver, within the same node limit as
+    // the counts.
+    let mut solver = SudokuSolver::new(&self.given_choices)?
+      .with_shuffled_value_orders(rng);
+    if let Some(max_nodes) = self.max_nodes {
+      solver = solver.with_node_limit(max_nodes);
+    }
+    solver.next_solution().into_solution()
+  }
+
+  fn count(&self, choices: &[SudokuChoice]) -> SolutionCount {
+    SudokuSolver::count_solutions(
+      choices,
+      self.max_count,
+      self.max_nodes,
+    )
+  }
+}
+
+// `most_constrained_position` is the empty position with the fewest
+// candidate values, or None if the grid is full.
+fn most_constrained_position(
+  grid: &SudokuGrid,
+  grid_checker: &SudokuGridConflictChecker,
+) -> Option<SudokuPosition> {
+  SudokuPosition::all()
+    .into_iter()
+    .filter(|&position| grid.cell(position) == SudokuCell::Empty)
+    .min_by_key(|&position| grid_checker.candidates(position).len())
+}
+
+// `weighted_choice` picks a candidate with probability proportional to
+// its solution count. A count that was cut short before finding any
+// solution still gets a small weight, since it may well have some.
+fn weighted_choice<R: Rng>(
+  candidate_counts: &[(SudokuValue, SolutionCount)],
+  rng: &mut R,
+) -> Option<(SudokuValue, SolutionCount)> {
+  let weights: Vec<usize> = candidate_counts
+    .iter()
+    .map(|&(_, count)| match count {
+      SolutionCount::Exactly(num_solutions) => num_solutions,
+      SolutionCount::AtLeast(num_solutions)
+      | SolutionCount::NodeLimitReached(num_solutions) => {
+        num_solutions.max(1)
+      }
+    })
+    .collect();
+
+  let total_weight: usize = weights.iter().sum();
+  if total_weight == 0 {
+    return None;
+  }
+
+  let mut target = rng.gen_range(0, total_weight);
+  for (&candidate_count, &weight) in
+    candidate_counts.iter().zip(&weights)
+  {
+    if target < weight {
+      return Some(candidate_count);
+    }
+    target -= weight;
+  }
+
+  unreachable!("target is below the total weight")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::SmallRng;
+  use rand::SeedableRng;
+  use std::collections::HashMap;
+  use test_util::random_solution;
+
+  // `puzzle_with_solutions` blanks a few cells of a random solution,
+  // leaving a puzzle whose solutions can all be counted.
+  fn puzzle_with_solutions(rng: &mut SmallRng) -> Vec<SudokuChoice> {
+    let mut choices = random_solution(rng).filled_choices();
+    choices.truncate(200);
+    choices
+  }
+
+  #[test]
+  fn samples_solve_the_puzzle() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let given_choices = puzzle_with_solutions(&mut rng);
+    let sampler = SolutionSampler::new(&given_choices).unwrap();
+    for _ in 0..5 {
+      let mut choices = given_choices.clone();
+      choices.extend(sampler.sample(&mut rng).unwrap());
+      assert_eq!(choices.len(), 256);
+      assert!(SudokuSolver::new(&choices).is_some());
+    }
+  }
+
+  #[test]
+  fn samples_nothing_for_an_unsolvable_puzzle() {
+    // The top row holds 1 through 14, and 15 sits just below in the
+    // same box, so both empty cells of the top row need a 16.
+    let mut given_choices: Vec<_> = (0..14)
+      .map(|col_idx| {
+        SudokuChoice::new(
+          SudokuPosition::new(0, col_idx),
+          SudokuValue::new(col_idx + 1),
+        )
+      })
+      .collect();
+    given_choices.push(SudokuChoice::new(
+      SudokuPosition::new(1, 14),
+      SudokuValue::new(15),
+    ));
+
+    let mut rng = SmallRng::seed_from_u64(2);
+    let sampler = SolutionSampler::new(&given_choices).unwrap();
+    assert!(sampler.sample(&mut rng).is_none());
+  }
+
+  #[test]
+  fn gives_up_within_the_node_limit() {
+    // With a single node per count every count is cut short, so the
+    // sampler relies on the fallback, which gives up just as quickly.
+    let mut rng = SmallRng::seed_from_u64(3);
+    let sampler = SolutionSampler::new(&[]).unwrap().with_node_limit(1);
+    for _ in 0..5 {
+      assert!(sampler.sample(&mut rng).is_none());
+    }
+  }
+
+  #[test]
+  fn draws_are_less_biased_than_solving_randomly() {
+    // This puzzle has six solutions, and solving it randomly finds some
+    // of them much more often than others.
+    let mut rng = SmallRng::seed_from_u64(2);
+    let given_choices: Vec<_> = random_solution(&mut rng)
+      .filled_choices()
+      .into_iter()
+      .filter(|choice| {
+        let (row_idx, col_idx) = choice.position.as_usize_pair();
+        row_idx > 2 || (row_idx == 2 && col_idx >= 8)
+      })
+      .collect();
+    assert_eq!(
+      SudokuSolver::count_solutions(&given_choices, 100, None),
+      SolutionCount::Exactly(6)
+    );
+
+    let sampler = SolutionSampler::new(&given_choices).unwrap();
+    let mut sampled_draws = HashMap::new();
+    let mut solved_draws = HashMap::new();
+    for _ in 0..300 {
+      let sampled = sampler.sample(&mut rng).unwrap();
+      *sampled_draws
+        .entry(SudokuGrid::from_choices(&sampled))
+        .or_insert(0) += 1;
+      let solved =
+        SudokuSolver::solve_randomly(&given_choices, &mut rng).unwrap();
+      *solved_draws
+        .entry(SudokuGrid::from_choices(&solved))
+        .or_insert(0) += 1;
+    }
+
+    assert_eq!(sampled_draws.len(), 6);
+    assert_eq!(solved_draws.len(), 6);
+    assert!(spread(&sampled_draws) < spread(&solved_draws));
+  }
+
+  // `spread` is how many more times the most drawn solution was drawn
+  // than the least drawn one.
+  fn spread(draws: &HashMap<SudokuGrid, usize>) -> usize {
+    draws.values().max().unwrap() - draws.values().min().unwrap()
+  }
+}
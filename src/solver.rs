@@ -1,5 +1,8 @@
 use super::checker::SudokuGridConflictChecker;
 use super::core::{SudokuChoice, SudokuPosition, SudokuValue};
+use rand::{seq::SliceRandom, Rng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 // SudokuSolver implements a backtracking search to attempt to solve the
 // Sudoku puzzle.
@@ -7,23 +10,62 @@ pub struct SudokuSolver {
   // `choices` is a vector of the solver's choices for the grid. Note: it
   // does *not* contain the pre-filled values chosen for us.
   choices: Vec<SudokuChoice>,
+  // `choice_value_idxs[i]` is the index into `value_orders[i]` of the
+  // value we chose in `choices[i]`. We need it to know which value to
+  // try next when we backtrack.
+  choice_value_idxs: Vec<usize>,
   // `fill_order` is the order in which we will try to fill values.
   fill_order: Vec<SudokuPosition>,
+  // `value_orders[i]` is the order in which we try values at position
+  // `fill_order[i]`. Normally this is just 1 through 9, but it may be
+  // shuffled to find a random solution.
+  value_orders: Vec<Vec<SudokuValue>>,
   // `grid_checker` keeps tracks of the conflicts your choices (and the
   // pre-filled values), impose.
   grid_checker: SudokuGridConflictChecker,
+  // `max_nodes` bounds the number of choices the search may make
+  // before giving up.
+  max_nodes: Option<u64>,
+  // `num_nodes` is the number of choices the search has made so far.
+  num_nodes: u64,
+  // `num_backtracks` is the number of choices the search has undone.
+  num_backtracks: u64,
+  // `needs_backtrack` is set after we hand back a solution. Before we
+  // can find the next solution we must undo the last choice.
+  needs_backtrack: bool,
+  // `is_exhausted` is set once the search has tried every value
+  // everywhere. It would start over from scratch otherwise.
+  is_exhausted: bool,
+  // `is_stopped` is set when an observer asks us to stop. From then on
+  // we act as if we hit the node limit.
+  is_stopped: bool,
 }
 
 impl SudokuSolver {
-  // `solve` builds the SudokuSolver and executes the backtracking
-  // search. `given_choices` are the values that are prefilled in the
-  // grid.
-  //
-  // Returns an `Option<Vec<SudokuChoice>>` because the grid may not be
-  // satisfiable.
-  pub fn solve(
+  // `new` builds a SudokuSolver for the grid with the given pre-filled
+  // values. Returns None if the `given_choices` are manifestly
+  // incompatible with each other.
+  pub fn new(given_choices: &[SudokuChoice]) -> Option<SudokuSolver> {
+    // This builds the `grid_checker`, and adds the constraints imposed
+    // by the pre-filled values.
+    let mut grid_checker = SudokuGridConflictChecker::new();
+    for &given_choice in given_choices {
+      if !grid_checker.add_choice(given_choice).did_add() {
+        return None;
+      }
+    }
+
+    Some(SudokuSolver::from_checker(given_choices, grid_checker))
+  }
+
+  // `from_checker` builds a SudokuSolver from a `grid_checker` which
+  // already holds the `given_choices`, and perhaps candidates a player
+  // eliminated (see `PencilMarkGrid::to_checker`). The solver never
+  // tries a value the checker has ruled out.
+  pub fn from_checker(
     given_choices: &[SudokuChoice],
-  ) -> Option<Vec<SudokuChoice>> {
+    grid_checker: SudokuGridConflictChecker,
+  ) -> SudokuSolver {
     // `fill_order` is the left-to-right, top-to-bottom order of all
     // positions. We filter away the pre-filled values since we don't
     // get to choose those.
@@ -44,118 +86,303 @@ impl SudokuSolver {
           .any(|given_choice| given_choice.position == *pos)
       }).collect();
 
-    // This builds the `grid_checker`, and adds the constraints imposed
-    // by the pre-filled values. If the grid_checker right away knows
-    // the grid is unsatisfiable, we panic.
-    let mut grid_checker = SudokuGridConflictChecker::new();
-    for &given_choice in given_choices {
-      if !grid_checker.add_choice(given_choice).did_add() {
-        panic!("given_choices are manifestly incompatable.");
-      }
-    }
+    // By default we try values in increasing order at every position.
+    let value_orders =
+      fill_order.iter().map(|_| SudokuValue::all()).collect();
 
-    let solver = SudokuSolver {
+    SudokuSolver {
       choices: vec![],
-      grid_checker,
+      choice_value_idxs: vec![],
       fill_order,
+      value_orders,
+      grid_checker,
+      max_nodes: None,
+      num_nodes: 0,
+      num_backtracks: 0,
+      needs_backtrack: false,
+      is_exhausted: false,
+      is_stopped: false,
+    }
+  }
+
+  // `solve` builds the SudokuSolver and executes the backtracking
+  // search. `given_choices` are the values that are prefilled in the
+  // grid.
+  //
+  // Returns an `Option<Vec<SudokuChoice>>` because the grid may not be
+  // satisfiable. If the grid_checker right away knows the grid is
+  // unsatisfiable, we panic.
+  pub fn solve(
+    given_choices: &[SudokuChoice],
+  ) -> Option<Vec<SudokuChoice>> {
+    let mut solver = match SudokuSolver::new(given_choices) {
+      None => panic!("given_choices are manifestly incompatable."),
+      Some(solver) => solver,
+    };
+
+    solver.next_solution().into_solution()
+  }
+
+  // `solve_randomly` is like `solve`, except that values are tried in a
+  // random order at each position. Thus repeated calls will tend to
+  // find different solutions when there are many.
+  //
+  // Unlike `solve`, manifestly incompatible `given_choices` are simply
+  // reported as unsatisfiable.
+  pub fn solve_randomly<R: Rng>(
+    given_choices: &[SudokuChoice],
+    rng: &mut R,
+  ) -> Option<Vec<SudokuChoice>> {
+    let mut solver =
+      SudokuSolver::new(given_choices)?.with_shuffled_value_orders(rng);
+
+    solver.next_solution().into_solution()
+  }
+
+  // `count_solutions` counts the solutions of the grid, stopping once
+  // `max_solutions` have been found. The search is abandoned once it
+  // has made `max_nodes` choices (if given). Either way the count is
+  // then only a lower bound, and the SolutionCount says which limit
+  // was hit.
+  pub fn count_solutions(
+    given_choices: &[SudokuChoice],
+    max_solutions: usize,
+    max_nodes: Option<u64>,
+  ) -> SolutionCount {
+    let mut solver = match SudokuSolver::new(given_choices) {
+      None => return SolutionCount::Exactly(0),
+      Some(solver) => solver,
     };
+    if let Some(max_nodes) = max_nodes {
+      solver = solver.with_node_limit(max_nodes);
+    }
+
+    let mut num_solutions = 0;
+    while num_solutions < max_solutions {
+      match solver.next_solution() {
+        SolverResult::Solved(_) => num_solutions += 1,
+        SolverResult::NoMoreSolutions => {
+          return SolutionCount::Exactly(num_solutions)
+        }
+        SolverResult::NodeLimitReached => {
+          return SolutionCount::NodeLimitReached(num_solutions)
+        }
+      }
+    }
+
+    SolutionCount::AtLeast(num_solutions)
+  }
+
+  // `with_shuffled_value_orders` makes the solver try values in a
+  // random order at each position, rather than 1 through 9.
+  pub fn with_shuffled_value_orders<R: Rng>(
+    mut self,
+    rng: &mut R,
+  ) -> SudokuSolver {
+    for value_order in &mut self.value_orders {
+      value_order.shuffle(rng);
+    }
+
+    self
+  }
+
+  // `with_node_limit` makes the solver give up once it has made
+  // `max_nodes` choices in total.
+  pub fn with_node_limit(mut self, max_nodes: u64) -> SudokuSolver {
+    self.max_nodes = Some(max_nodes);
+    self
+  }
 
-    // Execute the solving routine.
-    solver.run()
+  // `num_nodes` is the number of choices the search has made so far,
+  // including the ones that were later backtracked.
+  pub fn num_nodes(&self) -> u64 {
+    self.num_nodes
+  }
+
+  // `stats` summarizes the work the search has done so far.
+  pub fn stats(&self) -> SolverStats {
+    SolverStats {
+      num_nodes: self.num_nodes,
+      num_backtracks: self.num_backtracks,
+    }
+  }
+
+  // `next_solution` resumes the backtracking search and returns the
+  // next solution. Each solution is returned only once, so calling this
+  // repeatedly enumerates all the solutions of the grid.
+  pub fn next_solution(&mut self) -> SolverResult {
+    self.next_solution_observed(|_| SearchControl::Continue)
+  }
+
+  // `next_solution_observed` is like `next_solution`, but tells
+  // `observer` about every value the search places or takes back, in
+  // order, as it goes. This is slower, and is meant for watching the
+  // search work.
+  //
+  // The observer may stop the search. The search then ends as if it had
+  // hit its node limit, and can't be resumed. This holds even if the
+  // event the observer stopped on completed a solution.
+  pub fn next_solution_observed<O>(
+    &mut self,
+    mut observer: O,
+  ) -> SolverResult
+  where
+    O: FnMut(SolverEvent) -> SearchControl,
+  {
+    if self.is_exhausted {
+      return SolverResult::NoMoreSolutions;
+    }
+
+    // If we previously found a solution, we need to undo the last
+    // choice to look for another one.
+    if self.needs_backtrack {
+      self.needs_backtrack = false;
+
+      match self.backtrack(&mut observer) {
+        SolverProgressStatus::SolverMadeProgress => {}
+        SolverProgressStatus::SolverCouldNotMakeProgress => {
+          self.is_exhausted = true;
+          return SolverResult::NoMoreSolutions;
+        }
+        SolverProgressStatus::SolverHitNodeLimit => {
+          return SolverResult::NodeLimitReached
+        }
+      }
+    }
+
+    match self.run(&mut observer) {
+      SolverProgressStatus::SolverMadeProgress => {
+        self.needs_backtrack = true;
+        SolverResult::Solved(self.choices.clone())
+      }
+      SolverProgressStatus::SolverCouldNotMakeProgress => {
+        self.is_exhausted = true;
+        SolverResult::NoMoreSolutions
+      }
+      SolverProgressStatus::SolverHitNodeLimit => {
+        SolverResult::NodeLimitReached
+      }
+    }
   }
 
-  // `run` executes the backtracking search.
-  fn run(mut self) -> Option<Vec<SudokuChoice>> {
+  // `run` executes the backtracking search until every cell is filled.
+  fn run<O: FnMut(SolverEvent) -> SearchControl>(
+    &mut self,
+    observer: &mut O,
+  ) -> SolverProgressStatus {
     loop {
+      // A stop is honored even when the event that asked for it
+      // completed the grid.
+      if self.is_stopped {
+        return SolverProgressStatus::SolverHitNodeLimit;
+      }
       // If we've filled all the cells, we have a solution!
       if self.is_complete() {
-        return Some(self.choices);
+        return SolverProgressStatus::SolverMadeProgress;
+      }
+      // Moving forward uses up nodes too, not just backtracking.
+      if self.is_over_node_limit() {
+        return SolverProgressStatus::SolverHitNodeLimit;
       }
 
       // Try to fill next position, extending our previous choices.
-      let next_position_to_fill = self.next_position_to_fill();
-      if self
-        .try_to_extend_solution(
-          SudokuValue::first(),
-          next_position_to_fill,
-        ).did_make_progress()
-      {
+      let next_fill_idx = self.choices.len();
+      match self.try_to_extend_solution(next_fill_idx, 0, observer) {
         // If we make progress, great! Let's loop around and try to keep
         // extending!
-        continue;
+        SolverProgressStatus::SolverMadeProgress => continue,
+        SolverProgressStatus::SolverHitNodeLimit => {
+          return SolverProgressStatus::SolverHitNodeLimit
+        }
+        SolverProgressStatus::SolverCouldNotMakeProgress => {}
       }
 
       // Else, there is no valid choice that remains for the
-      // `next_position_to_fill`. We must backtrack.
+      // `next_fill_idx`. We must backtrack.
       //
       // But backtracking may also fail, in which case we must report
-      // that the Sudoku grid has no solution :-(
-      if !self.backtrack().did_make_progress() {
-        return None;
+      // that the Sudoku grid has no (more) solutions :-(
+      match self.backtrack(observer) {
+        SolverProgressStatus::SolverMadeProgress => {}
+        status => return status,
       }
     }
   }
 
   // `try_to_extend_solution` tries to extend the solution at the
-  // specified position. `start_value` tells the initial value to try;
-  // this lets us not retry previously attempted values.
-  fn try_to_extend_solution(
+  // position `fill_order[fill_idx]`. `start_value_idx` tells the index
+  // in `value_orders[fill_idx]` of the initial value to try; this lets
+  // us not retry previously attempted values.
+  fn try_to_extend_solution<O: FnMut(SolverEvent) -> SearchControl>(
     &mut self,
-    start_value: SudokuValue,
-    position: SudokuPosition,
+    fill_idx: usize,
+    start_value_idx: usize,
+    observer: &mut O,
   ) -> SolverProgressStatus {
-    let mut value = start_value;
-    loop {
+    let position = self.fill_order[fill_idx];
+
+    for value_idx in start_value_idx..self.value_orders[fill_idx].len()
+    {
+      let value = self.value_orders[fill_idx][value_idx];
       let choice = SudokuChoice::new(position, value);
 
       // Check whether choice is okay with existing conflicts. If so,
       // save it to our choices and return.
       if self.grid_checker.add_choice(choice).did_add() {
         self.choices.push(choice);
+        self.choice_value_idxs.push(value_idx);
+        self.num_nodes += 1;
+        self.notify(observer, SolverEvent::Place(choice));
         return SolverProgressStatus::SolverMadeProgress;
       }
 
       // If `value` didn't work, then let's try the next value.
-      value = match value.next() {
-        // If we exhausted all possible values at this position let the
-        // caller know.
-        None => return SolverProgressStatus::SolverCouldNotMakeProgress,
-        Some(new_value) => new_value,
-      };
     }
+
+    // We exhausted all possible values at this position; let the caller
+    // know.
+    SolverProgressStatus::SolverCouldNotMakeProgress
   }
 
   // `backtrack` undoes prior choices. We backtrack when we hit a
   // dead-end and can't advance otherwise.
-  fn backtrack(&mut self) -> SolverProgressStatus {
+  fn backtrack<O: FnMut(SolverEvent) -> SearchControl>(
+    &mut self,
+    observer: &mut O,
+  ) -> SolverProgressStatus {
     loop {
+      // Each value we try again counts against the node limit, so check
+      // it before we go any further.
+      if self.is_over_node_limit() {
+        return SolverProgressStatus::SolverHitNodeLimit;
+      }
+
       // If the choices stack is empty; it's game over. We exhausted all
       // possibilities.
       let prev_choice = match self.choices.pop() {
-        None => return SolverProgressStatus::SolverCouldNotMakeProgress,
+        None => {
+          return SolverProgressStatus::SolverCouldNotMakeProgress
+        }
         Some(prev_choice) => prev_choice,
       };
+      let prev_value_idx = self.choice_value_idxs.pop().unwrap();
+      self.num_backtracks += 1;
 
       // We're "undoing" this choice, so we must clear the conflicts we
       // recorded for it.
       self.grid_checker.remove_choice(prev_choice);
+      self.notify(observer, SolverEvent::Backtrack(prev_choice));
 
-      let next_value_to_try = match prev_choice.value.next() {
-        // If we've exhausted possible values for this position, we must
-        // keep on backtracking.
-        None => continue,
-        Some(next_value_to_try) => next_value_to_try,
-      };
-
-      // See if there is another value that works at this position.
+      // See if there is another value that works at this position. If
+      // we've exhausted possible values for this position, we must keep
+      // on unwinding the choices stack.
+      let fill_idx = self.choices.len();
       if self
-        .try_to_extend_solution(next_value_to_try, prev_choice.position)
+        .try_to_extend_solution(fill_idx, prev_value_idx + 1, observer)
         .did_make_progress()
       {
         return SolverProgressStatus::SolverMadeProgress;
       }
-
-      // If not, we keep unwinding the choices stack.
     }
   }
 
@@ -163,34 +390,324 @@ impl SudokuSolver {
     self.choices.len() == self.fill_order.len()
   }
 
-  fn next_position_to_fill(&self) -> SudokuPosition {
-    self.fill_order[self.choices.len()]
+  fn notify<O: FnMut(SolverEvent) -> SearchControl>(
+    &mut self,
+    observer: &mut O,
+    event: SolverEvent,
+  ) {
+    if let SearchControl::Stop = observer(event) {
+      self.is_stopped = true;
+    }
+  }
+
+  fn is_over_node_limit(&self) -> bool {
+    if self.is_stopped {
+      return true;
+    }
+    match self.max_nodes {
+      None => false,
+      Some(max_nodes) => self.num_nodes >= max_nodes,
+    }
+  }
+}
+
+// SolverResult is what the caller of `next_solution` gets back. The
+// search may find a solution, exhaust the search space, or run out of
+// its node budget before doing either.
+//
+// In JSON this is `{"status": "solved", "solution": [choice, ...]}`,
+// `{"status": "no_more_solutions"}` or
+// `{"status": "node_limit_reached"}`.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Serialize, Deserialize),
+  serde(tag = "status", content = "solution", rename_all = "snake_case")
+)]
+pub enum SolverResult {
+  Solved(Vec<SudokuChoice>),
+  NoMoreSolutions,
+  NodeLimitReached,
+}
+
+impl SolverResult {
+  pub fn into_solution(self) -> Option<Vec<SudokuChoice>> {
+    match self {
+      SolverResult::Solved(choices) => Some(choices),
+      SolverResult::NoMoreSolutions
+      | SolverResult::NodeLimitReached => None,
+    }
+  }
+}
+
+// SolverEvent is a step of the search, as told to the observer of
+// `next_solution_observed`.
+#[derive(Clone, Copy, Debug)]
+pub enum SolverEvent {
+  // The search tentatively placed a value.
+  Place(SudokuChoice),
+  // The search took back a value it had placed.
+  Backtrack(SudokuChoice),
+}
+
+// SearchControl is the observer's answer to each SolverEvent.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SearchControl {
+  Continue,
+  Stop,
+}
+
+// SolutionCount is the result of `count_solutions`. When the search is
+// cut short (because we hit `max_solutions` or `max_nodes`), we only
+// know a lower bound on the number of solutions.
+//
+// In JSON this is `{"exactly": 1}`, `{"at_least": 2}` or
+// `{"node_limit_reached": 0}`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Serialize, Deserialize),
+  serde(rename_all = "snake_case")
+)]
+pub enum SolutionCount {
+  Exactly(usize),
+  // Counting stopped at `max_solutions`.
+  AtLeast(usize),
+  // The search ran out of nodes after finding this many solutions.
+  NodeLimitReached(usize),
+}
+
+impl SolutionCount {
+  // `is_unique` is true only when we know that there is exactly one
+  // solution.
+  pub fn is_unique(self) -> bool {
+    self == SolutionCount::Exactly(1)
+  }
+
+  // `num_solutions` is the number of solutions found, which is a lower
+  // bound unless the count is exact.
+  pub fn num_solutions(self) -> usize {
+    match self {
+      SolutionCount::Exactly(num_solutions)
+      | SolutionCount::AtLeast(num_solutions)
+      | SolutionCount::NodeLimitReached(num_solutions) => num_solutions,
+    }
   }
 }
 
+// SolverStats counts the work done by a search. Both counts only ever
+// grow, including across calls to `next_solution`.
+//
+// In JSON this is `{"num_nodes": 1234, "num_backtracks": 1200}`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SolverStats {
+  // The number of choices made, including ones later undone.
+  pub num_nodes: u64,
+  // The number of choices undone.
+  pub num_backtracks: u64,
+}
+
 // Methods may return `SolverProgressStatus` so that caller cannot
 // ignore whether the method was able to make progress in making new
 // SudokuChoices.
 #[must_use]
 #[derive(Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
 enum SolverProgressStatus {
   SolverCouldNotMakeProgress,
+  SolverHitNodeLimit,
   SolverMadeProgress,
 }
 
 impl SolverProgressStatus {
   pub fn did_make_progress(self) -> bool {
     match self {
-      SolverProgressStatus::SolverCouldNotMakeProgress => false,
+      SolverProgressStatus::SolverCouldNotMakeProgress
+      | SolverProgressStatus::SolverHitNodeLimit => false,
       SolverProgressStatus::SolverMadeProgress => true,
     }
   }
 }
 
-// use rand::{self, prelude::*};
-// fn shuffled_sudoku_positions() -> Vec<SudokuPosition> {
-//   let mut rng = rand::thread_rng();
-//   let mut sudoku_positions = SudokuPosition::all();
-//   // sudoku_positions.shuffle(&mut rng);
-//   sudoku_positions
-// }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use test_util::seeded_solution;
+
+  fn random_solution() -> Vec<SudokuChoice> {
+    seeded_solution(1).filled_choices()
+  }
+
+  // `without_rows` blanks the rows of the solution below `num_rows`.
+  fn without_rows(num_rows: usize) -> Vec<SudokuChoice> {
+    random_solution()
+      .into_iter()
+      .filter(|choice| choice.position.as_usize_pair().0 >= num_rows)
+      .collect()
+  }
+
+  #[test]
+  fn next_solution_enumerates_every_solution_once() {
+    let given_choices = without_rows(3);
+    let mut solver = SudokuSolver::new(&given_choices).unwrap();
+    let mut solutions = vec![];
+    loop {
+      match solver.next_solution() {
+        SolverResult::Solved(choices) => solutions.push(
+          choices
+            .iter()
+            .map(|choice| (choice.position, choice.value))
+            .collect::<Vec<_>>(),
+        ),
+        SolverResult::NoMoreSolutions => break,
+        SolverResult::NodeLimitReached => {
+          panic!("no node limit was set")
+        }
+      }
+    }
+    // Once exhausted, the search stays exhausted.
+    assert!(matches!(
+      solver.next_solution(),
+      SolverResult::NoMoreSolutions
+    ));
+
+    assert!(solutions.len() > 1);
+    let mut distinct_solutions = solutions.clone();
+    distinct_solutions.sort_by_key(|solution| {
+      solution
+        .iter()
+        .map(|&(_, value)| value.as_u8_value())
+        .collect::<Vec<_>>()
+    });
+    distinct_solutions.dedup();
+    assert_eq!(distinct_solutions.len(), solutions.len());
+    assert_eq!(
+      SudokuSolver::count_solutions(&given_choices, usize::MAX, None),
+      SolutionCount::Exactly(solutions.len())
+    );
+  }
+
+  #[test]
+  fn gives_up_at_the_node_limit() {
+    let mut solver =
+      SudokuSolver::new(&[]).unwrap().with_node_limit(10);
+    assert!(matches!(
+      solver.next_solution(),
+      SolverResult::NodeLimitReached
+    ));
+    assert_eq!(solver.num_nodes(), 10);
+    assert_eq!(
+      SudokuSolver::count_solutions(&[], 5, Some(10)),
+      SolutionCount::NodeLimitReached(0)
+    );
+  }
+
+  #[test]
+  fn events_undo_the_latest_placement_first() {
+    let mut solver = SudokuSolver::new(&without_rows(4)).unwrap();
+    let mut placed: Vec<SudokuChoice> = vec![];
+    let result = solver.next_solution_observed(|event| {
+      match event {
+        SolverEvent::Place(choice) => placed.push(choice),
+        SolverEvent::Backtrack(choice) => {
+          let last = placed.pop().unwrap();
+          assert_eq!(
+            (last.position, last.value),
+            (choice.position, choice.value)
+          );
+        }
+      }
+      SearchControl::Continue
+    });
+
+    // What is left placed is the solution, in the order of the search.
+    let solution = result.into_solution().unwrap();
+    assert_eq!(placed.len(), solution.len());
+    for (choice, placed_choice) in solution.iter().zip(&placed) {
+      assert_eq!(choice.position, placed_choice.position);
+      assert_eq!(choice.value, placed_choice.value);
+    }
+    assert!(solver.stats().num_backtracks > 0);
+  }
+
+  #[test]
+  fn stopping_on_the_last_placement_yields_no_solution() {
+    // A single empty cell: its placement completes the grid.
+    let mut given_choices = random_solution();
+    given_choices.pop();
+    let mut solver = SudokuSolver::new(&given_choices).unwrap();
+    let result =
+      solver.next_solution_observed(|_| SearchControl::Stop);
+    assert!(matches!(result, SolverResult::NodeLimitReached));
+    assert!(matches!(
+      solver.next_solution(),
+      SolverResult::NodeLimitReached
+    ));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn results_are_tagged_by_status_in_json() {
+    use serde_json::{self, json};
+
+    let choice = random_solution()[0];
+    let solved = SolverResult::Solved(vec![choice]);
+    let (row, col) = choice.position.as_usize_pair();
+    assert_eq!(
+      serde_json::to_value(&solved).unwrap(),
+      json!({
+        "status": "solved",
+        "solution": [{
+          "position": { "row": row, "col": col },
+          "value": choice.value.as_u8_value(),
+        }],
+      })
+    );
+    assert_eq!(
+      serde_json::to_value(SolverResult::NoMoreSolutions).unwrap(),
+      json!({ "status": "no_more_solutions" })
+    );
+    assert_eq!(
+      serde_json::to_value(SolverResult::NodeLimitReached).unwrap(),
+      json!({ "status": "node_limit_reached" })
+    );
+
+    let parsed: SolverResult =
+      serde_json::from_value(serde_json::to_value(&solved).unwrap())
+        .unwrap();
+    let parsed_choice = parsed.into_solution().unwrap()[0];
+    assert_eq!(parsed_choice.position, choice.position);
+    assert_eq!(parsed_choice.value, choice.value);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn counts_and_stats_have_fixed_json_shapes() {
+    use serde_json::{self, json};
+
+    let counts = [
+      (SolutionCount::Exactly(1), json!({ "exactly": 1 })),
+      (SolutionCount::AtLeast(2), json!({ "at_least": 2 })),
+      (
+        SolutionCount::NodeLimitReached(0),
+        json!({ "node_limit_reached": 0 }),
+      ),
+    ];
+    for (count, expected) in &counts {
+      assert_eq!(&serde_json::to_value(count).unwrap(), expected);
+      let parsed: SolutionCount =
+        serde_json::from_value(expected.clone()).unwrap();
+      assert_eq!(parsed, *count);
+    }
+
+    let stats = SolverStats {
+      num_nodes: 12,
+      num_backtracks: 3,
+    };
+    assert_eq!(
+      serde_json::to_value(stats).unwrap(),
+      json!({ "num_nodes": 12, "num_backtracks": 3 })
+    );
+  }
+}
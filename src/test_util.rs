@@ -0,0 +1,26 @@
+// Fixtures shared by the unit tests.
+use core::{SudokuGrid, SudokuPosition, SUDOKU_DIM_USIZE};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use solver::SudokuSolver;
+
+pub fn random_solution(rng: &mut SmallRng) -> SudokuGrid {
+  let choices = SudokuSolver::solve_randomly(&[], rng).unwrap();
+  SudokuGrid::from_choices(&choices)
+}
+
+// `seeded_solution` is the solution a generator seeded with `seed`
+// draws first, for tests that need only one or two.
+pub fn seeded_solution(seed: u64) -> SudokuGrid {
+  random_solution(&mut SmallRng::seed_from_u64(seed))
+}
+
+// `puzzle_of` blanks the top row of a solution, which leaves a single
+// way to fill it back in.
+pub fn puzzle_of(solution: &SudokuGrid) -> SudokuGrid {
+  let mut puzzle = solution.clone();
+  for position in &SudokuPosition::all()[..SUDOKU_DIM_USIZE] {
+    puzzle.clear(*position);
+  }
+  puzzle
+}
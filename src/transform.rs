@@ -0,0 +1,568 @@
+use super::core::{
+  SudokuChoice, SudokuGrid, SudokuPosition, SudokuValue,
+  SUDOKU_DIM_SQRT_USIZE, SUDOKU_DIM_USIZE,
+};
+use rand::{seq::SliceRandom, Rng};
+
+// A SudokuTransform turns a Sudoku grid into "the same" grid in
+// disguise. It optionally transposes the grid, then reorders the rows
+// and the columns, and finally relabels the values.
+//
+// Only row orders which keep each band of rows together (and column
+// orders which keep each stack of columns together) are allowed,
+// otherwise the boxes would get scrambled.
+//
+// Transforms can be composed with `then` and undone with `inverse`.
+// Applying the same transform to a puzzle and to its solution gives a
+// disguised puzzle together with its solution.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SudokuTransform {
+  // Whether the grid is transposed before the rows and columns are
+  // reordered.
+  transpose: bool,
+  // Row `i` of the result is row `row_order[i]` of the (possibly
+  // transposed) original grid.
+  row_order: [u8; SUDOKU_DIM_USIZE],
+  // Column `j` of the result is column `col_order[j]` of the (possibly
+  // transposed) original grid.
+  col_order: [u8; SUDOKU_DIM_USIZE],
+  // Value `v` of the original grid is written as
+  // `relabeling[v.as_usize_idx()]` in the result.
+  relabeling: [SudokuValue; SUDOKU_DIM_USIZE],
+}
+
+impl SudokuTransform {
+  // `identity` leaves every grid unchanged.
+  pub fn identity() -> SudokuTransform {
+    let order = identity_order();
+
+    let mut relabeling = [SudokuValue::first(); SUDOKU_DIM_USIZE];
+    for (entry, value) in relabeling.iter_mut().zip(SudokuValue::all())
+    {
+      *entry = value;
+    }
+
+    SudokuTransform {
+      transpose: false,
+      row_order: order,
+      col_order: order,
+      relabeling,
+    }
+  }
+
+  // `from_parts` assembles a SudokuTransform. The caller is trusted to
+  // pass orders that keep bands and stacks together, and a relabeling
+  // that is a permutation of the values.
+  pub(crate) fn from_parts(
+    transpose: bool,
+    row_order: [u8; SUDOKU_DIM_USIZE],
+    col_order: [u8; SUDOKU_DIM_USIZE],
+    relabeling: [SudokuValue; SUDOKU_DIM_USIZE],
+  ) -> SudokuTransform {
+    SudokuTransform {
+      transpose,
+      row_order,
+      col_order,
+      relabeling,
+    }
+  }
+
+  // `new` builds a SudokuTransform from its parts, checking that they
+  // make a valid transform. Returns None if the row order splits up a
+  // band, the column order splits up a stack, or the relabeling is not
+  // a permutation of the values.
+  pub fn new(
+    transpose: bool,
+    row_order: [u8; SUDOKU_DIM_USIZE],
+    col_order: [u8; SUDOKU_DIM_USIZE],
+    relabeling: [SudokuValue; SUDOKU_DIM_USIZE],
+  ) -> Option<SudokuTransform> {
+    let relabeling_idxs: Vec<_> = relabeling
+      .iter()
+      .map(|value| value.as_usize_idx())
+      .collect();
+    if !is_valid_order(&row_order)
+      || !is_valid_order(&col_order)
+      || !is_permutation(&relabeling_idxs)
+    {
+      return None;
+    }
+
+    Some(SudokuTransform::from_parts(
+      transpose, row_order, col_order, relabeling,
+    ))
+  }
+
+  // `transpose` swaps rows for columns, reflecting the grid about its
+  // main diagonal.
+  pub fn transpose() -> SudokuTransform {
+    SudokuTransform {
+      transpose: true,
+      ..SudokuTransform::identity()
+    }
+  }
+
+  // `rotate_clockwise` rotates the grid a quarter turn clockwise. Apply
+  // it two or three times to rotate further.
+  pub fn rotate_clockwise() -> SudokuTransform {
+    // The first column, read bottom to top, becomes the first row.
+    SudokuTransform {
+      transpose: true,
+      col_order: reversed_order(),
+      ..SudokuTransform::identity()
+    }
+  }
+
+  // `reflect_left_right` mirrors the grid, swapping the first and last
+  // columns.
+  pub fn reflect_left_right() -> SudokuTransform {
+    SudokuTransform {
+      col_order: reversed_order(),
+      ..SudokuTransform::identity()
+    }
+  }
+
+  // `reflect_top_bottom` mirrors the grid, swapping the first and last
+  // rows.
+  pub fn reflect_top_bottom() -> SudokuTransform {
+    SudokuTransform {
+      row_order: reversed_order(),
+      ..SudokuTransform::identity()
+    }
+  }
+
+  // `relabel` rewrites each value `v` as `relabeling[v.as_usize_idx()]`.
+  // Returns None unless the relabeling is a permutation of all the
+  // values.
+  pub fn relabel(
+    relabeling: &[SudokuValue],
+  ) -> Option<SudokuTransform> {
+    let relabeling_idxs: Vec<_> = relabeling
+      .iter()
+      .map(|value| value.as_usize_idx())
+      .collect();
+    if relabeling.len() != SUDOKU_DIM_USIZE
+      || !is_permutation(&relabeling_idxs)
+    {
+      return None;
+    }
+
+    let mut transform = SudokuTransform::identity();
+    transform.relabeling.copy_from_slice(relabeling);
+    Some(transform)
+  }
+
+  // `permute_bands` moves band `band_order[i]` to band `i`. Returns None
+  // unless `band_order` is a permutation of the bands.
+  pub fn permute_bands(
+    band_order: &[usize],
+  ) -> Option<SudokuTransform> {
+    let mut transform = SudokuTransform::identity();
+    transform.row_order = group_order(band_order)?;
+    Some(transform)
+  }
+
+  // `permute_stacks` moves stack `stack_order[i]` to stack `i`. Returns
+  // None unless `stack_order` is a permutation of the stacks.
+  pub fn permute_stacks(
+    stack_order: &[usize],
+  ) -> Option<SudokuTransform> {
+    let mut transform = SudokuTransform::identity();
+    transform.col_order = group_order(stack_order)?;
+    Some(transform)
+  }
+
+  // `permute_rows_in_band` reorders the rows of one band: row
+  // `row_order[i]` of the band moves to row `i` of the band. Returns
+  // None unless `row_order` is a permutation of the rows of a band.
+  pub fn permute_rows_in_band(
+    band_idx: usize,
+    row_order: &[usize],
+  ) -> Option<SudokuTransform> {
+    let mut transform = SudokuTransform::identity();
+    transform.row_order = order_within_group(band_idx, row_order)?;
+    Some(transform)
+  }
+
+  // `permute_cols_in_stack` reorders the columns of one stack: column
+  // `col_order[j]` of the stack moves to column `j` of the stack.
+  // Returns None unless `col_order` is a permutation of the columns of
+  // a stack.
+  pub fn permute_cols_in_stack(
+    stack_idx: usize,
+    col_order: &[usize],
+  ) -> Option<SudokuTransform> {
+    let mut transform = SudokuTransform::identity();
+    transform.col_order = order_within_group(stack_idx, col_order)?;
+    Some(transform)
+  }
+
+  // `random` draws a transform uniformly at random. Handy for serving
+  // disguised variants of the same puzzle.
+  pub fn random<R: Rng>(rng: &mut R) -> SudokuTransform {
+    let mut relabeling = SudokuTransform::identity().relabeling;
+    relabeling.shuffle(rng);
+
+    SudokuTransform::from_parts(
+      rng.gen(),
+      random_order(rng),
+      random_order(rng),
+      relabeling,
+    )
+  }
+
+  // `source_position` tells which position of the original grid ends
+  // up at `position` of the transformed grid.
+  pub fn source_position(
+    self,
+    position: SudokuPosition,
+  ) -> SudokuPosition {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    let row_idx = self.row_order[row_idx];
+    let col_idx = self.col_order[col_idx];
+
+    if self.transpose {
+      SudokuPosition::new(col_idx, row_idx)
+    } else {
+      SudokuPosition::new(row_idx, col_idx)
+    }
+  }
+
+  // `target_position` tells where `position` of the original grid ends
+  // up in the transformed grid. It undoes `source_position`.
+  pub fn target_position(
+    self,
+    position: SudokuPosition,
+  ) -> SudokuPosition {
+    let (row_idx, col_idx) = position.as_usize_pair();
+    let (row_idx, col_idx) = if self.transpose {
+      (col_idx as u8, row_idx as u8)
+    } else {
+      (row_idx as u8, col_idx as u8)
+    };
+
+    SudokuPosition::new(
+      position_in(&self.row_order, row_idx),
+      position_in(&self.col_order, col_idx),
+    )
+  }
+
+  // `apply_to_value` relabels a single value.
+  pub fn apply_to_value(self, value: SudokuValue) -> SudokuValue {
+    self.relabeling[value.as_usize_idx()]
+  }
+
+  // `apply_to_choice` moves the choice to its new position and relabels
+  // its value.
+  pub fn apply_to_choice(self, choice: SudokuChoice) -> SudokuChoice {
+    SudokuChoice::new(
+      self.target_position(choice.position),
+      self.apply_to_value(choice.value),
+    )
+  }
+
+  // `apply_to_choices` transforms each of the choices, for instance the
+  // givens of a puzzle or the choices of its solution.
+  pub fn apply_to_choices(
+    self,
+    choices: &[SudokuChoice],
+  ) -> Vec<SudokuChoice> {
+    choices
+      .iter()
+      .map(|&choice| self.apply_to_choice(choice))
+      .collect()
+  }
+
+  // `apply_to_grid` builds the transformed grid.
+  pub fn apply_to_grid(self, grid: &SudokuGrid) -> SudokuGrid {
+    SudokuGrid::from_choices(
+      &self.apply_to_choices(&grid.filled_choices()),
+    )
+  }
+
+  // `inverse` returns the transform which undoes this one.
+  pub fn inverse(self) -> SudokuTransform {
+    let mut relabeling = [SudokuValue::first(); SUDOKU_DIM_USIZE];
+    for value in SudokuValue::all() {
+      relabeling[self.apply_to_value(value).as_usize_idx()] = value;
+    }
+
+    // The inverse takes each position of the transformed grid back to
+    // where it came from.
+    SudokuTransform::from_position_map(
+      self.transpose,
+      |position| self.target_position(position),
+      relabeling,
+    )
+  }
+
+  // `then` returns the transform which applies `self` first and `next`
+  // second.
+  pub fn then(self, next: SudokuTransform) -> SudokuTransform {
+    let mut relabeling = [SudokuValue::first(); SUDOKU_DIM_USIZE];
+    for value in SudokuValue::all() {
+      relabeling[value.as_usize_idx()] =
+        next.apply_to_value(self.apply_to_value(value));
+    }
+
+    SudokuTransform::from_position_map(
+      self.transpose != next.transpose,
+      |position| self.source_position(next.source_position(position)),
+      relabeling,
+    )
+  }
+
+  // `from_position_map` recovers the row and column orders of a
+  // transform from its `source_position` function. The first column
+  // tells us the row order, and the first row tells us the column
+  // order.
+  fn from_position_map<F>(
+    transpose: bool,
+    source_position: F,
+    relabeling: [SudokuValue; SUDOKU_DIM_USIZE],
+  ) -> SudokuTransform
+  where
+    F: Fn(SudokuPosition) -> SudokuPosition,
+  {
+    let mut row_order = [0; SUDOKU_DIM_USIZE];
+    let mut col_order = [0; SUDOKU_DIM_USIZE];
+    for idx in 0..SUDOKU_DIM_USIZE {
+      let (row_source_row_idx, row_source_col_idx) =
+        source_position(SudokuPosition::new(idx as u8, 0))
+          .as_usize_pair();
+      let (col_source_row_idx, col_source_col_idx) =
+        source_position(SudokuPosition::new(0, idx as u8))
+          .as_usize_pair();
+
+      if transpose {
+        row_order[idx] = row_source_col_idx as u8;
+        col_order[idx] = col_source_row_idx as u8;
+      } else {
+        row_order[idx] = row_source_row_idx as u8;
+        col_order[idx] = col_source_col_idx as u8;
+      }
+    }
+
+    SudokuTransform::from_parts(
+      transpose, row_order, col_order, relabeling,
+    )
+  }
+}
+
+// `position_in` finds where `idx` appears in the given order.
+fn position_in(order: &[u8; SUDOKU_DIM_USIZE], idx: u8) -> u8 {
+  order.iter().position(|&entry| entry == idx).unwrap() as u8
+}
+
+// `is_permutation` checks that `idxs` holds each of 0 through
+// `idxs.len() - 1` exactly once.
+fn is_permutation(idxs: &[usize]) -> bool {
+  let mut is_seen = vec![false; idxs.len()];
+  for &idx in idxs {
+    if idx >= idxs.len() || is_seen[idx] {
+      return false;
+    }
+    is_seen[idx] = true;
+  }
+
+  true
+}
+
+// `is_valid_order` checks that a row (or column) order is a permutation
+// which keeps each band (or stack) together.
+fn is_valid_order(order: &[u8; SUDOKU_DIM_USIZE]) -> bool {
+  let idxs: Vec<_> = order.iter().map(|&idx| idx as usize).collect();
+  if !is_permutation(&idxs) {
+    return false;
+  }
+
+  idxs.chunks(SUDOKU_DIM_SQRT_USIZE).all(|group| {
+    group.iter().all(|&idx| {
+      idx / SUDOKU_DIM_SQRT_USIZE == group[0] / SUDOKU_DIM_SQRT_USIZE
+    })
+  })
+}
+
+fn identity_order() -> [u8; SUDOKU_DIM_USIZE] {
+  let mut order = [0; SUDOKU_DIM_USIZE];
+  for (idx, entry) in order.iter_mut().enumerate() {
+    *entry = idx as u8;
+  }
+
+  order
+}
+
+fn reversed_order() -> [u8; SUDOKU_DIM_USIZE] {
+  let mut order = [0; SUDOKU_DIM_USIZE];
+  for (idx, entry) in order.iter_mut().enumerate() {
+    *entry = (SUDOKU_DIM_USIZE - 1 - idx) as u8;
+  }
+
+  order
+}
+
+// `group_order` builds the row (or column) order which moves whole
+// bands (or stacks) around.
+fn group_order(
+  group_order: &[usize],
+) -> Option<[u8; SUDOKU_DIM_USIZE]> {
+  if group_order.len() != SUDOKU_DIM_SQRT_USIZE
+    || !is_permutation(group_order)
+  {
+    return None;
+  }
+
+  let mut order = [0; SUDOKU_DIM_USIZE];
+  for (idx, entry) in order.iter_mut().enumerate() {
+    let group_idx = group_order[idx / SUDOKU_DIM_SQRT_USIZE];
+    *entry = (group_idx * SUDOKU_DIM_SQRT_USIZE
+      + idx % SUDOKU_DIM_SQRT_USIZE) as u8;
+  }
+
+  Some(order)
+}
+
+// `order_within_group` builds the row (or column) order which reorders
+// the rows of a single band (or the columns of a single stack).
+fn order_within_group(
+  group_idx: usize,
+  inner_order: &[usize],
+) -> Option<[u8; SUDOKU_DIM_USIZE]> {
+  if group_idx >= SUDOKU_DIM_SQRT_USIZE
+    || inner_order.len() != SUDOKU_DIM_SQRT_USIZE
+    || !is_permutation(inner_order)
+  {
+    return None;
+  }
+
+  let mut order = identity_order();
+  let group_start = group_idx * SUDOKU_DIM_SQRT_USIZE;
+  for (offset, &inner_idx) in inner_order.iter().enumerate() {
+    order[group_start + offset] = (group_start + inner_idx) as u8;
+  }
+
+  Some(order)
+}
+
+// `random_order` shuffles the bands (or stacks), and then the rows
+// within each band (or the columns within each stack).
+fn random_order<R: Rng>(rng: &mut R) -> [u8; SUDOKU_DIM_USIZE] {
+  let mut group_idxs: Vec<_> = (0..SUDOKU_DIM_SQRT_USIZE).collect();
+  group_idxs.shuffle(rng);
+
+  let mut order = [0; SUDOKU_DIM_USIZE];
+  for (group_slot_idx, &group_idx) in group_idxs.iter().enumerate() {
+    let mut inner_idxs: Vec<_> = (0..SUDOKU_DIM_SQRT_USIZE).collect();
+    inner_idxs.shuffle(rng);
+
+    for (offset, &inner_idx) in inner_idxs.iter().enumerate() {
+      order[group_slot_idx * SUDOKU_DIM_SQRT_USIZE + offset] =
+        (group_idx * SUDOKU_DIM_SQRT_USIZE + inner_idx) as u8;
+    }
+  }
+
+  order
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::rngs::SmallRng;
+  use rand::SeedableRng;
+  use solver::SudokuSolver;
+  use test_util::random_solution;
+
+  #[test]
+  fn relabel_rejects_the_wrong_number_of_values() {
+    let values = SudokuValue::all();
+    assert!(SudokuTransform::relabel(&values).is_some());
+    assert!(SudokuTransform::relabel(&values[..15]).is_none());
+    assert!(SudokuTransform::relabel(&[]).is_none());
+
+    let mut too_many_values = values.clone();
+    too_many_values.push(SudokuValue::first());
+    assert!(SudokuTransform::relabel(&too_many_values).is_none());
+  }
+
+  #[test]
+  fn relabel_rejects_repeated_values() {
+    let mut values = SudokuValue::all();
+    values[1] = values[0];
+    assert!(SudokuTransform::relabel(&values).is_none());
+  }
+
+  #[test]
+  fn new_rejects_orders_that_split_bands() {
+    let identity = SudokuTransform::identity();
+    let mut row_order = identity_order();
+    row_order.swap(3, 4);
+    assert!(SudokuTransform::new(
+      false,
+      row_order,
+      identity_order(),
+      identity.relabeling,
+    )
+    .is_none());
+    assert!(SudokuTransform::permute_bands(&[0, 1, 2]).is_none());
+    assert!(
+      SudokuTransform::permute_rows_in_band(4, &[0, 1, 2, 3]).is_none()
+    );
+  }
+
+  #[test]
+  fn inverse_undoes_the_transform() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let grid = random_solution(&mut rng);
+    for _ in 0..10 {
+      let transform = SudokuTransform::random(&mut rng);
+      let transformed_grid = transform.apply_to_grid(&grid);
+      assert_eq!(
+        transform.inverse().apply_to_grid(&transformed_grid),
+        grid
+      );
+      assert_eq!(
+        transform.then(transform.inverse()),
+        SudokuTransform::identity()
+      );
+    }
+  }
+
+  #[test]
+  fn then_applies_both_transforms() {
+    let mut rng = SmallRng::seed_from_u64(2);
+    let grid = random_solution(&mut rng);
+    let first = SudokuTransform::random(&mut rng);
+    let second = SudokuTransform::random(&mut rng);
+    assert_eq!(
+      first.then(second).apply_to_grid(&grid),
+      second.apply_to_grid(&first.apply_to_grid(&grid))
+    );
+  }
+
+  #[test]
+  fn four_quarter_turns_make_the_identity() {
+    let quarter_turn = SudokuTransform::rotate_clockwise();
+    let full_turn = quarter_turn
+      .then(quarter_turn)
+      .then(quarter_turn)
+      .then(quarter_turn);
+    assert_eq!(full_turn, SudokuTransform::identity());
+
+    // The top left corner moves to the top right corner.
+    assert_eq!(
+      quarter_turn.target_position(SudokuPosition::new(0, 0)),
+      SudokuPosition::new(0, 15)
+    );
+  }
+
+  #[test]
+  fn transformed_solutions_stay_valid() {
+    let mut rng = SmallRng::seed_from_u64(3);
+    let grid = random_solution(&mut rng);
+    let transformed_grid =
+      SudokuTransform::random(&mut rng).apply_to_grid(&grid);
+    let choices = transformed_grid.filled_choices();
+    assert_eq!(choices.len(), SUDOKU_DIM_USIZE * SUDOKU_DIM_USIZE);
+    assert!(SudokuSolver::new(&choices).is_some());
+  }
+}